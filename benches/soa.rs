@@ -1,3 +1,5 @@
+#![allow(clippy::needless_return)]
+
 use soa_derive::StructOfArray;
 
 use bencher::{Bencher, benchmark_group, benchmark_main};
@@ -35,7 +37,8 @@ impl Small {
     }
 }
 
-#[derive(StructOfArray)]
+#[derive(Clone, StructOfArray)]
+#[soa_derive = "Clone"]
 pub struct Big {
     position: (f64, f64, f64),
     velocity: (f64, f64, f64),
@@ -166,13 +169,49 @@ fn soa_big_do_work_10000(bencher: &mut Bencher) {
     })
 }
 
+fn aos_big_retain_10000(bencher: &mut Bencher) {
+    let mut vec = Big::aos_vec(10000);
+    for (i, v) in vec.iter_mut().enumerate() {
+        v.id = i;
+    }
+    bencher.iter(||{
+        let mut vec = vec.clone();
+        vec.retain(|v| v.id % 3 != 0);
+        vec
+    })
+}
+
+fn soa_big_retain_10000(bencher: &mut Bencher) {
+    let mut vec = Big::soa_vec(10000);
+    for (i, id) in vec.id.iter_mut().enumerate() {
+        *id = i;
+    }
+    bencher.iter(||{
+        let mut vec = vec.clone();
+        vec.retain(|v| v.id % 3 != 0);
+        vec
+    })
+}
+
+fn soa_big_retain_by_id_10000(bencher: &mut Bencher) {
+    let mut vec = Big::soa_vec(10000);
+    for (i, id) in vec.id.iter_mut().enumerate() {
+        *id = i;
+    }
+    bencher.iter(||{
+        let mut vec = vec.clone();
+        vec.retain_by_id(|id| id % 3 != 0);
+        vec
+    })
+}
 
 benchmark_group!(aos,
     aos_small_push, aos_big_push, aos_small_do_work_10000, aos_big_do_work_1000,
-    aos_big_do_work_10000
+    aos_big_do_work_10000, aos_big_retain_10000
 );
 benchmark_group!(soa,
     soa_small_push, soa_big_push, soa_small_do_work_10000, soa_big_do_work_1000,
-    soa_big_do_work_10000
+    soa_big_do_work_10000, soa_big_retain_10000,
+    soa_big_retain_by_id_10000
 );
 benchmark_main!(soa, aos);
@@ -0,0 +1,19 @@
+use std::env;
+use std::process::Command;
+
+// Detect nightly compilers, which are required by the tests using
+// `#![feature(portable_simd)]`. The `nightly` feature alone is not enough,
+// since `--all-features` also enables it on stable compilers.
+fn main() {
+    println!("cargo:rustc-check-cfg=cfg(nightly_compiler)");
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| String::from("rustc"));
+    let version = match Command::new(rustc).arg("--version").output() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).into_owned(),
+        Err(_) => return,
+    };
+
+    if version.contains("nightly") || version.contains("-dev") {
+        println!("cargo:rustc-cfg=nightly_compiler");
+    }
+}
@@ -0,0 +1,434 @@
+use proc_macro2::{Span, TokenStream};
+use syn::{Ident, Index, WherePredicate, parse_quote};
+use quote::TokenStreamExt;
+use quote::quote;
+
+use crate::input::Input;
+
+/// Generate the fixed capacity array type for `#[soa(array)]`. Each column is
+/// stored inline in a `[MaybeUninit<T>; N]`, and the first `len` elements of
+/// all columns are initialized. The slice types are used as views into the
+/// array.
+pub fn derive(input: &Input) -> TokenStream {
+    if !input.array {
+        return TokenStream::new();
+    }
+
+    let name = &input.name;
+    let visibility = &input.visibility;
+    let allow_dead_code = &input.allow_dead_code();
+    let array_name = &input.array_name();
+    let slice_name = &input.slice_name();
+    let slice_mut_name = &input.slice_mut_name();
+    let ptr_name = &input.ptr_name();
+    let ptr_mut_name = &input.ptr_mut_name();
+    let iter_mod = Ident::new(&format!("__detail_iter_{}", name.to_string().to_lowercase()), Span::call_site());
+
+    let doc_url = format!("[`{0}`](struct.{0}.html)", name);
+    let vec_name_str = format!("Vec<{}>", name);
+
+    let generics = &input.generics;
+    let where_clause = &generics.where_clause;
+    let generics_array = &input.array_generics(None);
+    let (impl_generics, ty_generics_array, _) = generics_array.split_for_impl();
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let generics_elided = &input.generics_with_lifetime("'_");
+    let (_, ty_generics_elided, _) = generics_elided.split_for_impl();
+    let generics_a = &input.generics_with_lifetime("'a");
+    let (_, ty_generics_a, _) = generics_a.split_for_impl();
+    let generics_array_a = &input.array_generics(Some("'a"));
+    let (impl_generics_a, _, _) = generics_array_a.split_for_impl();
+
+    let fields_names = &input.fields_names();
+    let fields_vars = &input.fields_vars();
+    let fields_types = &input.fields_types();
+    let columns = &(0..fields_names.len()).map(Index::from).collect::<Vec<_>>();
+    let destructure_value = input.destructure(&quote!(value), &quote!(()));
+
+    let mut generated = quote! {
+        /// A fixed capacity analog to `
+        #[doc = #vec_name_str]
+        /// ` with Struct of Array (SoA) layout, storing up to `N` values of
+        #[doc = #doc_url]
+        /// inline, without any heap allocation.
+        #allow_dead_code
+        #visibility struct #array_name #generics_array #where_clause {
+            columns: (#([::std::mem::MaybeUninit<#fields_types>; N],)*),
+            len: usize,
+        }
+
+        #allow_dead_code
+        impl #impl_generics #array_name #ty_generics_array #where_clause {
+            /// Create a new empty array
+            pub fn new() -> #array_name #ty_generics_array {
+                #array_name {
+                    // SAFETY: an array of `MaybeUninit` does not need to be
+                    // initialized
+                    columns: unsafe { ::std::mem::MaybeUninit::uninit().assume_init() },
+                    len: 0,
+                }
+            }
+
+            /// Get the maximal number of elements in this array, *i.e.* `N`
+            pub fn capacity(&self) -> usize {
+                N
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::len()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.len),
+            /// all the fields share the same length.
+            pub fn len(&self) -> usize {
+                self.len
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::is_empty()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.is_empty),
+            /// all the fields share the same length.
+            pub fn is_empty(&self) -> bool {
+                self.len == 0
+            }
+
+            /// Check if this array contains `N` elements, in which case no
+            /// other element can be added.
+            pub fn is_full(&self) -> bool {
+                self.len == N
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::push()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.push).
+            ///
+            /// # Panics
+            ///
+            /// This function panics if the array is already full.
+            pub fn push(&mut self, value: #name #ty_generics) {
+                if self.try_push(value).is_err() {
+                    panic!("can not push to a full array with capacity {}", N);
+                }
+            }
+
+            /// Try to add `value` at the end of this array, giving it back
+            /// if the array is already full.
+            pub fn try_push(&mut self, value: #name #ty_generics) -> Result<(), #name #ty_generics> {
+                if self.is_full() {
+                    return Err(value);
+                }
+                #destructure_value
+                #(self.columns.#columns[self.len] = ::std::mem::MaybeUninit::new(#fields_vars);)*
+                self.len += 1;
+                Ok(())
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::pop()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.pop).
+            pub fn pop(&mut self) -> Option<#name #ty_generics> {
+                if self.is_empty() {
+                    None
+                } else {
+                    self.len -= 1;
+                    unsafe {
+                        Some(self.as_mut_ptr().add(self.len).read())
+                    }
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::insert()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.insert).
+            ///
+            /// # Panics
+            ///
+            /// This function panics if the array is already full.
+            pub fn insert(&mut self, index: usize, element: #name #ty_generics) {
+                let len = self.len;
+                assert!(index <= len, "insertion index (is {}) should be <= len (is {})", index, len);
+                assert!(len < N, "can not insert in a full array with capacity {}", N);
+                unsafe {
+                    let ptr = self.as_mut_ptr();
+                    #(::std::ptr::copy(ptr.#fields_names.add(index), ptr.#fields_names.add(index + 1), len - index);)*
+                    ptr.add(index).write(element);
+                }
+                self.len = len + 1;
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::remove()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.remove).
+            pub fn remove(&mut self, index: usize) -> #name #ty_generics {
+                let len = self.len;
+                assert!(index < len, "removal index (is {}) should be < len (is {})", index, len);
+                unsafe {
+                    let ptr = self.as_mut_ptr();
+                    let value = ptr.add(index).read();
+                    #(::std::ptr::copy(ptr.#fields_names.add(index + 1), ptr.#fields_names.add(index), len - index - 1);)*
+                    self.len = len - 1;
+                    value
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::swap_remove()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.swap_remove).
+            pub fn swap_remove(&mut self, index: usize) -> #name #ty_generics {
+                let len = self.len;
+                assert!(index < len, "swap_remove index (is {}) should be < len (is {})", index, len);
+                unsafe {
+                    let ptr = self.as_mut_ptr();
+                    let value = ptr.add(index).read();
+                    #(::std::ptr::copy(ptr.#fields_names.add(len - 1), ptr.#fields_names.add(index), 1);)*
+                    self.len = len - 1;
+                    value
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::truncate()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.truncate)
+            /// truncating all fields.
+            pub fn truncate(&mut self, len: usize) {
+                if len >= self.len {
+                    return;
+                }
+
+                let old_len = self.len;
+                // leak the remaining values if one of the destructors panics
+                self.len = len;
+                unsafe {
+                    let ptr = self.as_mut_ptr();
+                    #(
+                        ::std::ptr::drop_in_place(::std::ptr::slice_from_raw_parts_mut(ptr.#fields_names.add(len), old_len - len));
+                    )*
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::clear()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.clear).
+            pub fn clear(&mut self) {
+                self.truncate(0);
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_slice()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.as_slice).
+            pub fn as_slice(&self) -> #slice_name #ty_generics_elided {
+                let ptr = self.as_ptr();
+                unsafe {
+                    #slice_name {
+                        #(#fields_names: ::std::slice::from_raw_parts(ptr.#fields_names, self.len),)*
+                    }
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_mut_slice()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.as_mut_slice).
+            pub fn as_mut_slice(&mut self) -> #slice_mut_name #ty_generics_elided {
+                let ptr = self.as_mut_ptr();
+                unsafe {
+                    #slice_mut_name {
+                        #(#fields_names: ::std::slice::from_raw_parts_mut(ptr.#fields_names, self.len),)*
+                    }
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_ptr()`](https://doc.rust-lang.org/std/struct.Vec.html#method.as_ptr).
+            pub fn as_ptr(&self) -> #ptr_name #ty_generics {
+                #ptr_name {
+                    #(#fields_names: self.columns.#columns.as_ptr().cast::<#fields_types>(),)*
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_mut_ptr()`](https://doc.rust-lang.org/std/struct.Vec.html#method.as_mut_ptr).
+            pub fn as_mut_ptr(&mut self) -> #ptr_mut_name #ty_generics {
+                #ptr_mut_name {
+                    #(#fields_names: self.columns.#columns.as_mut_ptr().cast::<#fields_types>(),)*
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::get<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.get).
+            pub fn get<'a, I>(&'a self, index: I) -> Option<I::RefOutput>
+            where
+                I: ::soa_derive::SoAIndex<&'a #array_name #ty_generics_array>
+            {
+                index.get(self)
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::get_unchecked<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.get_unchecked).
+            pub unsafe fn get_unchecked<'a, I>(&'a self, index: I) -> I::RefOutput
+            where
+                I: ::soa_derive::SoAIndex<&'a #array_name #ty_generics_array>
+            {
+                index.get_unchecked(self)
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::index<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.index).
+            pub fn index<'a, I>(&'a self, index: I) -> I::RefOutput
+            where
+                I: ::soa_derive::SoAIndex<&'a #array_name #ty_generics_array>
+            {
+                index.index(self)
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::get_mut<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.get_mut).
+            pub fn get_mut<'a, I>(&'a mut self, index: I) -> Option<I::MutOutput>
+            where
+                I: ::soa_derive::SoAIndexMut<&'a mut #array_name #ty_generics_array>
+            {
+                index.get_mut(self)
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::get_unchecked_mut<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.get_unchecked_mut).
+            pub unsafe fn get_unchecked_mut<'a, I>(&'a mut self, index: I) -> I::MutOutput
+            where
+                I: ::soa_derive::SoAIndexMut<&'a mut #array_name #ty_generics_array>
+            {
+                index.get_unchecked_mut(self)
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::index_mut<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.index_mut).
+            pub fn index_mut<'a, I>(&'a mut self, index: I) -> I::MutOutput
+            where
+                I: ::soa_derive::SoAIndexMut<&'a mut #array_name #ty_generics_array>
+            {
+                index.index_mut(self)
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::iter()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.iter).
+            pub fn iter(&self) -> #iter_mod::Iter #ty_generics_elided {
+                self.as_slice().into_iter()
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::iter_mut()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.iter_mut).
+            pub fn iter_mut(&mut self) -> #iter_mod::IterMut #ty_generics_elided {
+                self.as_mut_slice().into_iter()
+            }
+        }
+
+        impl #impl_generics ::std::default::Default for #array_name #ty_generics_array #where_clause {
+            fn default() -> Self {
+                #array_name::new()
+            }
+        }
+
+        impl #impl_generics Drop for #array_name #ty_generics_array #where_clause {
+            fn drop(&mut self) {
+                self.truncate(0);
+            }
+        }
+
+        impl #impl_generics_a IntoIterator for &'a #array_name #ty_generics_array #where_clause {
+            type Item = <#slice_name #ty_generics_a as IntoIterator>::Item;
+            type IntoIter = <#slice_name #ty_generics_a as IntoIterator>::IntoIter;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.as_slice().into_iter()
+            }
+        }
+
+        impl #impl_generics_a IntoIterator for &'a mut #array_name #ty_generics_array #where_clause {
+            type Item = <#slice_mut_name #ty_generics_a as IntoIterator>::Item;
+            type IntoIter = <#slice_mut_name #ty_generics_a as IntoIterator>::IntoIter;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.as_mut_slice().into_iter()
+            }
+        }
+    };
+
+    let with_bounds = |bound: TokenStream| {
+        let mut generics = generics_array.clone();
+        let predicates = fields_types.iter().map(|ty| -> WherePredicate { parse_quote!(#ty: #bound) });
+        generics.make_where_clause().predicates.extend(predicates);
+        generics.where_clause
+    };
+
+    // the traits derived for the vector are implemented manually for the
+    // array, since its fields are partially initialized
+    if input.attrs.vec_derives("Clone") {
+        let clone_where_clause = with_bounds(quote!(::std::clone::Clone));
+        generated.append_all(quote! {
+            impl #impl_generics ::std::clone::Clone for #array_name #ty_generics_array #clone_where_clause {
+                fn clone(&self) -> Self {
+                    let mut array = #array_name::new();
+                    let slice = self.as_slice();
+                    for i in 0..self.len {
+                        #(array.columns.#columns[i] = ::std::mem::MaybeUninit::new(::std::clone::Clone::clone(&slice.#fields_names[i]));)*
+                        array.len += 1;
+                    }
+                    array
+                }
+            }
+        });
+    }
+
+    if input.attrs.vec_derives("Debug") {
+        let debug_where_clause = with_bounds(quote!(::std::fmt::Debug));
+        let debug = if input.is_tuple() {
+            quote! {
+                f.debug_tuple(stringify!(#array_name))
+                    #(.field(&slice.#fields_names))*
+                    .finish()
+            }
+        } else {
+            let fields_names_str = fields_names.iter().map(|field| quote!(#field).to_string());
+            quote! {
+                f.debug_struct(stringify!(#array_name))
+                    #(.field(#fields_names_str, &slice.#fields_names))*
+                    .finish()
+            }
+        };
+        generated.append_all(quote! {
+            impl #impl_generics ::std::fmt::Debug for #array_name #ty_generics_array #debug_where_clause {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    let slice = self.as_slice();
+                    #debug
+                }
+            }
+        });
+    }
+
+    if input.attrs.vec_derives("PartialEq") {
+        let partial_eq_where_clause = with_bounds(quote!(::std::cmp::PartialEq));
+        generated.append_all(quote! {
+            impl #impl_generics ::std::cmp::PartialEq for #array_name #ty_generics_array #partial_eq_where_clause {
+                fn eq(&self, other: &Self) -> bool {
+                    let (slice, other) = (self.as_slice(), other.as_slice());
+                    #(slice.#fields_names == other.#fields_names)&&*
+                }
+            }
+        });
+    }
+
+    if input.attrs.vec_derives("Eq") {
+        let eq_where_clause = with_bounds(quote!(::std::cmp::Eq));
+        generated.append_all(quote! {
+            impl #impl_generics ::std::cmp::Eq for #array_name #ty_generics_array #eq_where_clause {}
+        });
+    }
+
+    return generated;
+}
@@ -0,0 +1,466 @@
+use proc_macro2::{Span, TokenStream};
+use syn::{Ident, Visibility, parse_quote};
+use quote::quote;
+
+use crate::input::Input;
+
+pub fn derive(input: &Input) -> TokenStream {
+    let name = &input.name;
+    let visibility = &input.visibility;
+    let allow_dead_code = &input.allow_dead_code();
+    // the iterators and functions are defined inside a module, and need to be
+    // visible from the parent module for private structs
+    let detail_visibility = &match *visibility {
+        Visibility::Inherited => quote!(pub(super)),
+        ref visibility => quote!(#visibility),
+    };
+    let detail_mod = Ident::new(&format!("__detail_chunks_{}", name.to_string().to_lowercase()), Span::call_site());
+    let slice_name = &input.slice_name();
+    let slice_mut_name = &input.slice_mut_name();
+    let ptr_mut_name = &input.ptr_mut_name();
+
+    let slice_doc_url = format!("[`{0}`](struct.{0}.html)", slice_name);
+    let slice_mut_doc_url = format!("[`{0}`](struct.{0}.html)", slice_mut_name);
+
+    let generics = &input.generics;
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+    let generics_elided = &input.generics_with_lifetime("'_");
+    let (_, ty_generics_elided, _) = generics_elided.split_for_impl();
+    let generics_a = &input.generics_outliving("'a");
+    let (impl_generics_a, ty_generics_a, _) = generics_a.split_for_impl();
+
+    // `ChunksMut` stores raw pointers, and is only `Send`/`Sync` if the
+    // corresponding mutable slice is
+    let mut send_generics = generics_a.clone();
+    send_generics.make_where_clause().predicates.push(parse_quote!(#slice_mut_name #ty_generics_a: Send));
+    let send_where_clause = &send_generics.where_clause;
+    let mut sync_generics = generics_a.clone();
+    sync_generics.make_where_clause().predicates.push(parse_quote!(#slice_mut_name #ty_generics_a: Sync));
+    let sync_where_clause = &sync_generics.where_clause;
+
+    quote! {
+        #allow_dead_code
+        mod #detail_mod {
+            use super::*;
+            use std::iter;
+            use std::marker::PhantomData;
+
+            /// An iterator over a
+            #[doc = #slice_doc_url]
+            /// in non-overlapping chunks, starting at the beginning of the slice.
+            #[allow(missing_debug_implementations)]
+            #detail_visibility struct Chunks #generics_a #where_clause {
+                slice: #slice_name #ty_generics_a,
+                chunk_size: usize,
+            }
+
+            impl #impl_generics_a Iterator for Chunks #ty_generics_a #where_clause {
+                type Item = #slice_name #ty_generics_a;
+
+                #[inline]
+                fn next(&mut self) -> Option<#slice_name #ty_generics_a> {
+                    if self.slice.is_empty() {
+                        return None;
+                    }
+                    let mid = ::std::cmp::min(self.chunk_size, self.slice.len());
+                    let (chunk, rest) = self.slice.split_at(mid);
+                    self.slice = rest;
+                    Some(chunk)
+                }
+
+                #[inline]
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    let len = self.slice.len();
+                    let count = len / self.chunk_size + usize::from(len % self.chunk_size != 0);
+                    (count, Some(count))
+                }
+            }
+
+            impl #impl_generics_a DoubleEndedIterator for Chunks #ty_generics_a #where_clause {
+                #[inline]
+                fn next_back(&mut self) -> Option<#slice_name #ty_generics_a> {
+                    if self.slice.is_empty() {
+                        return None;
+                    }
+                    let remainder = self.slice.len() % self.chunk_size;
+                    let size = if remainder == 0 { self.chunk_size } else { remainder };
+                    let (rest, chunk) = self.slice.split_at(self.slice.len() - size);
+                    self.slice = rest;
+                    Some(chunk)
+                }
+            }
+
+            impl #impl_generics_a ExactSizeIterator for Chunks #ty_generics_a #where_clause {}
+            impl #impl_generics_a iter::FusedIterator for Chunks #ty_generics_a #where_clause {}
+
+            /// An iterator over a
+            #[doc = #slice_doc_url]
+            /// in non-overlapping chunks of exactly `chunk_size` elements,
+            /// starting at the beginning of the slice. The last elements
+            /// which do not fill a chunk are available with `remainder`.
+            #[allow(missing_debug_implementations)]
+            #detail_visibility struct ChunksExact #generics_a #where_clause {
+                slice: #slice_name #ty_generics_a,
+                remainder: #slice_name #ty_generics_a,
+                chunk_size: usize,
+            }
+
+            impl #impl_generics_a ChunksExact #ty_generics_a #where_clause {
+                /// Get the last elements of the slice, which do not fill a
+                /// whole chunk.
+                #detail_visibility fn remainder(&self) -> #slice_name #ty_generics_a {
+                    self.remainder
+                }
+            }
+
+            impl #impl_generics_a Iterator for ChunksExact #ty_generics_a #where_clause {
+                type Item = #slice_name #ty_generics_a;
+
+                #[inline]
+                fn next(&mut self) -> Option<#slice_name #ty_generics_a> {
+                    if self.slice.len() < self.chunk_size {
+                        return None;
+                    }
+                    let (chunk, rest) = self.slice.split_at(self.chunk_size);
+                    self.slice = rest;
+                    Some(chunk)
+                }
+
+                #[inline]
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    let count = self.slice.len() / self.chunk_size;
+                    (count, Some(count))
+                }
+            }
+
+            impl #impl_generics_a DoubleEndedIterator for ChunksExact #ty_generics_a #where_clause {
+                #[inline]
+                fn next_back(&mut self) -> Option<#slice_name #ty_generics_a> {
+                    if self.slice.len() < self.chunk_size {
+                        return None;
+                    }
+                    let (rest, chunk) = self.slice.split_at(self.slice.len() - self.chunk_size);
+                    self.slice = rest;
+                    Some(chunk)
+                }
+            }
+
+            impl #impl_generics_a ExactSizeIterator for ChunksExact #ty_generics_a #where_clause {}
+            impl #impl_generics_a iter::FusedIterator for ChunksExact #ty_generics_a #where_clause {}
+
+            /// An iterator over a
+            #[doc = #slice_doc_url]
+            /// in non-overlapping chunks, starting at the end of the slice.
+            #[allow(missing_debug_implementations)]
+            #detail_visibility struct RChunks #generics_a #where_clause {
+                slice: #slice_name #ty_generics_a,
+                chunk_size: usize,
+            }
+
+            impl #impl_generics_a Iterator for RChunks #ty_generics_a #where_clause {
+                type Item = #slice_name #ty_generics_a;
+
+                #[inline]
+                fn next(&mut self) -> Option<#slice_name #ty_generics_a> {
+                    if self.slice.is_empty() {
+                        return None;
+                    }
+                    let mid = self.slice.len().saturating_sub(self.chunk_size);
+                    let (rest, chunk) = self.slice.split_at(mid);
+                    self.slice = rest;
+                    Some(chunk)
+                }
+
+                #[inline]
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    let len = self.slice.len();
+                    let count = len / self.chunk_size + usize::from(len % self.chunk_size != 0);
+                    (count, Some(count))
+                }
+            }
+
+            impl #impl_generics_a DoubleEndedIterator for RChunks #ty_generics_a #where_clause {
+                #[inline]
+                fn next_back(&mut self) -> Option<#slice_name #ty_generics_a> {
+                    if self.slice.is_empty() {
+                        return None;
+                    }
+                    let remainder = self.slice.len() % self.chunk_size;
+                    let size = if remainder == 0 { self.chunk_size } else { remainder };
+                    let (chunk, rest) = self.slice.split_at(size);
+                    self.slice = rest;
+                    Some(chunk)
+                }
+            }
+
+            impl #impl_generics_a ExactSizeIterator for RChunks #ty_generics_a #where_clause {}
+            impl #impl_generics_a iter::FusedIterator for RChunks #ty_generics_a #where_clause {}
+
+            /// An iterator over overlapping sub-slices of length `size` of a
+            #[doc = #slice_doc_url]
+            #[allow(missing_debug_implementations)]
+            #detail_visibility struct Windows #generics_a #where_clause {
+                slice: #slice_name #ty_generics_a,
+                size: usize,
+            }
+
+            impl #impl_generics_a Iterator for Windows #ty_generics_a #where_clause {
+                type Item = #slice_name #ty_generics_a;
+
+                #[inline]
+                fn next(&mut self) -> Option<#slice_name #ty_generics_a> {
+                    if self.slice.len() < self.size {
+                        return None;
+                    }
+                    let window = self.slice.split_at(self.size).0;
+                    self.slice = self.slice.split_at(1).1;
+                    Some(window)
+                }
+
+                #[inline]
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    let count = (self.slice.len() + 1).saturating_sub(self.size);
+                    (count, Some(count))
+                }
+            }
+
+            impl #impl_generics_a DoubleEndedIterator for Windows #ty_generics_a #where_clause {
+                #[inline]
+                fn next_back(&mut self) -> Option<#slice_name #ty_generics_a> {
+                    let len = self.slice.len();
+                    if len < self.size {
+                        return None;
+                    }
+                    let window = self.slice.split_at(len - self.size).1;
+                    self.slice = self.slice.split_at(len - 1).0;
+                    Some(window)
+                }
+            }
+
+            impl #impl_generics_a ExactSizeIterator for Windows #ty_generics_a #where_clause {}
+            impl #impl_generics_a iter::FusedIterator for Windows #ty_generics_a #where_clause {}
+
+            impl #impl_generics_a #slice_name #ty_generics_a #where_clause {
+                /// Similar to [`slice::chunks()`](https://doc.rust-lang.org/std/primitive.slice.html#method.chunks).
+                ///
+                /// # Panics
+                ///
+                /// This function panics if `chunk_size` is 0.
+                #detail_visibility fn chunks(&self, chunk_size: usize) -> Chunks #ty_generics_a {
+                    assert!(chunk_size != 0, "chunk size must be non-zero");
+                    Chunks {
+                        slice: *self,
+                        chunk_size: chunk_size,
+                    }
+                }
+
+                /// Similar to [`slice::chunks_exact()`](https://doc.rust-lang.org/std/primitive.slice.html#method.chunks_exact).
+                ///
+                /// # Panics
+                ///
+                /// This function panics if `chunk_size` is 0.
+                #detail_visibility fn chunks_exact(&self, chunk_size: usize) -> ChunksExact #ty_generics_a {
+                    assert!(chunk_size != 0, "chunk size must be non-zero");
+                    let len = self.len() - self.len() % chunk_size;
+                    let (slice, remainder) = self.split_at(len);
+                    ChunksExact {
+                        slice: slice,
+                        remainder: remainder,
+                        chunk_size: chunk_size,
+                    }
+                }
+
+                /// Similar to [`slice::rchunks()`](https://doc.rust-lang.org/std/primitive.slice.html#method.rchunks).
+                ///
+                /// # Panics
+                ///
+                /// This function panics if `chunk_size` is 0.
+                #detail_visibility fn rchunks(&self, chunk_size: usize) -> RChunks #ty_generics_a {
+                    assert!(chunk_size != 0, "chunk size must be non-zero");
+                    RChunks {
+                        slice: *self,
+                        chunk_size: chunk_size,
+                    }
+                }
+
+                /// Similar to [`slice::windows()`](https://doc.rust-lang.org/std/primitive.slice.html#method.windows).
+                ///
+                /// # Panics
+                ///
+                /// This function panics if `size` is 0.
+                #detail_visibility fn windows(&self, size: usize) -> Windows #ty_generics_a {
+                    assert!(size != 0, "window size must be non-zero");
+                    Windows {
+                        slice: *self,
+                        size: size,
+                    }
+                }
+            }
+
+            /// An iterator over a
+            #[doc = #slice_mut_doc_url]
+            /// in non-overlapping mutable chunks, starting at the beginning
+            /// of the slice.
+            #[allow(missing_debug_implementations)]
+            #detail_visibility struct ChunksMut #generics_a #where_clause {
+                // the chunks are created from raw pointers since a mutable
+                // slice can not be split while keeping its full lifetime
+                pub(super) ptr: #ptr_mut_name #ty_generics,
+                pub(super) len: usize,
+                pub(super) chunk_size: usize,
+                pub(super) marker: PhantomData<#slice_mut_name #ty_generics_a>,
+            }
+
+            unsafe impl #impl_generics_a Send for ChunksMut #ty_generics_a #send_where_clause {}
+            unsafe impl #impl_generics_a Sync for ChunksMut #ty_generics_a #sync_where_clause {}
+
+            impl #impl_generics_a Iterator for ChunksMut #ty_generics_a #where_clause {
+                type Item = #slice_mut_name #ty_generics_a;
+
+                #[inline]
+                fn next(&mut self) -> Option<#slice_mut_name #ty_generics_a> {
+                    if self.len == 0 {
+                        return None;
+                    }
+                    let size = ::std::cmp::min(self.chunk_size, self.len);
+                    // SAFETY: the chunks do not overlap, and stay inside the
+                    // slice this iterator was created from
+                    unsafe {
+                        let chunk = #slice_mut_name::from_raw_parts_mut(self.ptr, size);
+                        self.ptr = self.ptr.add(size);
+                        self.len -= size;
+                        Some(chunk)
+                    }
+                }
+
+                #[inline]
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    let count = self.len / self.chunk_size + usize::from(self.len % self.chunk_size != 0);
+                    (count, Some(count))
+                }
+            }
+
+            impl #impl_generics_a DoubleEndedIterator for ChunksMut #ty_generics_a #where_clause {
+                #[inline]
+                fn next_back(&mut self) -> Option<#slice_mut_name #ty_generics_a> {
+                    if self.len == 0 {
+                        return None;
+                    }
+                    let remainder = self.len % self.chunk_size;
+                    let size = if remainder == 0 { self.chunk_size } else { remainder };
+                    self.len -= size;
+                    // SAFETY: the chunks do not overlap, and stay inside the
+                    // slice this iterator was created from
+                    unsafe {
+                        Some(#slice_mut_name::from_raw_parts_mut(self.ptr.add(self.len), size))
+                    }
+                }
+            }
+
+            impl #impl_generics_a ExactSizeIterator for ChunksMut #ty_generics_a #where_clause {}
+            impl #impl_generics_a iter::FusedIterator for ChunksMut #ty_generics_a #where_clause {}
+
+            /// An iterator over a
+            #[doc = #slice_mut_doc_url]
+            /// in non-overlapping mutable chunks of exactly `chunk_size`
+            /// elements, starting at the beginning of the slice. The last
+            /// elements which do not fill a chunk are available with
+            /// `into_remainder`.
+            #[allow(missing_debug_implementations)]
+            #detail_visibility struct ChunksExactMut #generics_a #where_clause {
+                pub(super) ptr: #ptr_mut_name #ty_generics,
+                pub(super) len: usize,
+                pub(super) chunk_size: usize,
+                pub(super) remainder: #slice_mut_name #ty_generics_a,
+            }
+
+            unsafe impl #impl_generics_a Send for ChunksExactMut #ty_generics_a #send_where_clause {}
+            unsafe impl #impl_generics_a Sync for ChunksExactMut #ty_generics_a #sync_where_clause {}
+
+            impl #impl_generics_a ChunksExactMut #ty_generics_a #where_clause {
+                /// Get the last elements of the slice, which do not fill a
+                /// whole chunk.
+                #detail_visibility fn into_remainder(self) -> #slice_mut_name #ty_generics_a {
+                    self.remainder
+                }
+            }
+
+            impl #impl_generics_a Iterator for ChunksExactMut #ty_generics_a #where_clause {
+                type Item = #slice_mut_name #ty_generics_a;
+
+                #[inline]
+                fn next(&mut self) -> Option<#slice_mut_name #ty_generics_a> {
+                    if self.len < self.chunk_size {
+                        return None;
+                    }
+                    // SAFETY: the chunks do not overlap, and stay inside the
+                    // slice this iterator was created from
+                    unsafe {
+                        let chunk = #slice_mut_name::from_raw_parts_mut(self.ptr, self.chunk_size);
+                        self.ptr = self.ptr.add(self.chunk_size);
+                        self.len -= self.chunk_size;
+                        Some(chunk)
+                    }
+                }
+
+                #[inline]
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    let count = self.len / self.chunk_size;
+                    (count, Some(count))
+                }
+            }
+
+            impl #impl_generics_a DoubleEndedIterator for ChunksExactMut #ty_generics_a #where_clause {
+                #[inline]
+                fn next_back(&mut self) -> Option<#slice_mut_name #ty_generics_a> {
+                    if self.len < self.chunk_size {
+                        return None;
+                    }
+                    self.len -= self.chunk_size;
+                    // SAFETY: the chunks do not overlap, and stay inside the
+                    // slice this iterator was created from
+                    unsafe {
+                        Some(#slice_mut_name::from_raw_parts_mut(self.ptr.add(self.len), self.chunk_size))
+                    }
+                }
+            }
+
+            impl #impl_generics_a ExactSizeIterator for ChunksExactMut #ty_generics_a #where_clause {}
+            impl #impl_generics_a iter::FusedIterator for ChunksExactMut #ty_generics_a #where_clause {}
+
+            impl #impl_generics_a #slice_mut_name #ty_generics_a #where_clause {
+                /// Similar to [`slice::chunks_mut()`](https://doc.rust-lang.org/std/primitive.slice.html#method.chunks_mut).
+                ///
+                /// # Panics
+                ///
+                /// This function panics if `chunk_size` is 0.
+                #detail_visibility fn chunks_mut(&mut self, chunk_size: usize) -> ChunksMut #ty_generics_elided {
+                    assert!(chunk_size != 0, "chunk size must be non-zero");
+                    ChunksMut {
+                        ptr: self.as_mut_ptr(),
+                        len: self.len(),
+                        chunk_size: chunk_size,
+                        marker: PhantomData,
+                    }
+                }
+
+                /// Similar to [`slice::chunks_exact_mut()`](https://doc.rust-lang.org/std/primitive.slice.html#method.chunks_exact_mut).
+                ///
+                /// # Panics
+                ///
+                /// This function panics if `chunk_size` is 0.
+                #detail_visibility fn chunks_exact_mut(&mut self, chunk_size: usize) -> ChunksExactMut #ty_generics_elided {
+                    assert!(chunk_size != 0, "chunk size must be non-zero");
+                    let len = self.len() - self.len() % chunk_size;
+                    let (mut slice, remainder) = self.split_at_mut(len);
+                    ChunksExactMut {
+                        ptr: slice.as_mut_ptr(),
+                        len: len,
+                        chunk_size: chunk_size,
+                        remainder: remainder,
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use proc_macro2::TokenStream;
+use syn::ImplGenerics;
 use quote::quote;
 
-use crate::input::Input;
+use crate::input::{Input, VecLayout};
 
 pub fn derive(input: &Input) -> TokenStream {
     let vec_name = &input.vec_name();
@@ -9,20 +10,59 @@ pub fn derive(input: &Input) -> TokenStream {
     let slice_mut_name = &input.slice_mut_name();
     let ref_name = &input.ref_name();
     let ref_mut_name = &input.ref_mut_name();
-    let fields_names = input.fields.iter()
-                                   .map(|field| field.ident.clone().unwrap())
-                                   .collect::<Vec<_>>();
+    let fields_names = input.fields_names();
     let fields_names_1 = &fields_names;
-    let fields_names_2 = &fields_names;
     let first_field_name = &fields_names[0];
 
-    quote!{
+    // #[nested_soa] fields are indexed through the SoAIndex traits, taking
+    // either a reference to the nested vector or the nested slice by value.
+    let get_unchecked = |soa: TokenStream, borrow: TokenStream, index: TokenStream| input.map_fields_nested_or(
+        |name, _| quote!(::soa_derive::SoAIndex::get_unchecked(#index, #borrow #soa.#name)),
+        |name, _| quote!(#soa.#name.get_unchecked(#index)),
+    );
+    let index = |soa: TokenStream, borrow: TokenStream, index: TokenStream| input.map_fields_nested_or(
+        |name, _| quote!(::soa_derive::SoAIndex::index(#index, #borrow #soa.#name)),
+        |name, _| quote!(& #soa.#name[#index]),
+    );
+    let get_unchecked_mut = |soa: TokenStream, borrow: TokenStream, index: TokenStream| input.map_fields_nested_or(
+        |name, _| quote!(::soa_derive::SoAIndexMut::get_unchecked_mut(#index, #borrow #soa.#name)),
+        |name, _| quote!(#soa.#name.get_unchecked_mut(#index)),
+    );
+    let index_mut = |soa: TokenStream, borrow: TokenStream, index: TokenStream| input.map_fields_nested_or(
+        |name, _| quote!(::soa_derive::SoAIndexMut::index_mut(#index, #borrow #soa.#name)),
+        |name, _| quote!(&mut #soa.#name[#index]),
+    );
+
+    let vec_get_unchecked = &get_unchecked(quote!(soa), quote!(&), quote!(self));
+    let vec_index = &index(quote!(soa), quote!(&), quote!(self));
+    let vec_get_unchecked_mut = &get_unchecked_mut(quote!(soa), quote!(&mut), quote!(self));
+    let vec_index_mut = &index_mut(quote!(soa), quote!(&mut), quote!(self));
+    let vec_get_unchecked_range = &get_unchecked(quote!(soa), quote!(&), quote!(self.clone()));
+    let vec_index_range = &index(quote!(soa), quote!(&), quote!(self.clone()));
+    let vec_get_unchecked_mut_range = &get_unchecked_mut(quote!(soa), quote!(&mut), quote!(self.clone()));
+    let vec_index_mut_range = &index_mut(quote!(soa), quote!(&mut), quote!(self.clone()));
+
+    let slice_get_unchecked = &get_unchecked(quote!(slice), quote!(), quote!(self));
+    let slice_index = &index(quote!(slice), quote!(), quote!(self));
+    let slice_get_unchecked_mut = &get_unchecked_mut(quote!(slice), quote!(), quote!(self));
+    let slice_index_mut = &index_mut(quote!(slice), quote!(), quote!(self));
+    let slice_get_unchecked_range = &get_unchecked(quote!(slice), quote!(), quote!(self.clone()));
+    let slice_index_range = &index(quote!(slice), quote!(), quote!(self.clone()));
+    let slice_get_unchecked_mut_range = &get_unchecked_mut(quote!(slice), quote!(), quote!(self.clone()));
+    let slice_index_mut_range = &index_mut(quote!(slice), quote!(), quote!(self.clone()));
+
+    let generics = &input.generics;
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+    let generics_a = &input.generics_with_lifetime("'a");
+    let (impl_generics_a, ty_generics_a, _) = generics_a.split_for_impl();
+
+    let vec_impls = quote!{
         // usize
-        impl<'a> ::soa_derive::SoAIndex<&'a #vec_name> for usize {
-            type RefOutput = #ref_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<&'a #vec_name #ty_generics> for usize #where_clause {
+            type RefOutput = #ref_name #ty_generics_a;
 
             #[inline]
-            fn get(self, soa: &'a #vec_name) -> Option<Self::RefOutput> {
+            fn get(self, soa: &'a #vec_name #ty_generics) -> Option<Self::RefOutput> {
                 if self < soa.len() {
                     Some(unsafe { self.get_unchecked(soa) })
                 } else {
@@ -31,25 +71,25 @@ pub fn derive(input: &Input) -> TokenStream {
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, soa: &'a #vec_name) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 #ref_name {
-                    #(#fields_names_1: soa.#fields_names_2.get_unchecked(self),)*
+                    #(#fields_names_1: #vec_get_unchecked,)*
                 }
             }
 
             #[inline]
-            fn index(self, soa: &'a #vec_name) -> Self::RefOutput {
+            fn index(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 #ref_name {
-                    #(#fields_names_1: & soa.#fields_names_2[self],)*
+                    #(#fields_names_1: #vec_index,)*
                 }
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<&'a mut #vec_name> for usize {
-            type MutOutput = #ref_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<&'a mut #vec_name #ty_generics> for usize #where_clause {
+            type MutOutput = #ref_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, soa: &'a mut #vec_name) -> Option<Self::MutOutput> {
+            fn get_mut(self, soa: &'a mut #vec_name #ty_generics) -> Option<Self::MutOutput> {
                 if self < soa.len() {
                     Some(unsafe { self.get_unchecked_mut(soa) })
                 } else {
@@ -58,16 +98,16 @@ pub fn derive(input: &Input) -> TokenStream {
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 #ref_mut_name {
-                    #(#fields_names_1: soa.#fields_names_2.get_unchecked_mut(self),)*
+                    #(#fields_names_1: #vec_get_unchecked_mut,)*
                 }
             }
 
             #[inline]
-            fn index_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            fn index_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 #ref_mut_name {
-                    #(#fields_names_1: &mut soa.#fields_names_2[self],)*
+                    #(#fields_names_1: #vec_index_mut,)*
                 }
             }
         }
@@ -75,11 +115,11 @@ pub fn derive(input: &Input) -> TokenStream {
 
 
         // Range<usize>
-        impl<'a> ::soa_derive::SoAIndex<&'a #vec_name> for ::std::ops::Range<usize> {
-            type RefOutput = #slice_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<&'a #vec_name #ty_generics> for ::std::ops::Range<usize> #where_clause {
+            type RefOutput = #slice_name #ty_generics_a;
 
             #[inline]
-            fn get(self, soa: &'a #vec_name) -> Option<Self::RefOutput> {
+            fn get(self, soa: &'a #vec_name #ty_generics) -> Option<Self::RefOutput> {
                 if self.start <= self.end && self.end <= soa.len() {
                     unsafe { Some(self.get_unchecked(soa)) }
                 } else {
@@ -88,25 +128,25 @@ pub fn derive(input: &Input) -> TokenStream {
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, soa: &'a #vec_name) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 #slice_name {
-                    #(#fields_names_1: soa.#fields_names_2.get_unchecked(self.clone()),)*
+                    #(#fields_names_1: #vec_get_unchecked_range,)*
                 }
             }
 
             #[inline]
-            fn index(self, soa: &'a #vec_name) -> Self::RefOutput {
+            fn index(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 #slice_name {
-                    #(#fields_names_1: & soa.#fields_names_2[self.clone()],)*
+                    #(#fields_names_1: #vec_index_range,)*
                 }
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<&'a mut #vec_name> for ::std::ops::Range<usize> {
-            type MutOutput = #slice_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<&'a mut #vec_name #ty_generics> for ::std::ops::Range<usize> #where_clause {
+            type MutOutput = #slice_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, soa: &'a mut #vec_name) -> Option<Self::MutOutput> {
+            fn get_mut(self, soa: &'a mut #vec_name #ty_generics) -> Option<Self::MutOutput> {
                 if self.start <= self.end && self.end <= soa.len() {
                     unsafe { Some(self.get_unchecked_mut(soa)) }
                 } else {
@@ -115,16 +155,16 @@ pub fn derive(input: &Input) -> TokenStream {
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 #slice_mut_name {
-                    #(#fields_names_1: soa.#fields_names_2.get_unchecked_mut(self.clone()),)*
+                    #(#fields_names_1: #vec_get_unchecked_mut_range,)*
                 }
             }
 
             #[inline]
-            fn index_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            fn index_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 #slice_mut_name {
-                    #(#fields_names_1: &mut soa.#fields_names_2[self.clone()],)*
+                    #(#fields_names_1: #vec_index_mut_range,)*
                 }
             }
         }
@@ -132,131 +172,131 @@ pub fn derive(input: &Input) -> TokenStream {
 
 
         // RangeTo<usize>
-        impl<'a> ::soa_derive::SoAIndex<&'a #vec_name> for ::std::ops::RangeTo<usize> {
-            type RefOutput = #slice_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<&'a #vec_name #ty_generics> for ::std::ops::RangeTo<usize> #where_clause {
+            type RefOutput = #slice_name #ty_generics_a;
 
             #[inline]
-            fn get(self, soa: &'a #vec_name) -> Option<Self::RefOutput> {
+            fn get(self, soa: &'a #vec_name #ty_generics) -> Option<Self::RefOutput> {
                 (0..self.end).get(soa)
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, soa: &'a #vec_name) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 (0..self.end).get_unchecked(soa)
             }
 
             #[inline]
-            fn index(self, soa: &'a #vec_name) -> Self::RefOutput {
+            fn index(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 (0..self.end).index(soa)
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<&'a mut #vec_name> for ::std::ops::RangeTo<usize> {
-            type MutOutput = #slice_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<&'a mut #vec_name #ty_generics> for ::std::ops::RangeTo<usize> #where_clause {
+            type MutOutput = #slice_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, soa: &'a mut #vec_name) -> Option<Self::MutOutput> {
+            fn get_mut(self, soa: &'a mut #vec_name #ty_generics) -> Option<Self::MutOutput> {
                 (0..self.end).get_mut(soa)
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 (0..self.end).get_unchecked_mut(soa)
             }
 
             #[inline]
-            fn index_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            fn index_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 (0..self.end).index_mut(soa)
             }
         }
 
 
         // RangeFrom<usize>
-        impl<'a> ::soa_derive::SoAIndex<&'a #vec_name> for ::std::ops::RangeFrom<usize> {
-            type RefOutput = #slice_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<&'a #vec_name #ty_generics> for ::std::ops::RangeFrom<usize> #where_clause {
+            type RefOutput = #slice_name #ty_generics_a;
 
             #[inline]
-            fn get(self, soa: &'a #vec_name) -> Option<Self::RefOutput> {
+            fn get(self, soa: &'a #vec_name #ty_generics) -> Option<Self::RefOutput> {
                 (self.start..soa.len()).get(soa)
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, soa: &'a #vec_name) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 (self.start..soa.len()).get_unchecked(soa)
             }
 
             #[inline]
-            fn index(self, soa: &'a #vec_name) -> Self::RefOutput {
+            fn index(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 (self.start..soa.len()).index(soa)
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<&'a mut #vec_name> for ::std::ops::RangeFrom<usize> {
-            type MutOutput = #slice_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<&'a mut #vec_name #ty_generics> for ::std::ops::RangeFrom<usize> #where_clause {
+            type MutOutput = #slice_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, soa: &'a mut #vec_name) -> Option<Self::MutOutput> {
+            fn get_mut(self, soa: &'a mut #vec_name #ty_generics) -> Option<Self::MutOutput> {
                 (self.start..soa.len()).get_mut(soa)
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 (self.start..soa.len()).get_unchecked_mut(soa)
             }
 
             #[inline]
-            fn index_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            fn index_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 (self.start..soa.len()).index_mut(soa)
             }
         }
 
 
         // RangeFull
-        impl<'a> ::soa_derive::SoAIndex<&'a #vec_name> for ::std::ops::RangeFull {
-            type RefOutput = #slice_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<&'a #vec_name #ty_generics> for ::std::ops::RangeFull #where_clause {
+            type RefOutput = #slice_name #ty_generics_a;
 
             #[inline]
-            fn get(self, soa: &'a #vec_name) -> Option<Self::RefOutput> {
+            fn get(self, soa: &'a #vec_name #ty_generics) -> Option<Self::RefOutput> {
                 Some(soa.as_slice())
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, soa: &'a #vec_name) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 soa.as_slice()
             }
 
             #[inline]
-            fn index(self, soa: &'a #vec_name) -> Self::RefOutput {
+            fn index(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 soa.as_slice()
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<&'a mut #vec_name> for ::std::ops::RangeFull {
-            type MutOutput = #slice_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<&'a mut #vec_name #ty_generics> for ::std::ops::RangeFull #where_clause {
+            type MutOutput = #slice_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, soa: &'a mut #vec_name) -> Option<Self::MutOutput> {
+            fn get_mut(self, soa: &'a mut #vec_name #ty_generics) -> Option<Self::MutOutput> {
                 Some(soa.as_mut_slice())
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 soa.as_mut_slice()
             }
 
             #[inline]
-            fn index_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            fn index_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 soa.as_mut_slice()
             }
         }
 
 
         // RangeInclusive<usize>
-        impl<'a> ::soa_derive::SoAIndex<&'a #vec_name> for ::std::ops::RangeInclusive<usize> {
-            type RefOutput = #slice_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<&'a #vec_name #ty_generics> for ::std::ops::RangeInclusive<usize> #where_clause {
+            type RefOutput = #slice_name #ty_generics_a;
 
             #[inline]
-            fn get(self, soa: &'a #vec_name) -> Option<Self::RefOutput> {
+            fn get(self, soa: &'a #vec_name #ty_generics) -> Option<Self::RefOutput> {
                 if *self.end() == usize::MAX {
                     None
                 } else {
@@ -265,21 +305,21 @@ pub fn derive(input: &Input) -> TokenStream {
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, soa: &'a #vec_name) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 (*self.start()..self.end() + 1).get_unchecked(soa)
             }
 
             #[inline]
-            fn index(self, soa: &'a #vec_name) -> Self::RefOutput {
+            fn index(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 (*self.start()..self.end() + 1).index(soa)
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<&'a mut #vec_name> for ::std::ops::RangeInclusive<usize> {
-            type MutOutput = #slice_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<&'a mut #vec_name #ty_generics> for ::std::ops::RangeInclusive<usize> #where_clause {
+            type MutOutput = #slice_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, soa: &'a mut #vec_name) -> Option<Self::MutOutput> {
+            fn get_mut(self, soa: &'a mut #vec_name #ty_generics) -> Option<Self::MutOutput> {
                 if *self.end() == usize::MAX {
                     None
                 } else {
@@ -288,62 +328,91 @@ pub fn derive(input: &Input) -> TokenStream {
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 (*self.start()..self.end() + 1).get_unchecked_mut(soa)
             }
 
             #[inline]
-            fn index_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            fn index_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 (*self.start()..self.end() + 1).index_mut(soa)
             }
         }
 
 
         // RangeToInclusive<usize>
-        impl<'a> ::soa_derive::SoAIndex<&'a #vec_name> for ::std::ops::RangeToInclusive<usize> {
-            type RefOutput = #slice_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<&'a #vec_name #ty_generics> for ::std::ops::RangeToInclusive<usize> #where_clause {
+            type RefOutput = #slice_name #ty_generics_a;
 
             #[inline]
-            fn get(self, soa: &'a #vec_name) -> Option<Self::RefOutput> {
+            fn get(self, soa: &'a #vec_name #ty_generics) -> Option<Self::RefOutput> {
                 (0..=self.end).get(soa)
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, soa: &'a #vec_name) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 (0..=self.end).get_unchecked(soa)
             }
 
             #[inline]
-            fn index(self, soa: &'a #vec_name) -> Self::RefOutput {
+            fn index(self, soa: &'a #vec_name #ty_generics) -> Self::RefOutput {
                 (0..=self.end).index(soa)
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<&'a mut #vec_name> for ::std::ops::RangeToInclusive<usize> {
-            type MutOutput = #slice_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<&'a mut #vec_name #ty_generics> for ::std::ops::RangeToInclusive<usize> #where_clause {
+            type MutOutput = #slice_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, soa: &'a mut #vec_name) -> Option<Self::MutOutput> {
+            fn get_mut(self, soa: &'a mut #vec_name #ty_generics) -> Option<Self::MutOutput> {
                 (0..=self.end).get_mut(soa)
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 (0..=self.end).get_unchecked_mut(soa)
             }
 
             #[inline]
-            fn index_mut(self, soa: &'a mut #vec_name) -> Self::MutOutput {
+            fn index_mut(self, soa: &'a mut #vec_name #ty_generics) -> Self::MutOutput {
                 (0..=self.end).index_mut(soa)
             }
         }
+    };
+
+    // vectors with a single allocation do not give direct access to their
+    // columns, and are indexed through the corresponding slices
+    let vec_impls = if input.layout == VecLayout::SingleAlloc {
+        index_through_slices(input, &impl_generics_a, &quote!(#vec_name #ty_generics))
+    } else {
+        vec_impls
+    };
+
+    let array_impls = if input.array {
+        let array_name = &input.array_name();
+        let generics_array = &input.array_generics(None);
+        let (_, ty_generics_array, _) = generics_array.split_for_impl();
+        let generics_array_a = &input.array_generics(Some("'a"));
+        let (impl_generics_array_a, _, _) = generics_array_a.split_for_impl();
+        let mut impls = index_through_slices(input, &impl_generics_array_a, &quote!(#array_name #ty_generics_array));
+        if input.small_vec {
+            let small_vec_name = &input.small_vec_name();
+            impls.extend(index_through_slices(input, &impl_generics_array_a, &quote!(#small_vec_name #ty_generics_array)));
+        }
+        impls
+    } else {
+        TokenStream::new()
+    };
+
+    quote!{
+        #vec_impls
+        #array_impls
 
         // usize
-        impl<'a> ::soa_derive::SoAIndex<#slice_name<'a>> for usize {
-            type RefOutput = #ref_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<#slice_name #ty_generics_a> for usize #where_clause {
+            type RefOutput = #ref_name #ty_generics_a;
 
             #[inline]
-            fn get(self, slice: #slice_name<'a>) -> Option<Self::RefOutput> {
+            fn get(self, slice: #slice_name #ty_generics_a) -> Option<Self::RefOutput> {
                 if self < slice.#first_field_name.len() {
                     Some(unsafe { self.get_unchecked(slice) })
                 } else {
@@ -352,25 +421,25 @@ pub fn derive(input: &Input) -> TokenStream {
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 #ref_name {
-                    #(#fields_names_1: slice.#fields_names_2.get_unchecked(self),)*
+                    #(#fields_names_1: #slice_get_unchecked,)*
                 }
             }
 
             #[inline]
-            fn index(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            fn index(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 #ref_name {
-                    #(#fields_names_1: & slice.#fields_names_2[self],)*
+                    #(#fields_names_1: #slice_index,)*
                 }
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<#slice_mut_name<'a>> for usize {
-            type MutOutput = #ref_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<#slice_mut_name #ty_generics_a> for usize #where_clause {
+            type MutOutput = #ref_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, slice: #slice_mut_name<'a>) -> Option<Self::MutOutput> {
+            fn get_mut(self, slice: #slice_mut_name #ty_generics_a) -> Option<Self::MutOutput> {
                 if self < slice.len() {
                     Some(unsafe { self.get_unchecked_mut(slice) })
                 } else {
@@ -379,16 +448,16 @@ pub fn derive(input: &Input) -> TokenStream {
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 #ref_mut_name {
-                    #(#fields_names_1: slice.#fields_names_2.get_unchecked_mut(self),)*
+                    #(#fields_names_1: #slice_get_unchecked_mut,)*
                 }
             }
 
             #[inline]
-            fn index_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            fn index_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 #ref_mut_name {
-                    #(#fields_names_1: &mut slice.#fields_names_2[self],)*
+                    #(#fields_names_1: #slice_index_mut,)*
                 }
             }
         }
@@ -396,11 +465,11 @@ pub fn derive(input: &Input) -> TokenStream {
 
 
         // Range<usize>
-        impl<'a> ::soa_derive::SoAIndex<#slice_name<'a>> for ::std::ops::Range<usize> {
-            type RefOutput = #slice_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<#slice_name #ty_generics_a> for ::std::ops::Range<usize> #where_clause {
+            type RefOutput = #slice_name #ty_generics_a;
 
             #[inline]
-            fn get(self, slice: #slice_name<'a>) -> Option<Self::RefOutput> {
+            fn get(self, slice: #slice_name #ty_generics_a) -> Option<Self::RefOutput> {
                 if self.start <= self.end && self.end <= slice.#first_field_name.len() {
                     unsafe { Some(self.get_unchecked(slice)) }
                 } else {
@@ -409,25 +478,25 @@ pub fn derive(input: &Input) -> TokenStream {
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 #slice_name {
-                    #(#fields_names_1: slice.#fields_names_2.get_unchecked(self.clone()),)*
+                    #(#fields_names_1: #slice_get_unchecked_range,)*
                 }
             }
 
             #[inline]
-            fn index(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            fn index(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 #slice_name {
-                    #(#fields_names_1: & slice.#fields_names_2[self.clone()],)*
+                    #(#fields_names_1: #slice_index_range,)*
                 }
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<#slice_mut_name<'a>> for ::std::ops::Range<usize> {
-            type MutOutput = #slice_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<#slice_mut_name #ty_generics_a> for ::std::ops::Range<usize> #where_clause {
+            type MutOutput = #slice_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, slice: #slice_mut_name<'a>) -> Option<Self::MutOutput> {
+            fn get_mut(self, slice: #slice_mut_name #ty_generics_a) -> Option<Self::MutOutput> {
                 if self.start <= self.end && self.end <= slice.#first_field_name.len() {
                     unsafe { Some(self.get_unchecked_mut(slice)) }
                 } else {
@@ -436,16 +505,16 @@ pub fn derive(input: &Input) -> TokenStream {
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 #slice_mut_name {
-                    #(#fields_names_1: slice.#fields_names_2.get_unchecked_mut(self.clone()),)*
+                    #(#fields_names_1: #slice_get_unchecked_mut_range,)*
                 }
             }
 
             #[inline]
-            fn index_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            fn index_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 #slice_mut_name {
-                    #(#fields_names_1: &mut slice.#fields_names_2[self.clone()],)*
+                    #(#fields_names_1: #slice_index_mut_range,)*
                 }
             }
         }
@@ -453,131 +522,131 @@ pub fn derive(input: &Input) -> TokenStream {
 
 
         // RangeTo<usize>
-        impl<'a> ::soa_derive::SoAIndex<#slice_name<'a>> for ::std::ops::RangeTo<usize> {
-            type RefOutput = #slice_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<#slice_name #ty_generics_a> for ::std::ops::RangeTo<usize> #where_clause {
+            type RefOutput = #slice_name #ty_generics_a;
 
             #[inline]
-            fn get(self, slice: #slice_name<'a>) -> Option<Self::RefOutput> {
+            fn get(self, slice: #slice_name #ty_generics_a) -> Option<Self::RefOutput> {
                 (0..self.end).get(slice)
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 (0..self.end).get_unchecked(slice)
             }
 
             #[inline]
-            fn index(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            fn index(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 (0..self.end).index(slice)
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<#slice_mut_name<'a>> for ::std::ops::RangeTo<usize> {
-            type MutOutput = #slice_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<#slice_mut_name #ty_generics_a> for ::std::ops::RangeTo<usize> #where_clause {
+            type MutOutput = #slice_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, slice: #slice_mut_name<'a>) -> Option<Self::MutOutput> {
+            fn get_mut(self, slice: #slice_mut_name #ty_generics_a) -> Option<Self::MutOutput> {
                 (0..self.end).get_mut(slice)
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 (0..self.end).get_unchecked_mut(slice)
             }
 
             #[inline]
-            fn index_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            fn index_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 (0..self.end).index_mut(slice)
             }
         }
 
 
         // RangeFrom<usize>
-        impl<'a> ::soa_derive::SoAIndex<#slice_name<'a>> for ::std::ops::RangeFrom<usize> {
-            type RefOutput = #slice_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<#slice_name #ty_generics_a> for ::std::ops::RangeFrom<usize> #where_clause {
+            type RefOutput = #slice_name #ty_generics_a;
 
             #[inline]
-            fn get(self, slice: #slice_name<'a>) -> Option<Self::RefOutput> {
+            fn get(self, slice: #slice_name #ty_generics_a) -> Option<Self::RefOutput> {
                 (self.start..slice.len()).get(slice)
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 (self.start..slice.len()).get_unchecked(slice)
             }
 
             #[inline]
-            fn index(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            fn index(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 (self.start..slice.len()).index(slice)
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<#slice_mut_name<'a>> for ::std::ops::RangeFrom<usize> {
-            type MutOutput = #slice_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<#slice_mut_name #ty_generics_a> for ::std::ops::RangeFrom<usize> #where_clause {
+            type MutOutput = #slice_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, slice: #slice_mut_name<'a>) -> Option<Self::MutOutput> {
+            fn get_mut(self, slice: #slice_mut_name #ty_generics_a) -> Option<Self::MutOutput> {
                 (self.start..slice.len()).get_mut(slice)
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 (self.start..slice.len()).get_unchecked_mut(slice)
             }
 
             #[inline]
-            fn index_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            fn index_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 (self.start..slice.len()).index_mut(slice)
             }
         }
 
 
         // RangeFull
-        impl<'a> ::soa_derive::SoAIndex<#slice_name<'a>> for ::std::ops::RangeFull {
-            type RefOutput = #slice_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<#slice_name #ty_generics_a> for ::std::ops::RangeFull #where_clause {
+            type RefOutput = #slice_name #ty_generics_a;
 
             #[inline]
-            fn get(self, slice: #slice_name<'a>) -> Option<Self::RefOutput> {
+            fn get(self, slice: #slice_name #ty_generics_a) -> Option<Self::RefOutput> {
                 Some(slice)
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 slice
             }
 
             #[inline]
-            fn index(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            fn index(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 slice
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<#slice_mut_name<'a>> for ::std::ops::RangeFull {
-            type MutOutput = #slice_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<#slice_mut_name #ty_generics_a> for ::std::ops::RangeFull #where_clause {
+            type MutOutput = #slice_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, slice: #slice_mut_name<'a>) -> Option<Self::MutOutput> {
+            fn get_mut(self, slice: #slice_mut_name #ty_generics_a) -> Option<Self::MutOutput> {
                 Some(slice)
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 slice
             }
 
             #[inline]
-            fn index_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            fn index_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 slice
             }
         }
 
 
         // RangeInclusive<usize>
-        impl<'a> ::soa_derive::SoAIndex<#slice_name<'a>> for ::std::ops::RangeInclusive<usize> {
-            type RefOutput = #slice_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<#slice_name #ty_generics_a> for ::std::ops::RangeInclusive<usize> #where_clause {
+            type RefOutput = #slice_name #ty_generics_a;
 
             #[inline]
-            fn get(self, slice: #slice_name<'a>) -> Option<Self::RefOutput> {
+            fn get(self, slice: #slice_name #ty_generics_a) -> Option<Self::RefOutput> {
                 if *self.end() == usize::MAX {
                     None
                 } else {
@@ -586,21 +655,21 @@ pub fn derive(input: &Input) -> TokenStream {
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 (*self.start()..self.end() + 1).get_unchecked(slice)
             }
 
             #[inline]
-            fn index(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            fn index(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 (*self.start()..self.end() + 1).index(slice)
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<#slice_mut_name<'a>> for ::std::ops::RangeInclusive<usize> {
-            type MutOutput = #slice_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<#slice_mut_name #ty_generics_a> for ::std::ops::RangeInclusive<usize> #where_clause {
+            type MutOutput = #slice_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, slice: #slice_mut_name<'a>) -> Option<Self::MutOutput> {
+            fn get_mut(self, slice: #slice_mut_name #ty_generics_a) -> Option<Self::MutOutput> {
                 if *self.end() == usize::MAX {
                     None
                 } else {
@@ -609,54 +678,117 @@ pub fn derive(input: &Input) -> TokenStream {
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 (*self.start()..self.end() + 1).get_unchecked_mut(slice)
             }
 
             #[inline]
-            fn index_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            fn index_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 (*self.start()..self.end() + 1).index_mut(slice)
             }
         }
 
 
         // RangeToInclusive<usize>
-        impl<'a> ::soa_derive::SoAIndex<#slice_name<'a>> for ::std::ops::RangeToInclusive<usize> {
-            type RefOutput = #slice_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndex<#slice_name #ty_generics_a> for ::std::ops::RangeToInclusive<usize> #where_clause {
+            type RefOutput = #slice_name #ty_generics_a;
 
             #[inline]
-            fn get(self, slice: #slice_name<'a>) -> Option<Self::RefOutput> {
+            fn get(self, slice: #slice_name #ty_generics_a) -> Option<Self::RefOutput> {
                 (0..=self.end).get(slice)
             }
 
             #[inline]
-            unsafe fn get_unchecked(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            unsafe fn get_unchecked(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 (0..=self.end).get_unchecked(slice)
             }
 
             #[inline]
-            fn index(self, slice: #slice_name<'a>) -> Self::RefOutput {
+            fn index(self, slice: #slice_name #ty_generics_a) -> Self::RefOutput {
                 (0..=self.end).index(slice)
             }
         }
 
-        impl<'a> ::soa_derive::SoAIndexMut<#slice_mut_name<'a>> for ::std::ops::RangeToInclusive<usize> {
-            type MutOutput = #slice_mut_name<'a>;
+        impl #impl_generics_a ::soa_derive::SoAIndexMut<#slice_mut_name #ty_generics_a> for ::std::ops::RangeToInclusive<usize> #where_clause {
+            type MutOutput = #slice_mut_name #ty_generics_a;
 
             #[inline]
-            fn get_mut(self, slice: #slice_mut_name<'a>) -> Option<Self::MutOutput> {
+            fn get_mut(self, slice: #slice_mut_name #ty_generics_a) -> Option<Self::MutOutput> {
                 (0..=self.end).get_mut(slice)
             }
 
             #[inline]
-            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            unsafe fn get_unchecked_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 (0..=self.end).get_unchecked_mut(slice)
             }
 
             #[inline]
-            fn index_mut(self, slice: #slice_mut_name<'a>) -> Self::MutOutput {
+            fn index_mut(self, slice: #slice_mut_name #ty_generics_a) -> Self::MutOutput {
                 (0..=self.end).index_mut(slice)
             }
         }
     }
 }
+
+/// Implement `SoAIndex` and `SoAIndexMut` for references to a `container`
+/// which does not give direct access to its columns, by indexing into the
+/// slices returned by `as_slice()` and `as_mut_slice()`. `impl_generics`
+/// must contain the `'a` lifetime used for the references.
+fn index_through_slices(input: &Input, impl_generics: &ImplGenerics, container: &TokenStream) -> TokenStream {
+    let slice_name = &input.slice_name();
+    let slice_mut_name = &input.slice_mut_name();
+    let where_clause = &input.generics.where_clause;
+    let generics_a = &input.generics_with_lifetime("'a");
+    let (_, ty_generics_a, _) = generics_a.split_for_impl();
+
+    let index_types = [
+        quote!(usize),
+        quote!(::std::ops::Range<usize>),
+        quote!(::std::ops::RangeTo<usize>),
+        quote!(::std::ops::RangeFrom<usize>),
+        quote!(::std::ops::RangeFull),
+        quote!(::std::ops::RangeInclusive<usize>),
+        quote!(::std::ops::RangeToInclusive<usize>),
+    ];
+    quote! {
+        #(
+            impl #impl_generics ::soa_derive::SoAIndex<&'a #container> for #index_types #where_clause {
+                type RefOutput = <Self as ::soa_derive::SoAIndex<#slice_name #ty_generics_a>>::RefOutput;
+
+                #[inline]
+                fn get(self, soa: &'a #container) -> Option<Self::RefOutput> {
+                    ::soa_derive::SoAIndex::get(self, soa.as_slice())
+                }
+
+                #[inline]
+                unsafe fn get_unchecked(self, soa: &'a #container) -> Self::RefOutput {
+                    ::soa_derive::SoAIndex::get_unchecked(self, soa.as_slice())
+                }
+
+                #[inline]
+                fn index(self, soa: &'a #container) -> Self::RefOutput {
+                    ::soa_derive::SoAIndex::index(self, soa.as_slice())
+                }
+            }
+
+            impl #impl_generics ::soa_derive::SoAIndexMut<&'a mut #container> for #index_types #where_clause {
+                type MutOutput = <Self as ::soa_derive::SoAIndexMut<#slice_mut_name #ty_generics_a>>::MutOutput;
+
+                #[inline]
+                fn get_mut(self, soa: &'a mut #container) -> Option<Self::MutOutput> {
+                    ::soa_derive::SoAIndexMut::get_mut(self, soa.as_mut_slice())
+                }
+
+                #[inline]
+                unsafe fn get_unchecked_mut(self, soa: &'a mut #container) -> Self::MutOutput {
+                    ::soa_derive::SoAIndexMut::get_unchecked_mut(self, soa.as_mut_slice())
+                }
+
+                #[inline]
+                fn index_mut(self, soa: &'a mut #container) -> Self::MutOutput {
+                    ::soa_derive::SoAIndexMut::index_mut(self, soa.as_mut_slice())
+                }
+            }
+        )*
+    }
+}
@@ -1,107 +1,1022 @@
 use proc_macro2::{Span, TokenStream};
-use syn::{Data, DeriveInput, Ident, Field, Visibility, Meta, MetaNameValue, Lit};
+use syn::{Data, DeriveInput, Ident, Field, Fields, Visibility, Meta, MetaNameValue, Lit, NestedMeta};
+use syn::{Generics, GenericParam, LifetimeDef, Lifetime, Member, Index, Type, parse_quote};
+use syn::PathArguments;
 use quote::quote;
 
 /// Representing the struct we are deriving
 pub struct Input {
     /// The input struct name
     pub name: Ident,
-    /// The list of traits to derive passed to `soa_derive` attribute
-    pub derives: Vec<Ident>,
-    /// The list of fields in the struct
+    /// Additional attributes for the generated types, from the `soa_derive`
+    /// and `soa_attr` attributes
+    pub attrs: ExtraAttributes,
+    /// Custom names for the generated types, from the `soa` attribute
+    pub names: CustomNames,
+    /// The list of fields in the struct stored in the generated types, *i.e.*
+    /// all the fields not marked with `#[soa(skip)]`
     pub fields: Vec<Field>,
+    /// How to access each one of `fields` in the input struct
+    pub members: Vec<Member>,
+    /// Whether each one of `fields` is marked with `#[nested_soa]`
+    pub nested: Vec<bool>,
+    /// The alignment in bytes requested for the column of each one of
+    /// `fields` with `#[soa(align = N)]`, or 1 to use the natural alignment
+    /// of the field type
+    pub align: Vec<usize>,
+    /// The fields marked with `#[soa(skip)]`, which are not stored in the
+    /// generated types, and use their default value when reconstructing the
+    /// input struct
+    pub skipped: Vec<Member>,
     /// The struct overall visibility
-    pub visibility: Visibility
+    pub visibility: Visibility,
+    /// The generic parameters, bounds and where clause of the struct
+    pub generics: Generics,
+    /// How the generated types should be serialized, from the `soa(serde)`
+    /// attribute
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub serde: Option<SerdeForm>,
+    /// Should the fields of the generated vector be private, from the
+    /// `soa(private_fields)` attribute
+    pub private_fields: bool,
+    /// How the columns of the generated vector are stored in memory, from the
+    /// `soa(layout)` attribute
+    pub layout: VecLayout,
+    /// How to store groups of values from the numeric fields in the
+    /// generated lanes type, from the `soa(lanes)` attribute
+    pub lanes: Option<LaneForm>,
+    /// Should we also generate a fixed capacity array type, from the
+    /// `soa(array)` attribute
+    pub array: bool,
+    /// Should we also generate a small vector type, storing elements inline
+    /// before spilling to the heap, from the `soa(small_vec)` attribute
+    pub small_vec: bool,
 }
 
 impl Input {
-    pub fn new(input: DeriveInput) -> Input {
+    pub fn new(input: DeriveInput) -> Result<Input, syn::Error> {
         let fields = match input.data {
-            Data::Struct(s) => {
-                s.fields.iter().cloned().collect::<Vec<_>>()
+            Data::Struct(s) => match s.fields {
+                Fields::Named(fields) => fields.named.into_iter().collect::<Vec<_>>(),
+                Fields::Unnamed(fields) => fields.unnamed.into_iter().collect::<Vec<_>>(),
+                Fields::Unit => Vec::new(),
+            },
+            Data::Enum(data) => {
+                return Err(syn::Error::new(
+                    data.enum_token.span,
+                    "#[derive(StructOfArray)] only supports structs, not enums",
+                ));
+            }
+            Data::Union(data) => {
+                return Err(syn::Error::new(
+                    data.union_token.span,
+                    "#[derive(StructOfArray)] only supports structs, not unions",
+                ));
             }
-            _ => panic!("#[derive(StructOfArray)] only supports structs."),
         };
 
-        let mut derives: Vec<Ident> = vec![];
+        let mut soa_fields = Vec::with_capacity(fields.len());
+        let mut members = Vec::with_capacity(fields.len());
+        let mut nested = Vec::with_capacity(fields.len());
+        let mut fields_align = Vec::with_capacity(fields.len());
+        let mut skipped = Vec::new();
+        for (i, field) in fields.into_iter().enumerate() {
+            let member = match field.ident {
+                Some(ref ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(Index::from(i)),
+            };
+
+            let mut is_nested = false;
+            let mut skip = false;
+            let mut align = None;
+            for attr in &field.attrs {
+                if attr.path.is_ident("soa") {
+                    for option in parse_soa_options(&attr.parse_meta()?)? {
+                        if matches!(option, Meta::Path(ref path) if path.is_ident("skip")) {
+                            skip = true;
+                        } else if let Some(value) = parse_align(&option)? {
+                            align = Some(value);
+                        } else {
+                            return Err(syn::Error::new_spanned(
+                                &option,
+                                format!("unknown field option #[soa({})], expected #[soa(skip)] or #[soa(align = N)]", quote!(#option)),
+                            ));
+                        }
+                    }
+                }
+
+                if !attr.path.is_ident("nested_soa") {
+                    continue;
+                }
+
+                if !attr.tokens.is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        &attr.tokens,
+                        "#[nested_soa] does not take any argument",
+                    ));
+                }
+
+                match field.ty {
+                    Type::Path(ref path) if path.qself.is_none() && !matches!(
+                        path.path.segments.last().map(|segment| &segment.arguments),
+                        Some(PathArguments::Parenthesized(_)) | None
+                    ) => {}
+                    ref ty => return Err(syn::Error::new_spanned(
+                        ty,
+                        "#[nested_soa] can only be used on fields with a struct type deriving StructOfArray",
+                    )),
+                }
+                is_nested = true;
+            }
+
+            if skip {
+                if is_nested || align.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        &field,
+                        "#[soa(skip)] can not be used together with #[nested_soa] or #[soa(align)] on the same field",
+                    ));
+                }
+                skipped.push(member);
+            } else {
+                soa_fields.push(field);
+                members.push(member);
+                nested.push(is_nested);
+                fields_align.push(align);
+            }
+        }
+
+        let mut attrs = ExtraAttributes::default();
+        let mut names = CustomNames::default();
+        let mut serde = None;
+        let mut private_fields = false;
+        let mut layout = None;
+        let mut align = None;
+        let mut lanes = None;
+        let mut array = false;
+        let mut small_vec = false;
         for attr in input.attrs {
-            if let Ok(meta) = attr.parse_meta() {
-                if meta.path().is_ident("soa_derive") {
-                    match meta {
-                        Meta::NameValue(MetaNameValue{lit: Lit::Str(string), ..}) => {
-                            for value in string.value().split(',') {
-                                derives.push(Ident::new(value.trim(), Span::call_site()));
-                            }
+            if attr.path.is_ident("soa") {
+                for option in parse_soa_options(&attr.parse_meta()?)? {
+                    if let Some(form) = SerdeForm::parse_option(&option)? {
+                        serde = Some(form);
+                    } else if let Some(value) = VecLayout::parse_option(&option)? {
+                        layout = Some(value);
+                    } else if let Some(value) = parse_align(&option)? {
+                        align = Some(value);
+                    } else if let Some(form) = LaneForm::parse_option(&option)? {
+                        lanes = Some(form);
+                    } else if matches!(option, Meta::Path(ref path) if path.is_ident("private_fields")) {
+                        if soa_fields.iter().any(|field| field.ident.is_none()) {
+                            return Err(syn::Error::new_spanned(
+                                &option,
+                                "#[soa(private_fields)] can only be used on structs with named fields",
+                            ));
+                        }
+                        private_fields = true;
+                    } else if matches!(option, Meta::Path(ref path) if path.is_ident("array") || path.is_ident("small_vec")) {
+                        // the small vector stores its elements inline in the
+                        // generated array, which must be able to store all the
+                        // fields
+                        if soa_fields.is_empty() {
+                            return Err(syn::Error::new_spanned(
+                                &option,
+                                format!("#[soa({})] can not be used on structs without fields", quote!(#option)),
+                            ));
                         }
-                        _ => panic!("expected #[soa_derive = \"Traits, To, Derive\"], got #[{}]", quote!(#meta))
+                        if nested.iter().any(|&nested| nested) {
+                            return Err(syn::Error::new_spanned(
+                                &option,
+                                format!("#[soa({})] can not be used with #[nested_soa] fields", quote!(#option)),
+                            ));
+                        }
+                        array = true;
+                        small_vec |= option.path().is_ident("small_vec");
+                    } else if !names.parse_option(&option)? {
+                        return Err(syn::Error::new_spanned(
+                            &option,
+                            format!("unknown option #[soa({})]", quote!(#option)),
+                        ));
                     }
                 }
+            } else if attr.path.is_ident("soa_derive") {
+                let derives = parse_soa_derive(attr.parse_meta()?)?;
+                attrs.add_derives(&derives);
+            } else if attr.path.is_ident("soa_attr") {
+                attrs.add_soa_attr(&attr.parse_meta()?)?;
             }
         }
 
-        Input {
-            name: input.ident,
-            derives: derives,
-            fields: fields,
-            visibility: input.vis
+        if serde.is_some() && (attrs.vec_derives("Serialize") || attrs.vec_derives("Deserialize")) {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "#[soa(serde)] already implements Serialize and Deserialize, they can not also be derived with #[soa_derive]",
+            ));
         }
+
+        // plain `Vec` do not support custom alignment, so `#[soa(align)]`
+        // implies `#[soa(layout = "single_alloc")]`
+        if align.is_some() || fields_align.iter().any(Option::is_some) {
+            if layout == Some(VecLayout::Separate) {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "#[soa(align)] can not be used with #[soa(layout = \"separate\")]",
+                ));
+            }
+            layout = Some(VecLayout::SingleAlloc);
+        }
+        let layout = layout.unwrap_or(VecLayout::Separate);
+        // the alignment of a field overrides the alignment of the struct
+        let fields_align = fields_align.into_iter()
+            .map(|field_align| field_align.or(align).unwrap_or(1))
+            .collect();
+
+        if lanes.is_some() && !soa_fields.iter().any(|field| is_primitive_number(&field.ty)) {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "#[soa(lanes)] requires at least one field with a primitive numeric type",
+            ));
+        }
+
+        if layout == VecLayout::SingleAlloc {
+            if soa_fields.is_empty() {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "#[soa(layout = \"single_alloc\")] can not be used on structs without fields",
+                ));
+            }
+
+            if nested.iter().any(|&nested| nested) {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "#[soa(layout = \"single_alloc\")] can not be used with #[nested_soa] fields",
+                ));
+            }
+
+            // these traits are implemented manually for the vector, since its
+            // fields are raw memory
+            let supported = ["Clone", "Debug", "Default", "PartialEq", "Eq"];
+            if let Some(path) = attrs.vec_derive_paths().find(|path| !supported.iter().any(|name| path.is_ident(name))) {
+                return Err(syn::Error::new_spanned(
+                    path,
+                    format!(
+                        "#[soa(layout = \"single_alloc\")] vectors can only derive Clone, Debug, Default, PartialEq and Eq, got `{}`",
+                        quote!(#path)
+                    ),
+                ));
+            }
+        }
+
+        // the columns of these vectors are accessed through methods named
+        // after the fields (`mass()` and `mass_mut()`), which must not have
+        // the same name as the other methods of the vector
+        if private_fields || layout == VecLayout::SingleAlloc {
+            for field in &soa_fields {
+                if let Some(ident) = &field.ident {
+                    let accessor = ident.to_string();
+                    let accessor_mut = format!("{}_mut", accessor);
+                    if let Some(method) = VEC_METHODS.iter().find(|&&method| method == accessor || method == accessor_mut) {
+                        return Err(syn::Error::new_spanned(
+                            ident,
+                            format!(
+                                "the accessors for the field `{}` would have the same name as the `{}` method of the vector, rename this field",
+                                accessor, method
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(Input {
+            name: input.ident,
+            attrs: attrs,
+            names: names,
+            fields: soa_fields,
+            members: members,
+            nested: nested,
+            align: fields_align,
+            skipped: skipped,
+            visibility: input.vis,
+            generics: input.generics,
+            serde: serde,
+            private_fields: private_fields,
+            layout: layout,
+            lanes: lanes,
+            array: array,
+            small_vec: small_vec,
+        })
+    }
+
+    /// Is the input struct a tuple struct, with unnamed fields?
+    pub fn is_tuple(&self) -> bool {
+        self.fields.iter().any(|field| field.ident.is_none())
+    }
+
+    /// Does the input struct have no fields at all? In this case, the
+    /// generated code contains a single private `len` column of `()`, which
+    /// keeps track of the number of elements in the same way as a `Vec<()>`.
+    pub fn is_fieldless(&self) -> bool {
+        self.fields.is_empty()
     }
 
-    pub fn derive(&self) -> TokenStream {
-        if self.derives.is_empty() {
+    /// Get the attribute allowing unused generated code. This is only needed
+    /// for non-public structs, and is left out for public ones since it
+    /// conflicts with `#![forbid(warnings)]` in the user crate.
+    pub fn allow_dead_code(&self) -> TokenStream {
+        if let Visibility::Public(_) = self.visibility {
             TokenStream::new()
         } else {
-            let derives = &self.derives;
-            quote!(
-                #[derive(
-                    #(#derives,)*
-                )]
-            )
+            quote!(#[allow(dead_code)])
         }
     }
 
-    pub fn derive_with_exceptions(&self) -> TokenStream {
-        if self.derives.is_empty() {
-            TokenStream::new()
+    /// Get the names used to access each field of the struct: identifiers for
+    /// structs with named fields, and indexes for tuple structs.
+    pub fn fields_names(&self) -> Vec<Member> {
+        if self.is_fieldless() {
+            return vec![Member::Named(Ident::new("len", Span::call_site()))];
+        }
+
+        self.fields.iter().enumerate().map(|(i, field)| {
+            match field.ident {
+                Some(ref ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(Index::from(i)),
+            }
+        }).collect()
+    }
+
+    /// Get the identifiers to use for local variables containing the value of
+    /// each field of the struct.
+    pub fn fields_vars(&self) -> Vec<Ident> {
+        if self.is_fieldless() {
+            return vec![Ident::new("_len", Span::call_site())];
+        }
+
+        self.fields.iter().enumerate().map(|(i, field)| {
+            match field.ident {
+                Some(ref ident) => {
+                    let mut ident = ident.clone();
+                    ident.set_span(Span::call_site());
+                    ident
+                }
+                None => Ident::new(&format!("field_{}", i), Span::call_site()),
+            }
+        }).collect()
+    }
+
+    /// Get the types of each field of the struct
+    pub fn fields_types(&self) -> Vec<Type> {
+        if self.is_fieldless() {
+            return vec![parse_quote!(())];
+        }
+
+        self.fields.iter().map(|field| field.ty.clone()).collect()
+    }
+
+    /// Is the field at index `i` marked with `#[nested_soa]`?
+    pub fn is_nested(&self, i: usize) -> bool {
+        self.nested.get(i).copied().unwrap_or(false)
+    }
+
+    /// Get the name, type and a method name made of `prefix` followed by the
+    /// field name (`sort_by_field_mass`, `retain_by_0`) for each field stored in a
+    /// plain `Vec`, excluding `#[nested_soa]` fields and the `len` marker of
+    /// structs without fields.
+    pub fn column_methods(&self, prefix: &str) -> Vec<(Member, Type, Ident)> {
+        if self.is_fieldless() {
+            return Vec::new();
+        }
+
+        self.fields_names().into_iter().zip(self.fields_types()).enumerate()
+            .filter(|&(i, _)| !self.is_nested(i))
+            .map(|(_, (name, ty))| {
+                let method = match name {
+                    Member::Named(ref ident) => format!("{}{}", prefix, ident),
+                    Member::Unnamed(ref index) => format!("{}{}", prefix, index.index),
+                };
+                (name, ty, Ident::new(&method, Span::call_site()))
+            })
+            .collect()
+    }
+
+    /// Get the name used to access each field with a primitive numeric type
+    /// in the input struct and in the generated lanes type, together with
+    /// the type of the field. Fields of tuple structs are numbered in the
+    /// lanes type, skipping all the non-numeric fields.
+    pub fn lanes_fields(&self) -> Vec<(Member, Member, Type)> {
+        self.fields_names().into_iter().zip(self.fields_types())
+            .filter(|(_, ty)| is_primitive_number(ty))
+            .enumerate()
+            .map(|(i, (name, ty))| {
+                let lanes_name = match name {
+                    Member::Named(_) => name.clone(),
+                    Member::Unnamed(_) => Member::Unnamed(Index::from(i)),
+                };
+                (name, lanes_name, ty)
+            })
+            .collect()
+    }
+
+    /// Map each field of the struct to some code, using `nested_map` for the
+    /// fields marked with `#[nested_soa]` and `plain_map` for all the other
+    /// fields. Both functions get the name and type of the field.
+    pub fn map_fields_nested_or<N, P>(&self, nested_map: N, plain_map: P) -> Vec<TokenStream>
+    where
+        N: Fn(&Member, &Type) -> TokenStream,
+        P: Fn(&Member, &Type) -> TokenStream,
+    {
+        let fields_names = self.fields_names();
+        let fields_types = self.fields_types();
+        fields_names.iter().zip(&fields_types).enumerate().map(|(i, (name, ty))| {
+            if self.is_nested(i) {
+                nested_map(name, ty)
+            } else {
+                plain_map(name, ty)
+            }
+        }).collect()
+    }
+
+    /// Generate code binding the fields of `value` (either an instance of the
+    /// input struct or a reference to one) to local variables named after
+    /// `fields_vars()`. For structs without fields, the variable for the `len`
+    /// column is bound to `marker` instead.
+    pub fn destructure(&self, value: &TokenStream, marker: &TokenStream) -> TokenStream {
+        let name = &self.name;
+        let rest = if self.skipped.is_empty() { quote!() } else { quote!(..) };
+        if self.is_fieldless() {
+            return quote! {
+                let #name { #rest } = #value;
+                let _len = #marker;
+            };
+        }
+
+        let members = &self.members;
+        let fields_vars = self.fields_vars();
+        quote! {
+            let #name { #(#members: #fields_vars,)* #rest } = #value;
+        }
+    }
+
+    /// Generate an expression creating an instance of the input struct from
+    /// local variables named after `fields_vars()`. The fields marked with
+    /// `#[soa(skip)]` are set to their default value.
+    pub fn restructure(&self) -> TokenStream {
+        let name = &self.name;
+        let members = &self.members;
+        let fields_vars = if self.is_fieldless() { Vec::new() } else { self.fields_vars() };
+        let skipped = &self.skipped;
+        quote! {
+            #name {
+                #(#members: #fields_vars,)*
+                #(#skipped: ::std::default::Default::default(),)*
+            }
+        }
+    }
+
+    /// Generate the body of a struct definition (including the where clause)
+    /// containing one public field for each field of the input struct, with
+    /// the given documentation and types. This generates a tuple struct if the
+    /// input is a tuple struct.
+    pub fn struct_body(&self, docs: &[String], types: &[TokenStream]) -> TokenStream {
+        self.struct_body_with_visibility(docs, types, &quote!(pub))
+    }
+
+    /// Same as `struct_body`, using `visibility` for the fields instead of
+    /// making them public.
+    pub fn struct_body_with_visibility(&self, docs: &[String], types: &[TokenStream], visibility: &TokenStream) -> TokenStream {
+        let where_clause = &self.generics.where_clause;
+        if self.is_fieldless() {
+            return quote! {
+                #where_clause {
+                    #(len: #types,)*
+                }
+            };
+        }
+
+        if self.is_tuple() {
+            quote! {
+                (
+                    #(
+                        #[doc = #docs]
+                        #visibility #types,
+                    )*
+                ) #where_clause;
+            }
         } else {
-            let derives = &self.derives.iter()
-                                       .cloned()
-                                       .filter(|name| name != "Clone")
-                                       .filter(|name| name != "Deserialize")
-                                       .collect::<Vec<_>>();
-            quote!(
-                #[derive(
-                    #(#derives,)*
-                )]
-            )
+            let fields_names = self.fields_names();
+            quote! {
+                #where_clause {
+                    #(
+                        #[doc = #docs]
+                        #visibility #fields_names: #types,
+                    )*
+                }
+            }
+        }
+    }
+
+    /// Get the generics of the struct, with an additional `lifetime` in first
+    /// position. This is used by all the generated types borrowing data
+    /// (references, slices, iterators, ...).
+    pub fn generics_with_lifetime(&self, lifetime: &str) -> Generics {
+        let mut generics = self.generics.clone();
+        let lifetime = LifetimeDef::new(Lifetime::new(lifetime, Span::call_site()));
+        generics.params.insert(0, GenericParam::Lifetime(lifetime));
+        return generics;
+    }
+
+    /// Get the generics of the struct with an additional `lifetime` in first
+    /// position, which all the other generic parameters must outlive. This is
+    /// needed for the generated types and iterators to be well formed, since
+    /// the bounds can not be inferred for `#[nested_soa]` fields.
+    pub fn generics_outliving(&self, lifetime: &str) -> Generics {
+        let mut generics = self.generics_with_lifetime(lifetime);
+        let lifetime = Lifetime::new(lifetime, Span::call_site());
+        for param in generics.lifetimes_mut().skip(1) {
+            param.bounds.push(lifetime.clone());
         }
+        for param in generics.type_params_mut() {
+            param.bounds.push(parse_quote!(#lifetime));
+        }
+        return generics;
+    }
+
+    /// Get the generics of the struct, with an additional `const N: usize`
+    /// parameter for the capacity of the generated array type, and an
+    /// optional `lifetime` in first position.
+    pub fn array_generics(&self, lifetime: Option<&str>) -> Generics {
+        let mut generics = match lifetime {
+            Some(lifetime) => self.generics_with_lifetime(lifetime),
+            None => self.generics.clone(),
+        };
+        generics.params.push(parse_quote!(const N: usize));
+        return generics;
     }
 
     pub fn vec_name(&self) -> Ident {
-        Ident::new(&format!("{}Vec", self.name), Span::call_site())
+        match self.names.vec {
+            Some(ref name) => name.clone(),
+            None => Ident::new(&format!("{}Vec", self.name), Span::call_site()),
+        }
     }
 
     pub fn slice_name(&self) -> Ident {
-        Ident::new(&format!("{}Slice", self.name), Span::call_site())
+        match self.names.slice {
+            Some(ref name) => name.clone(),
+            None => Ident::new(&format!("{}Slice", self.name), Span::call_site()),
+        }
     }
 
     pub fn slice_mut_name(&self) -> Ident {
-        Ident::new(&format!("{}SliceMut", self.name), Span::call_site())
+        match self.names.slice_mut {
+            Some(ref name) => name.clone(),
+            None => Ident::new(&format!("{}SliceMut", self.name), Span::call_site()),
+        }
     }
 
     pub fn ref_name(&self) -> Ident {
-        Ident::new(&format!("{}Ref", self.name), Span::call_site())
+        match self.names.ref_ {
+            Some(ref name) => name.clone(),
+            None => Ident::new(&format!("{}Ref", self.name), Span::call_site()),
+        }
     }
 
     pub fn ref_mut_name(&self) -> Ident {
-        Ident::new(&format!("{}RefMut", self.name), Span::call_site())
+        match self.names.ref_mut {
+            Some(ref name) => name.clone(),
+            None => Ident::new(&format!("{}RefMut", self.name), Span::call_site()),
+        }
     }
 
     pub fn ptr_name(&self) -> Ident {
-        Ident::new(&format!("{}Ptr", self.name), Span::call_site())
+        match self.names.ptr {
+            Some(ref name) => name.clone(),
+            None => Ident::new(&format!("{}Ptr", self.name), Span::call_site()),
+        }
     }
 
     pub fn ptr_mut_name(&self) -> Ident {
-        Ident::new(&format!("{}PtrMut", self.name), Span::call_site())
+        match self.names.ptr_mut {
+            Some(ref name) => name.clone(),
+            None => Ident::new(&format!("{}PtrMut", self.name), Span::call_site()),
+        }
+    }
+
+    pub fn array_name(&self) -> Ident {
+        Ident::new(&format!("{}Array", self.name), Span::call_site())
+    }
+
+    pub fn small_vec_name(&self) -> Ident {
+        Ident::new(&format!("{}SmallVec", self.name), Span::call_site())
+    }
+
+    pub fn lanes_name(&self) -> Ident {
+        Ident::new(&format!("{}Lanes", self.name), Span::call_site())
+    }
+}
+
+/// Parse the options in `#[soa(option, other = "option")]`
+fn parse_soa_options(meta: &Meta) -> Result<Vec<Meta>, syn::Error> {
+    let error = || syn::Error::new_spanned(
+        meta,
+        format!("expected #[soa(option, ...)], got #[{}]", quote!(#meta)),
+    );
+
+    let list = match meta {
+        Meta::List(list) => list,
+        _ => return Err(error()),
+    };
+
+    let mut options = Vec::new();
+    for nested in &list.nested {
+        match nested {
+            NestedMeta::Meta(meta) => options.push(meta.clone()),
+            NestedMeta::Lit(_) => return Err(error()),
+        }
+    }
+    return Ok(options);
+}
+
+/// Try to parse a single option from `#[soa(...)]` as the alignment of the
+/// columns, returning `None` if this option is not `align = N`.
+fn parse_align(option: &Meta) -> Result<Option<usize>, syn::Error> {
+    let name_value = match option {
+        Meta::NameValue(name_value) if name_value.path.is_ident("align") => name_value,
+        _ => return Ok(None),
+    };
+
+    let value = match name_value.lit {
+        Lit::Int(ref int) => int.base10_parse::<usize>().ok(),
+        _ => None,
+    };
+
+    match value {
+        Some(value) if value.is_power_of_two() => Ok(Some(value)),
+        _ => {
+            let lit = &name_value.lit;
+            Err(syn::Error::new_spanned(
+                lit,
+                format!("expected #[soa(align = N)] with N a power of two, got `{}`", quote!(#lit)),
+            ))
+        }
+    }
+}
+
+/// Parse the list of traits in `#[soa_derive = "Traits, To, Derive"]`
+fn parse_soa_derive(meta: Meta) -> Result<Vec<Ident>, syn::Error> {
+    let mut derives = Vec::new();
+    match meta {
+        Meta::NameValue(MetaNameValue{lit: Lit::Str(string), ..}) => {
+            for value in string.value().split(',') {
+                let value = value.trim();
+                if value.is_empty() {
+                    continue;
+                }
+
+                match syn::parse_str::<Ident>(value) {
+                    Ok(mut ident) => {
+                        ident.set_span(string.span());
+                        derives.push(ident);
+                    }
+                    Err(_) => return Err(syn::Error::new(
+                        string.span(),
+                        format!("expected a list of traits to derive, got `{}`", value),
+                    )),
+                }
+            }
+        }
+        meta => return Err(syn::Error::new_spanned(
+            &meta,
+            format!("expected #[soa_derive = \"Traits, To, Derive\"], got #[{}]", quote!(#meta)),
+        )),
+    }
+    return Ok(derives);
+}
+
+/// Custom names for the generated types, set with `#[soa(vec = "Name")]`
+#[derive(Default)]
+pub struct CustomNames {
+    pub vec: Option<Ident>,
+    pub slice: Option<Ident>,
+    pub slice_mut: Option<Ident>,
+    pub ref_: Option<Ident>,
+    pub ref_mut: Option<Ident>,
+    pub ptr: Option<Ident>,
+    pub ptr_mut: Option<Ident>,
+}
+
+impl CustomNames {
+    /// Try to parse a single option from `#[soa(...)]` as a custom name,
+    /// returning `false` if this option is not setting a name.
+    fn parse_option(&mut self, option: &Meta) -> Result<bool, syn::Error> {
+        let name_value = match option {
+            Meta::NameValue(name_value) => name_value,
+            _ => return Ok(false),
+        };
+
+        let name = match name_value.path.get_ident().map(ToString::to_string).as_deref() {
+            Some("vec") => &mut self.vec,
+            Some("slice") => &mut self.slice,
+            Some("slice_mut") => &mut self.slice_mut,
+            Some("ref") => &mut self.ref_,
+            Some("ref_mut") => &mut self.ref_mut,
+            Some("ptr") => &mut self.ptr,
+            Some("ptr_mut") => &mut self.ptr_mut,
+            _ => return Ok(false),
+        };
+
+        let ident = match name_value.lit {
+            Lit::Str(ref string) => match string.parse::<Ident>() {
+                Ok(ident) => ident,
+                Err(_) => return Err(syn::Error::new_spanned(
+                    string,
+                    format!("expected a type name, got `{}`", string.value()),
+                )),
+            },
+            ref lit => return Err(syn::Error::new_spanned(
+                lit,
+                format!("expected a type name as a string, got `{}`", quote!(#lit)),
+            )),
+        };
+        *name = Some(ident);
+
+        return Ok(true);
+    }
+}
+
+/// Serialization format of the generated types, set with
+/// `#[soa(serde = "columns")]` or `#[soa(serde = "rows")]`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SerdeForm {
+    /// Serialize vectors and slices as a struct containing one sequence for
+    /// each field: `{"mass": [...], "name": [...]}`
+    Columns,
+    /// Serialize vectors and slices as a sequence of structs, in the same way
+    /// as a `Vec` of the input struct: `[{"mass": ..., "name": ...}, ...]`
+    Rows,
+}
+
+impl SerdeForm {
+    /// Try to parse a single option from `#[soa(...)]` as the serialization
+    /// format, returning `None` if this option is not `serde = "..."`.
+    fn parse_option(option: &Meta) -> Result<Option<SerdeForm>, syn::Error> {
+        let name_value = match option {
+            Meta::NameValue(name_value) if name_value.path.is_ident("serde") => name_value,
+            _ => return Ok(None),
+        };
+
+        let form = match name_value.lit {
+            Lit::Str(ref string) if string.value() == "columns" => SerdeForm::Columns,
+            Lit::Str(ref string) if string.value() == "rows" => SerdeForm::Rows,
+            ref lit => return Err(syn::Error::new_spanned(
+                lit,
+                format!("expected #[soa(serde = \"columns\")] or #[soa(serde = \"rows\")], got `{}`", quote!(#lit)),
+            )),
+        };
+
+        if !cfg!(feature = "serde") {
+            return Err(syn::Error::new_spanned(
+                option,
+                "#[soa(serde)] requires the `serde` feature of soa_derive",
+            ));
+        }
+
+        return Ok(Some(form));
+    }
+}
+
+/// Memory layout of the generated vector, set with
+/// `#[soa(layout = "separate")]` or `#[soa(layout = "single_alloc")]`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum VecLayout {
+    /// Store each field in a separate `Vec`
+    Separate,
+    /// Store all fields in a single allocation, with a shared length and
+    /// capacity
+    SingleAlloc,
+}
+
+impl VecLayout {
+    /// Try to parse a single option from `#[soa(...)]` as the vector layout,
+    /// returning `None` if this option is not `layout = "..."`.
+    fn parse_option(option: &Meta) -> Result<Option<VecLayout>, syn::Error> {
+        let name_value = match option {
+            Meta::NameValue(name_value) if name_value.path.is_ident("layout") => name_value,
+            _ => return Ok(None),
+        };
+
+        match name_value.lit {
+            Lit::Str(ref string) if string.value() == "separate" => Ok(Some(VecLayout::Separate)),
+            Lit::Str(ref string) if string.value() == "single_alloc" => Ok(Some(VecLayout::SingleAlloc)),
+            ref lit => Err(syn::Error::new_spanned(
+                lit,
+                format!("expected #[soa(layout = \"separate\")] or #[soa(layout = \"single_alloc\")], got `{}`", quote!(#lit)),
+            )),
+        }
+    }
+}
+
+/// Storage for the values in the generated lanes type, set with
+/// `#[soa(lanes = "array")]` or `#[soa(lanes = "simd")]`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LaneForm {
+    /// Store the values of each numeric field in an array `[T; N]`
+    Array,
+    /// Store the values of each numeric field in a `std::simd::Simd<T, N>`,
+    /// which requires a nightly compiler
+    Simd,
+}
+
+impl LaneForm {
+    /// Try to parse a single option from `#[soa(...)]` as the lanes storage,
+    /// returning `None` if this option is not `lanes = "..."`.
+    fn parse_option(option: &Meta) -> Result<Option<LaneForm>, syn::Error> {
+        let name_value = match option {
+            Meta::NameValue(name_value) if name_value.path.is_ident("lanes") => name_value,
+            _ => return Ok(None),
+        };
+
+        let form = match name_value.lit {
+            Lit::Str(ref string) if string.value() == "array" => LaneForm::Array,
+            Lit::Str(ref string) if string.value() == "simd" => LaneForm::Simd,
+            ref lit => return Err(syn::Error::new_spanned(
+                lit,
+                format!("expected #[soa(lanes = \"array\")] or #[soa(lanes = \"simd\")], got `{}`", quote!(#lit)),
+            )),
+        };
+
+        if form == LaneForm::Simd && !cfg!(feature = "nightly") {
+            return Err(syn::Error::new_spanned(
+                option,
+                "#[soa(lanes = \"simd\")] requires the `nightly` feature of soa_derive",
+            ));
+        }
+
+        return Ok(Some(form));
+    }
+}
+
+/// Methods of the generated vectors, which can not be used as the name of a
+/// column accessor.
+const VEC_METHODS: &[&str] = &[
+    "append", "apply_permutation", "as_mut_ptr", "as_mut_slice", "as_ptr",
+    "as_slice", "capacity", "check_columns_length", "clear", "dedup_by",
+    "dedup_by_key", "drain", "extend", "extend_columns",
+    "extend_from_slice", "from_columns", "from_raw_parts", "get", "get_mut",
+    "get_unchecked", "get_unchecked_mut", "index", "index_mut", "insert",
+    "into_columns", "is_empty", "iter", "iter_mut", "len", "new",
+    "par_chunks_mut", "par_iter", "par_iter_mut", "pop", "push",
+    "push_columns", "remove", "reserve", "reserve_exact", "resize",
+    "retain", "retain_mask", "retain_mut", "set_len", "shrink_to_fit",
+    "slice", "slice_mut", "sort_by", "sort_by_key", "sort_unstable_by",
+    "split_off", "swap_remove", "truncate", "with_capacity",
+];
+
+/// Is `ty` one of the primitive integer or floating point types? Only the
+/// exact type names are recognized, not aliases or paths like `std::f64`.
+fn is_primitive_number(ty: &Type) -> bool {
+    const NUMBERS: [&str; 14] = [
+        "i8", "i16", "i32", "i64", "i128", "isize",
+        "u8", "u16", "u32", "u64", "u128", "usize",
+        "f32", "f64",
+    ];
+
+    match ty {
+        Type::Path(path) if path.qself.is_none() => {
+            NUMBERS.iter().any(|number| path.path.is_ident(number))
+        }
+        _ => false,
+    }
+}
+
+/// Is `ty` one of the primitive floating point types? These are not `Ord`,
+/// and are sorted with `total_cmp` instead.
+pub fn is_primitive_float(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => {
+            path.path.is_ident("f32") || path.path.is_ident("f64")
+        }
+        _ => false,
+    }
+}
+
+/// Additional attributes to add to each one of the generated types
+#[derive(Default)]
+pub struct ExtraAttributes {
+    pub vec: Vec<Meta>,
+    pub slice: Vec<Meta>,
+    pub slice_mut: Vec<Meta>,
+    pub ref_: Vec<Meta>,
+    pub ref_mut: Vec<Meta>,
+    pub ptr: Vec<Meta>,
+    pub ptr_mut: Vec<Meta>,
+}
+
+impl ExtraAttributes {
+    /// Add the traits from `#[soa_derive]` to all the generated types. The
+    /// slice, reference and pointer types implement `Clone` manually, and can
+    /// not implement `Deserialize`, so these traits are only derived for the
+    /// vector.
+    fn add_derives(&mut self, derives: &[Ident]) {
+        if derives.is_empty() {
+            return;
+        }
+
+        self.vec.push(parse_quote!(derive(#(#derives),*)));
+
+        let derives = derives.iter()
+            .filter(|&name| name != "Clone")
+            .filter(|&name| name != "Deserialize")
+            .collect::<Vec<_>>();
+        if derives.is_empty() {
+            return;
+        }
+
+        let meta: Meta = parse_quote!(derive(#(#derives),*));
+        self.slice.push(meta.clone());
+        self.slice_mut.push(meta.clone());
+        self.ref_.push(meta.clone());
+        self.ref_mut.push(meta.clone());
+        self.ptr.push(meta.clone());
+        self.ptr_mut.push(meta);
+    }
+
+    /// Add the attribute from `#[soa_attr(Type, attribute)]` to the
+    /// corresponding generated type
+    fn add_soa_attr(&mut self, meta: &Meta) -> Result<(), syn::Error> {
+        let error = |meta: &Meta| syn::Error::new_spanned(
+            meta,
+            format!("expected #[soa_attr(Type, attribute)], got #[{}]", quote!(#meta)),
+        );
+
+        let list = match meta {
+            Meta::List(list) if list.nested.len() == 2 => list,
+            meta => return Err(error(meta)),
+        };
+
+        let target = match list.nested[0] {
+            NestedMeta::Meta(Meta::Path(ref path)) => path,
+            _ => return Err(error(meta)),
+        };
+        let attr = match list.nested[1] {
+            NestedMeta::Meta(ref attr) => attr.clone(),
+            NestedMeta::Lit(_) => return Err(error(meta)),
+        };
+
+        let attrs = match target.get_ident().map(ToString::to_string).as_deref() {
+            Some("Vec") => &mut self.vec,
+            Some("Slice") => &mut self.slice,
+            Some("SliceMut") => &mut self.slice_mut,
+            Some("Ref") => &mut self.ref_,
+            Some("RefMut") => &mut self.ref_mut,
+            Some("Ptr") => &mut self.ptr,
+            Some("PtrMut") => &mut self.ptr_mut,
+            _ => return Err(syn::Error::new_spanned(
+                target,
+                format!(
+                    "unknown type `{}` in #[soa_attr], expected one of Vec, Slice, SliceMut, Ref, RefMut, Ptr or PtrMut",
+                    quote!(#target)
+                ),
+            )),
+        };
+        attrs.push(attr);
+
+        return Ok(());
+    }
+
+    /// Does the generated vector derive the trait with the given `name`?
+    pub fn vec_derives(&self, name: &str) -> bool {
+        self.vec_derive_paths().any(|path| path.is_ident(name))
+    }
+
+    /// Get all the traits derived for the generated vector
+    pub fn vec_derive_paths(&self) -> impl Iterator<Item=&syn::Path> {
+        self.vec.iter().filter_map(|meta| match meta {
+            Meta::List(list) if list.path.is_ident("derive") => Some(&list.nested),
+            _ => None,
+        }).flatten().filter_map(|nested| match nested {
+            NestedMeta::Meta(Meta::Path(path)) => Some(path),
+            _ => None,
+        })
+    }
+}
+
+/// Get the type generated by `#[derive(StructOfArray)]` for the type `ty` of
+/// a `#[nested_soa]` field, where `suffix` is the default suffix of the
+/// generated type (`Vec`, `Slice`, `Ref`, ...). The type is accessed through
+/// the `StructOfArray` and `SoAIter` traits, so that nested types can use
+/// custom names. If `lifetime` is given, it is used for the `SoAIter` trait
+/// (`<Vector3 as SoAIter<'a>>::Ref`).
+pub fn nested_type(ty: &Type, suffix: &str, lifetime: Option<&str>) -> TokenStream {
+    let associated = match suffix {
+        "Vec" => Ident::new("Type", Span::call_site()),
+        _ => Ident::new(suffix, Span::call_site()),
+    };
+
+    if let Some(lifetime) = lifetime {
+        let lifetime = Lifetime::new(lifetime, Span::call_site());
+        quote!(<#ty as ::soa_derive::SoAIter<#lifetime>>::#associated)
+    } else {
+        quote!(<#ty as ::soa_derive::StructOfArray>::#associated)
     }
 }
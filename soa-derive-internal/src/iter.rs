@@ -1,13 +1,20 @@
 use proc_macro2::{Span, TokenStream};
-use syn::{Ident, Visibility};
+use syn::{Ident, Visibility, parse_quote};
 use quote::TokenStreamExt;
 use quote::quote;
 
-use crate::input::Input;
+use crate::input::{Input, VecLayout, nested_type};
 
 pub fn derive(input: &Input) -> TokenStream {
     let name = &input.name;
     let visibility = &input.visibility;
+    let allow_dead_code = &input.allow_dead_code();
+    // the iterators and `iter` functions are defined inside a module, and
+    // need to be visible from the parent module for private structs
+    let detail_visibility = &match *visibility {
+        Visibility::Inherited => quote!(pub(super)),
+        ref visibility => quote!(#visibility),
+    };
     let detail_mod = Ident::new(&format!("__detail_iter_{}", name.to_string().to_lowercase()), Span::call_site());
     let vec_name = &input.vec_name();
     let slice_name = &input.slice_name();
@@ -16,81 +23,396 @@ pub fn derive(input: &Input) -> TokenStream {
     let ref_mut_name = &input.ref_mut_name();
 
     let ref_doc_url = format!("[`{0}`](struct.{0}.html)", ref_name);
+    let vec_name_str = format!("Vec<{}>", name);
+    let vec_doc_url = format!("[`{0}`](struct.{0}.html)", vec_name);
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let generics_elided = &input.generics_with_lifetime("'_");
+    let (_, ty_generics_elided, _) = generics_elided.split_for_impl();
+    let generics_a = &input.generics_outliving("'a");
+    let (impl_generics_a, ty_generics_a, _) = generics_a.split_for_impl();
+    let generics_b = &input.generics_with_lifetime("'b");
+    let (_, ty_generics_b, _) = generics_b.split_for_impl();
+    let mut ref_slice_generics = input.generics_with_lifetime("'b");
+    ref_slice_generics.params.insert(0, parse_quote!('a));
+    let (impl_generics_ref_slice, _, _) = ref_slice_generics.split_for_impl();
+
     let ref_mut_doc_url = format!("[`{0}`](struct.{0}.html)", ref_mut_name);
 
-    let fields_names = &input.fields.iter()
-                                    .map(|field| field.ident.clone().unwrap())
-                                    .collect::<Vec<_>>();
+    let fields_names = &input.fields_names();
+    let fields_vars = &input.fields_vars();
     let first_field = &fields_names[0];
-
-    let fields_types = &input.fields.iter()
-                                    .map(|field| &field.ty)
-                                    .collect::<Vec<_>>();
-    let first_field_type = &fields_types[0];
-
-    let mut iter_type = quote!{
-        slice::Iter<'a, #first_field_type>
-    };
+    let first_field_var = &fields_vars[0];
+
+    let iter_types = &input.map_fields_nested_or(
+        |_, ty| quote!(<#ty as ::soa_derive::SoAIter<'a>>::Iter),
+        |_, ty| quote!(slice::Iter<'a, #ty>),
+    );
+    let iter_mut_types = &input.map_fields_nested_or(
+        |_, ty| quote!(<#ty as ::soa_derive::SoAIter<'a>>::IterMut),
+        |_, ty| quote!(slice::IterMut<'a, #ty>),
+    );
+    // slices are consumed by value when creating an iterator from them, which
+    // requires using `into_iter` for nested slices to keep the `'a` lifetime
+    let into_iters = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.into_iter()),
+        |name, _| quote!(self.#name.iter()),
+    );
+    let into_iters_mut = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.into_iter()),
+        |name, _| quote!(self.#name.iter_mut()),
+    );
+    let vec_into_iter_types = &input.map_fields_nested_or(
+        |_, ty| {
+            let vec_type = nested_type(ty, "Vec", None);
+            quote!(<#vec_type as IntoIterator>::IntoIter)
+        },
+        |_, ty| quote!(vec::IntoIter<#ty>),
+    );
+    let drain_types = &input.map_fields_nested_or(
+        |_, ty| quote!(<#ty as ::soa_derive::SoAIter<'a>>::Drain),
+        |_, ty| quote!(vec::Drain<'a, #ty>),
+    );
+    let restructure = input.restructure();
+
+    let mut iter_type = iter_types[0].clone();
     let mut iter_pat = quote!{
-        #first_field
+        #first_field_var
     };
     let mut create_iter = quote!{
         self.#first_field.iter()
     };
+    let mut create_into_iter = into_iters[0].clone();
 
-    let mut iter_mut_type = quote!{
-        slice::IterMut<'a, #first_field_type>
-    };
+    let mut iter_mut_type = iter_mut_types[0].clone();
     let mut create_iter_mut = quote!{
         self.#first_field.iter_mut()
     };
+    let mut create_into_iter_mut = into_iters_mut[0].clone();
+
+    let mut vec_into_iter_type = vec_into_iter_types[0].clone();
+    let mut create_vec_into_iter = quote!{
+        self.#first_field.into_iter()
+    };
+
+    let mut drain_type = drain_types[0].clone();
+    let mut create_drain = quote!{
+        self.#first_field.drain(start..end)
+    };
 
-    if fields_types.len() > 1 {
-        for field in &input.fields[1..] {
-            let field_name = &field.ident;
-            let field_type = &field.ty;
+    if fields_names.len() > 1 {
+        for (i, (field_name, field_var)) in fields_names.iter().zip(fields_vars).enumerate().skip(1) {
+            let field_iter_type = &iter_types[i];
+            let field_iter_mut_type = &iter_mut_types[i];
+            let field_into_iter = &into_iters[i];
+            let field_into_iter_mut = &into_iters_mut[i];
+            let field_vec_into_iter_type = &vec_into_iter_types[i];
+            let field_drain_type = &drain_types[i];
 
             iter_pat = quote!{
-                (#iter_pat, #field_name)
+                (#iter_pat, #field_var)
             };
 
             iter_type = quote!{
-                iter::Zip<#iter_type, slice::Iter<'a, #field_type>>
+                iter::Zip<#iter_type, #field_iter_type>
             };
 
             create_iter = quote!{
                 #create_iter.zip(self.#field_name.iter())
             };
 
+            create_into_iter = quote!{
+                #create_into_iter.zip(#field_into_iter)
+            };
+
             iter_mut_type = quote!{
-                iter::Zip<#iter_mut_type, slice::IterMut<'a, #field_type>>
+                iter::Zip<#iter_mut_type, #field_iter_mut_type>
             };
 
             create_iter_mut = quote!{
                 #create_iter_mut.zip(self.#field_name.iter_mut())
             };
+
+            create_into_iter_mut = quote!{
+                #create_into_iter_mut.zip(#field_into_iter_mut)
+            };
+
+            vec_into_iter_type = quote!{
+                iter::Zip<#vec_into_iter_type, #field_vec_into_iter_type>
+            };
+
+            create_vec_into_iter = quote!{
+                #create_vec_into_iter.zip(self.#field_name.into_iter())
+            };
+
+            drain_type = quote!{
+                iter::Zip<#drain_type, #field_drain_type>
+            };
+
+            create_drain = quote!{
+                #create_drain.zip(self.#field_name.drain(start..end))
+            };
+        }
+    }
+
+    let mut create_vec_iter = quote!(Iter(#create_iter));
+    let mut create_vec_iter_mut = quote!(IterMut(#create_iter_mut));
+    let mut create_vec_into_iter = quote!(IntoIter(#create_vec_into_iter));
+    let mut create_drain = quote!(Drain(#create_drain));
+
+    let mut into_iter_types = quote! {
+    /// An iterator moving the values out of a
+    #[doc = #vec_doc_url]
+    #[allow(missing_debug_implementations)]
+    #detail_visibility struct IntoIter #generics (pub(super) #vec_into_iter_type) #where_clause;
+
+    impl #impl_generics Iterator for IntoIter #ty_generics #where_clause {
+        type Item = #name #ty_generics;
+
+        #[inline]
+        fn next(&mut self) -> Option<#name #ty_generics> {
+            self.0.next().map(|#iter_pat| #restructure)
+        }
+
+        #[inline]
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.0.size_hint()
+        }
+    }
+
+    impl #impl_generics DoubleEndedIterator for IntoIter #ty_generics #where_clause {
+        #[inline]
+        fn next_back(&mut self) -> Option<#name #ty_generics> {
+            self.0.next_back().map(|#iter_pat| #restructure)
         }
     }
 
+    impl #impl_generics ExactSizeIterator for IntoIter #ty_generics #where_clause {
+        #[inline]
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    impl #impl_generics iter::FusedIterator for IntoIter #ty_generics #where_clause {}
+    };
+
+    let mut drain_types = quote! {
+    /// A draining iterator over a range of a
+    #[doc = #vec_doc_url]
+    #[allow(missing_debug_implementations)]
+    #detail_visibility struct Drain #generics_a (pub(super) #drain_type) #where_clause;
+
+    impl #impl_generics_a Iterator for Drain #ty_generics_a #where_clause {
+        type Item = #name #ty_generics;
+
+        #[inline]
+        fn next(&mut self) -> Option<#name #ty_generics> {
+            self.0.next().map(|#iter_pat| #restructure)
+        }
+
+        #[inline]
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.0.size_hint()
+        }
+    }
+
+    impl #impl_generics_a DoubleEndedIterator for Drain #ty_generics_a #where_clause {
+        #[inline]
+        fn next_back(&mut self) -> Option<#name #ty_generics> {
+            self.0.next_back().map(|#iter_pat| #restructure)
+        }
+    }
+
+    impl #impl_generics_a ExactSizeIterator for Drain #ty_generics_a #where_clause {
+        #[inline]
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    impl #impl_generics_a iter::FusedIterator for Drain #ty_generics_a #where_clause {}
+    };
+
+    // vectors with a single allocation iterate over their slices, and move
+    // values out of the allocation in `IntoIter` and `Drain`
+    if input.layout == VecLayout::SingleAlloc {
+        create_vec_iter = quote!(self.as_slice().into_iter());
+        create_vec_iter_mut = quote!(self.as_mut_slice().into_iter());
+        create_vec_into_iter = quote! {
+            let mut vec = self;
+            let end = vec.len;
+            // the values are now owned by the iterator
+            vec.len = 0;
+            IntoIter { vec: vec, start: 0, end: end }
+        };
+        create_drain = quote! {
+            let len = self.len();
+            assert!(start <= end, "slice index starts at {} but ends at {}", start, end);
+            assert!(end <= len, "range end index {} out of range for slice of length {}", end, len);
+            // the drained values are now owned by the iterator, and the
+            // remaining values are leaked if the iterator is leaked
+            self.len = start;
+            Drain { vec: self, start: start, end: end, front: start, back: end, len: len }
+        };
+
+        into_iter_types = quote! {
+            /// An iterator moving the values out of a
+            #[doc = #vec_doc_url]
+            #[allow(missing_debug_implementations)]
+            #detail_visibility struct IntoIter #generics #where_clause {
+                // the length of `vec` is set to zero, and the values between
+                // `start` and `end` are owned by the iterator
+                vec: #vec_name #ty_generics,
+                start: usize,
+                end: usize,
+            }
+
+            impl #impl_generics Iterator for IntoIter #ty_generics #where_clause {
+                type Item = #name #ty_generics;
+
+                #[inline]
+                fn next(&mut self) -> Option<#name #ty_generics> {
+                    if self.start == self.end {
+                        None
+                    } else {
+                        let value = unsafe { self.vec.as_mut_ptr().add(self.start).read() };
+                        self.start += 1;
+                        Some(value)
+                    }
+                }
+
+                #[inline]
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    let len = self.end - self.start;
+                    (len, Some(len))
+                }
+            }
+
+            impl #impl_generics DoubleEndedIterator for IntoIter #ty_generics #where_clause {
+                #[inline]
+                fn next_back(&mut self) -> Option<#name #ty_generics> {
+                    if self.start == self.end {
+                        None
+                    } else {
+                        self.end -= 1;
+                        unsafe { Some(self.vec.as_mut_ptr().add(self.end).read()) }
+                    }
+                }
+            }
+
+            impl #impl_generics ExactSizeIterator for IntoIter #ty_generics #where_clause {
+                #[inline]
+                fn len(&self) -> usize {
+                    self.end - self.start
+                }
+            }
+
+            impl #impl_generics iter::FusedIterator for IntoIter #ty_generics #where_clause {}
+
+            impl #impl_generics Drop for IntoIter #ty_generics #where_clause {
+                fn drop(&mut self) {
+                    let ptr = self.vec.as_mut_ptr();
+                    let count = self.end - self.start;
+                    unsafe {
+                        #(::std::ptr::drop_in_place(::std::ptr::slice_from_raw_parts_mut(ptr.#fields_names.add(self.start), count));)*
+                    }
+                }
+            }
+        };
+
+        drain_types = quote! {
+            /// A draining iterator over a range of a
+            #[doc = #vec_doc_url]
+            #[allow(missing_debug_implementations)]
+            #detail_visibility struct Drain #generics_a #where_clause {
+                vec: &'a mut #vec_name #ty_generics,
+                // the drained range
+                start: usize,
+                end: usize,
+                // the values between `front` and `back` are not yet yielded
+                front: usize,
+                back: usize,
+                // the length of the vector before draining
+                len: usize,
+            }
+
+            impl #impl_generics_a Iterator for Drain #ty_generics_a #where_clause {
+                type Item = #name #ty_generics;
+
+                #[inline]
+                fn next(&mut self) -> Option<#name #ty_generics> {
+                    if self.front == self.back {
+                        None
+                    } else {
+                        let value = unsafe { self.vec.as_mut_ptr().add(self.front).read() };
+                        self.front += 1;
+                        Some(value)
+                    }
+                }
+
+                #[inline]
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    let len = self.back - self.front;
+                    (len, Some(len))
+                }
+            }
+
+            impl #impl_generics_a DoubleEndedIterator for Drain #ty_generics_a #where_clause {
+                #[inline]
+                fn next_back(&mut self) -> Option<#name #ty_generics> {
+                    if self.front == self.back {
+                        None
+                    } else {
+                        self.back -= 1;
+                        unsafe { Some(self.vec.as_mut_ptr().add(self.back).read()) }
+                    }
+                }
+            }
+
+            impl #impl_generics_a ExactSizeIterator for Drain #ty_generics_a #where_clause {
+                #[inline]
+                fn len(&self) -> usize {
+                    self.back - self.front
+                }
+            }
+
+            impl #impl_generics_a iter::FusedIterator for Drain #ty_generics_a #where_clause {}
+
+            impl #impl_generics_a Drop for Drain #ty_generics_a #where_clause {
+                fn drop(&mut self) {
+                    let ptr = self.vec.as_mut_ptr();
+                    let count = self.back - self.front;
+                    let tail = self.len - self.end;
+                    unsafe {
+                        #(::std::ptr::drop_in_place(::std::ptr::slice_from_raw_parts_mut(ptr.#fields_names.add(self.front), count));)*
+                        #(::std::ptr::copy(ptr.#fields_names.add(self.end), ptr.#fields_names.add(self.start), tail);)*
+                    }
+                    self.vec.len = self.start + tail;
+                }
+            }
+        };
+    }
+
     let mut generated = quote! {
-        #[allow(non_snake_case, dead_code)]
+        #allow_dead_code
         mod #detail_mod {
             use super::*;
             use std::slice;
-            #[allow(unused_imports)]
+            use std::vec;
             use std::iter;
 
             #[allow(missing_debug_implementations)]
-            #visibility struct Iter<'a>(pub(super) #iter_type);
+            #detail_visibility struct Iter #generics_a (pub(super) #iter_type) #where_clause;
 
-            impl<'a> Iterator for Iter<'a> {
-                type Item = #ref_name<'a>;
+            impl #impl_generics_a Iterator for Iter #ty_generics_a #where_clause {
+                type Item = #ref_name #ty_generics_a;
 
                 #[inline]
-                fn next(&mut self) -> Option<#ref_name<'a>> {
+                fn next(&mut self) -> Option<#ref_name #ty_generics_a> {
                     self.0.next().and_then(|#iter_pat|
                         Some(#ref_name{
-                            #(#fields_names,)*
+                            #(#fields_names: #fields_vars,)*
                         })
                     )
                 }
@@ -101,47 +423,54 @@ pub fn derive(input: &Input) -> TokenStream {
                 }
             }
 
-            impl<'a> DoubleEndedIterator for Iter<'a> {
+            impl #impl_generics_a DoubleEndedIterator for Iter #ty_generics_a #where_clause {
 
                 #[inline]
-                fn next_back(&mut self) -> Option<#ref_name<'a>> {
+                fn next_back(&mut self) -> Option<#ref_name #ty_generics_a> {
                     self.0.next_back().and_then(|#iter_pat|
                         Some(#ref_name{
-                            #(#fields_names,)*
+                            #(#fields_names: #fields_vars,)*
                         })
                     )
                 }
             }
 
-            impl #vec_name {
+            impl #impl_generics_a ExactSizeIterator for Iter #ty_generics_a #where_clause {
+                #[inline]
+                fn len(&self) -> usize {
+                    self.0.len()
+                }
+            }
+
+            impl #impl_generics #vec_name #ty_generics #where_clause {
                 /// Get an iterator over the
                 #[doc = #ref_doc_url]
                 /// in this vector
-                #visibility fn iter(&self) -> Iter {
-                    Iter(#create_iter)
+                #detail_visibility fn iter(&self) -> Iter #ty_generics_elided {
+                    #create_vec_iter
                 }
             }
 
-            impl<'a> #slice_name<'a> {
+            impl #impl_generics_a #slice_name #ty_generics_a #where_clause {
                 /// Get an iterator over the
                 #[doc = #ref_doc_url]
                 /// in this slice.
-                #visibility fn iter(&self) -> Iter {
+                #detail_visibility fn iter(&self) -> Iter #ty_generics_elided {
                     Iter(#create_iter)
                 }
             }
 
             #[allow(missing_debug_implementations)]
-            #visibility struct IterMut<'a>(pub(super) #iter_mut_type);
+            #detail_visibility struct IterMut #generics_a (pub(super) #iter_mut_type) #where_clause;
 
-            impl<'a> Iterator for IterMut<'a> {
-                type Item = #ref_mut_name<'a>;
+            impl #impl_generics_a Iterator for IterMut #ty_generics_a #where_clause {
+                type Item = #ref_mut_name #ty_generics_a;
 
                 #[inline]
-                fn next(&mut self) -> Option<#ref_mut_name<'a>> {
+                fn next(&mut self) -> Option<#ref_mut_name #ty_generics_a> {
                     self.0.next().and_then(|#iter_pat|
                         Some(#ref_mut_name{
-                            #(#fields_names,)*
+                            #(#fields_names: #fields_vars,)*
                         })
                     )
                 }
@@ -152,102 +481,158 @@ pub fn derive(input: &Input) -> TokenStream {
                 }
             }
 
-            impl<'a> DoubleEndedIterator for IterMut<'a> {
+            impl #impl_generics_a DoubleEndedIterator for IterMut #ty_generics_a #where_clause {
 
                 #[inline]
-                fn next_back(&mut self) -> Option<#ref_mut_name<'a>> {
+                fn next_back(&mut self) -> Option<#ref_mut_name #ty_generics_a> {
                     self.0.next_back().and_then(|#iter_pat|
                         Some(#ref_mut_name{
-                            #(#fields_names,)*
+                            #(#fields_names: #fields_vars,)*
                         })
                     )
                 }
             }
 
-            impl #vec_name {
+            impl #impl_generics_a ExactSizeIterator for IterMut #ty_generics_a #where_clause {
+                #[inline]
+                fn len(&self) -> usize {
+                    self.0.len()
+                }
+            }
+
+            impl #impl_generics #vec_name #ty_generics #where_clause {
                 /// Get a mutable iterator over the
                 #[doc = #ref_mut_doc_url]
                 /// in this vector
-                #visibility fn iter_mut(&mut self) -> IterMut {
-                    IterMut(#create_iter_mut)
+                #detail_visibility fn iter_mut(&mut self) -> IterMut #ty_generics_elided {
+                    #create_vec_iter_mut
                 }
             }
 
-            impl<'a> #slice_mut_name<'a> {
+            impl #impl_generics_a #slice_mut_name #ty_generics_a #where_clause {
                 /// Get an iterator over the
                 #[doc = #ref_doc_url]
                 /// in this vector
-                #visibility fn iter(&mut self) -> Iter {
+                #detail_visibility fn iter(&mut self) -> Iter #ty_generics_elided {
                     Iter(#create_iter)
                 }
 
                 /// Get a mutable iterator over the
                 #[doc = #ref_mut_doc_url]
                 /// in this vector
-                #visibility fn iter_mut(&mut self) -> IterMut {
+                #detail_visibility fn iter_mut(&mut self) -> IterMut #ty_generics_elided {
                     IterMut(#create_iter_mut)
                 }
             }
-        }
-    };
 
-    if let Visibility::Public(_) = *visibility {
-        generated.append_all(quote!{
-            impl<'a> IntoIterator for #slice_name<'a> {
-                type Item = #ref_name<'a>;
-                type IntoIter = #detail_mod::Iter<'a>;
+            #into_iter_types
+
+            #drain_types
+
+            impl #impl_generics IntoIterator for #vec_name #ty_generics #where_clause {
+                type Item = #name #ty_generics;
+                type IntoIter = IntoIter #ty_generics;
 
                 fn into_iter(self) -> Self::IntoIter {
-                    #detail_mod::Iter(#create_iter)
+                    #create_vec_into_iter
                 }
             }
 
 
-            impl std::iter::FromIterator<#name> for #vec_name {
-                fn from_iter<T: IntoIterator<Item=#name>>(iter: T) -> Self {
-                    let mut result = #vec_name::new();
-                    for element in iter {
-                        #(
-                            (result.#fields_names).push(element.#fields_names);
-                        )*
-                    }
-                    result
+            impl #impl_generics #vec_name #ty_generics #where_clause {
+                /// Similar to [`
+                #[doc = #vec_name_str]
+                /// ::drain()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.drain),
+                /// removing the same `range` from all fields. The remaining
+                /// elements are shifted when the iterator is dropped, even if
+                /// it was not fully consumed.
+                #detail_visibility fn drain<R>(&mut self, range: R) -> Drain #ty_generics_elided
+                where
+                    R: ::std::ops::RangeBounds<usize>
+                {
+                    use std::ops::Bound;
+                    let start = match range.start_bound() {
+                        Bound::Included(&start) => start,
+                        Bound::Excluded(&start) => start.checked_add(1).expect("attempted to drain from after maximum usize"),
+                        Bound::Unbounded => 0,
+                    };
+                    let end = match range.end_bound() {
+                        Bound::Included(&end) => end.checked_add(1).expect("attempted to drain up to maximum usize"),
+                        Bound::Excluded(&end) => end,
+                        Bound::Unbounded => self.len(),
+                    };
+                    #create_drain
                 }
             }
+        }
+    };
 
-            impl<'a, 'b> IntoIterator for &'a #slice_name<'b> {
-                type Item = #ref_name<'a>;
-                type IntoIter = #detail_mod::Iter<'a>;
+    generated.append_all(quote!{
+        impl #impl_generics_a ::soa_derive::SoAIter<'a> for #name #ty_generics #where_clause {
+            type Slice = #slice_name #ty_generics_a;
+            type SliceMut = #slice_mut_name #ty_generics_a;
+            type Ref = #ref_name #ty_generics_a;
+            type RefMut = #ref_mut_name #ty_generics_a;
+            type Iter = #detail_mod::Iter #ty_generics_a;
+            type IterMut = #detail_mod::IterMut #ty_generics_a;
+            type Drain = #detail_mod::Drain #ty_generics_a;
+        }
 
-                fn into_iter(self) -> Self::IntoIter {
-                    #detail_mod::Iter(#create_iter)
+        // slices are always iterable by value, which is used to iterate over
+        // `#[nested_soa]` fields and by the parallel iterators
+        impl #impl_generics_a IntoIterator for #slice_name #ty_generics_a #where_clause {
+            type Item = #ref_name #ty_generics_a;
+            type IntoIter = #detail_mod::Iter #ty_generics_a;
+
+            fn into_iter(self) -> Self::IntoIter {
+                #detail_mod::Iter(#create_into_iter)
+            }
+        }
+
+        impl #impl_generics_a IntoIterator for #slice_mut_name #ty_generics_a #where_clause {
+            type Item = #ref_mut_name #ty_generics_a;
+            type IntoIter = #detail_mod::IterMut #ty_generics_a;
+
+            fn into_iter(self) -> Self::IntoIter {
+                #detail_mod::IterMut(#create_into_iter_mut)
+            }
+        }
+    });
+
+    if let Visibility::Public(_) = *visibility {
+        generated.append_all(quote!{
+            impl #impl_generics std::iter::FromIterator<#name #ty_generics> for #vec_name #ty_generics #where_clause {
+                fn from_iter<I: IntoIterator<Item=#name #ty_generics>>(iter: I) -> Self {
+                    let mut result = #vec_name::new();
+                    result.extend(iter);
+                    result
                 }
             }
 
-            impl<'a> IntoIterator for &'a #vec_name {
-                type Item = #ref_name<'a>;
-                type IntoIter = #detail_mod::Iter<'a>;
+            impl #impl_generics_ref_slice IntoIterator for &'a #slice_name #ty_generics_b #where_clause {
+                type Item = #ref_name #ty_generics_a;
+                type IntoIter = #detail_mod::Iter #ty_generics_a;
 
                 fn into_iter(self) -> Self::IntoIter {
                     #detail_mod::Iter(#create_iter)
                 }
             }
 
-            impl<'a> IntoIterator for #slice_mut_name<'a> {
-                type Item = #ref_mut_name<'a>;
-                type IntoIter = #detail_mod::IterMut<'a>;
+            impl #impl_generics_a IntoIterator for &'a #vec_name #ty_generics #where_clause {
+                type Item = #ref_name #ty_generics_a;
+                type IntoIter = #detail_mod::Iter #ty_generics_a;
 
                 fn into_iter(self) -> Self::IntoIter {
-                    #detail_mod::IterMut(#create_iter_mut)
+                    self.iter()
                 }
             }
 
-            impl<'a> IntoIterator for &'a mut #vec_name {
-                type Item = #ref_mut_name<'a>;
-                type IntoIter = #detail_mod::IterMut<'a>;
+            impl #impl_generics_a IntoIterator for &'a mut #vec_name #ty_generics #where_clause {
+                type Item = #ref_mut_name #ty_generics_a;
+                type IntoIter = #detail_mod::IterMut #ty_generics_a;
 
                 fn into_iter(self) -> Self::IntoIter {
-                    #detail_mod::IterMut(#create_iter_mut)
+                    self.iter_mut()
                 }
             }
         });
@@ -0,0 +1,208 @@
+use proc_macro2::{Span, TokenStream};
+use syn::{Ident, Visibility, parse_quote};
+use quote::quote;
+
+use crate::input::{Input, LaneForm};
+
+pub fn derive(input: &Input) -> TokenStream {
+    let form = match input.lanes {
+        Some(form) => form,
+        None => return TokenStream::new(),
+    };
+
+    let name = &input.name;
+    let visibility = &input.visibility;
+    let allow_dead_code = &input.allow_dead_code();
+    // the iterator is defined inside a module, and needs to be visible from
+    // the parent module for private structs
+    let detail_visibility = &match *visibility {
+        Visibility::Inherited => quote!(pub(super)),
+        ref visibility => quote!(#visibility),
+    };
+    let detail_mod = Ident::new(&format!("__detail_lanes_{}", name.to_string().to_lowercase()), Span::call_site());
+    let lanes_name = &input.lanes_name();
+    let slice_name = &input.slice_name();
+    let slice_mut_name = &input.slice_mut_name();
+
+    let doc_url = format!("[`{0}`](struct.{0}.html)", name);
+    let slice_doc_url = format!("[`{0}`](struct.{0}.html)", slice_name);
+    let lanes_doc_url = format!("[`{0}`](struct.{0}.html)", lanes_name);
+
+    let generics = &input.generics;
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let generics_elided = &input.generics_with_lifetime("'_");
+    let (_, ty_generics_elided, _) = generics_elided.split_for_impl();
+    let generics_a = &input.generics_outliving("'a");
+    let (impl_generics_a, ty_generics_a, _) = generics_a.split_for_impl();
+    let mut generics_lanes = generics_a.clone();
+    generics_lanes.params.push(parse_quote!(const N: usize));
+    let generics_lanes = &generics_lanes;
+    let (impl_generics_lanes, ty_generics_lanes, _) = generics_lanes.split_for_impl();
+
+    let lanes_fields = input.lanes_fields();
+    let fields_names = &lanes_fields.iter().map(|(name, _, _)| name).collect::<Vec<_>>();
+    let lanes_names = &lanes_fields.iter().map(|(_, name, _)| name).collect::<Vec<_>>();
+    let fields_docs = fields_names.iter()
+        .map(|field| format!("The values of `{}` in consecutive elements", quote!(#field)))
+        .collect::<Vec<_>>();
+
+    let lanes_types = lanes_fields.iter().map(|(_, _, ty)| match form {
+        LaneForm::Array => quote!([#ty; N]),
+        LaneForm::Simd => quote!(::std::simd::Simd<#ty, N>),
+    }).collect::<Vec<_>>();
+
+    let lanes_body = if input.is_tuple() {
+        quote! {
+            (
+                #(
+                    #[doc = #fields_docs]
+                    pub #lanes_types,
+                )*
+            );
+        }
+    } else {
+        quote! {
+            {
+                #(
+                    #[doc = #fields_docs]
+                    pub #lanes_names: #lanes_types,
+                )*
+            }
+        }
+    };
+
+    let load = fields_names.iter().map(|field| match form {
+        LaneForm::Array => quote! {
+            <[_; N] as ::std::convert::TryFrom<_>>::try_from(&slice.#field[..N]).expect("the slice should contain N elements")
+        },
+        LaneForm::Simd => quote! {
+            ::std::simd::Simd::from_slice(&slice.#field[..N])
+        },
+    }).collect::<Vec<_>>();
+
+    let store = fields_names.iter().zip(lanes_names).map(|(field, lanes)| match form {
+        LaneForm::Array => quote! {
+            slice.#field[..N].copy_from_slice(&self.#lanes);
+        },
+        LaneForm::Simd => quote! {
+            self.#lanes.copy_to_slice(&mut slice.#field[..N]);
+        },
+    }).collect::<Vec<_>>();
+
+    quote! {
+        /// Groups of `N` consecutive values from each field of
+        #[doc = #doc_url]
+        /// with a primitive numeric type, to be used with SIMD instructions.
+        /// The other fields are not included.
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        #visibility struct #lanes_name<const N: usize> #lanes_body
+
+        #allow_dead_code
+        impl<const N: usize> #lanes_name<N> {
+            /// Load the values from the first `N` elements of `slice`.
+            ///
+            /// # Panics
+            ///
+            /// This function panics if `slice` contains less than `N` elements.
+            pub fn from_slice #impl_generics (slice: #slice_name #ty_generics_elided) -> Self #where_clause {
+                assert!(slice.len() >= N, "the slice must contain at least N elements");
+                #lanes_name {
+                    #(#lanes_names: #load,)*
+                }
+            }
+
+            /// Store the values in the first `N` elements of `slice`.
+            ///
+            /// # Panics
+            ///
+            /// This function panics if `slice` contains less than `N` elements.
+            pub fn copy_to_slice #impl_generics (&self, slice: &mut #slice_mut_name #ty_generics_elided) #where_clause {
+                assert!(slice.len() >= N, "the slice must contain at least N elements");
+                #(#store)*
+            }
+        }
+
+        #allow_dead_code
+        mod #detail_mod {
+            use super::*;
+            use std::iter;
+
+            /// An iterator over a
+            #[doc = #slice_doc_url]
+            /// yielding
+            #[doc = #lanes_doc_url]
+            /// for groups of `N` consecutive elements, starting at the
+            /// beginning of the slice. The last elements which do not fill a
+            /// group are available with `remainder`.
+            #[allow(missing_debug_implementations)]
+            #detail_visibility struct Lanes #generics_lanes #where_clause {
+                slice: #slice_name #ty_generics_a,
+                remainder: #slice_name #ty_generics_a,
+            }
+
+            impl #impl_generics_lanes Lanes #ty_generics_lanes #where_clause {
+                /// Get the last elements of the slice, which do not fill a
+                /// whole group, to be processed one by one.
+                #detail_visibility fn remainder(&self) -> #slice_name #ty_generics_a {
+                    self.remainder
+                }
+            }
+
+            impl #impl_generics_lanes Iterator for Lanes #ty_generics_lanes #where_clause {
+                type Item = #lanes_name<N>;
+
+                #[inline]
+                fn next(&mut self) -> Option<#lanes_name<N>> {
+                    if self.slice.len() < N {
+                        return None;
+                    }
+                    let (lanes, rest) = self.slice.split_at(N);
+                    self.slice = rest;
+                    Some(#lanes_name::from_slice(lanes))
+                }
+
+                #[inline]
+                fn size_hint(&self) -> (usize, Option<usize>) {
+                    let count = self.slice.len() / N;
+                    (count, Some(count))
+                }
+            }
+
+            impl #impl_generics_lanes DoubleEndedIterator for Lanes #ty_generics_lanes #where_clause {
+                #[inline]
+                fn next_back(&mut self) -> Option<#lanes_name<N>> {
+                    if self.slice.len() < N {
+                        return None;
+                    }
+                    let (rest, lanes) = self.slice.split_at(self.slice.len() - N);
+                    self.slice = rest;
+                    Some(#lanes_name::from_slice(lanes))
+                }
+            }
+
+            impl #impl_generics_lanes ExactSizeIterator for Lanes #ty_generics_lanes #where_clause {}
+            impl #impl_generics_lanes iter::FusedIterator for Lanes #ty_generics_lanes #where_clause {}
+
+            impl #impl_generics_a #slice_name #ty_generics_a #where_clause {
+                /// Iterate over the numeric fields of this slice in groups of
+                /// `N` consecutive elements, stored in
+                #[doc = #lanes_doc_url]
+                /// . The last elements which do not fill a group are
+                /// available with `remainder()` on the iterator.
+                ///
+                /// # Panics
+                ///
+                /// This function panics if `N` is 0.
+                #detail_visibility fn lanes<const N: usize>(&self) -> Lanes #ty_generics_lanes {
+                    assert!(N != 0, "the number of lanes must be non-zero");
+                    let len = self.len() - self.len() % N;
+                    let (slice, remainder) = self.split_at(len);
+                    Lanes {
+                        slice: slice,
+                        remainder: remainder,
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
 #![warn(clippy::all, clippy::pedantic)]
 #![allow(clippy::needless_return, clippy::redundant_field_names)]
-#![allow(clippy::use_self, clippy::too_many_lines)]
+#![allow(clippy::use_self, clippy::too_many_lines, clippy::uninlined_format_args)]
+// let-else is not available on older compilers
+#![allow(clippy::manual_let_else)]
 // TODO: improve the code and make it simpler to read
 #![allow(clippy::cognitive_complexity)]
 
@@ -9,18 +11,33 @@ extern crate proc_macro;
 use proc_macro2::TokenStream;
 use quote::TokenStreamExt;
 
+mod array;
+mod chunks;
 mod index;
 mod input;
 mod iter;
+mod lanes;
 mod ptr;
+#[cfg(feature = "rayon")]
+mod rayon;
 mod refs;
+#[cfg(feature = "serde")]
+mod serde;
+mod single_alloc;
 mod slice;
+mod small_vec;
 mod vec;
 
-#[proc_macro_derive(StructOfArray, attributes(soa_derive))]
+// the `#[serde(...)]` attributes of the fields are forwarded to the generated
+// serialization code
+#[cfg_attr(not(feature = "serde"), proc_macro_derive(StructOfArray, attributes(soa_derive, soa_attr, soa, nested_soa)))]
+#[cfg_attr(feature = "serde", proc_macro_derive(StructOfArray, attributes(soa_derive, soa_attr, soa, nested_soa, serde)))]
 pub fn soa_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let ast = syn::parse(input).unwrap();
-    let input = input::Input::new(ast);
+    let ast = syn::parse_macro_input!(input as syn::DeriveInput);
+    let input = match input::Input::new(ast) {
+        Ok(input) => input,
+        Err(error) => return error.to_compile_error().into(),
+    };
 
     let mut generated = TokenStream::new();
     generated.append_all(vec::derive(&input));
@@ -30,6 +47,14 @@ pub fn soa_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     generated.append_all(slice::derive_mut(&input));
     generated.append_all(index::derive(&input));
     generated.append_all(iter::derive(&input));
+    generated.append_all(chunks::derive(&input));
+    generated.append_all(lanes::derive(&input));
+    generated.append_all(array::derive(&input));
+    generated.append_all(small_vec::derive(&input));
+    #[cfg(feature = "rayon")]
+    generated.append_all(rayon::derive(&input));
+    #[cfg(feature = "serde")]
+    generated.append_all(serde::derive(&input));
     generated.append_all(derive_trait(&input));
     generated.into()
 }
@@ -39,10 +64,15 @@ use quote::quote;
 fn derive_trait(input: &Input) -> TokenStream {
     let name = &input.name;
     let vec_name = &input.vec_name();
+    let ptr_name = &input.ptr_name();
+    let ptr_mut_name = &input.ptr_mut_name();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
     quote! {
-        impl soa_derive::StructOfArray for #name {
-            type Type = #vec_name;
+        impl #impl_generics soa_derive::StructOfArray for #name #ty_generics #where_clause {
+            type Type = #vec_name #ty_generics;
+            type Ptr = #ptr_name #ty_generics;
+            type PtrMut = #ptr_mut_name #ty_generics;
         }
     }
 }
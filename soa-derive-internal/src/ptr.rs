@@ -1,77 +1,159 @@
-use proc_macro2::TokenStream;
+use proc_macro2::{Span, TokenStream};
+use syn::Ident;
 use quote::quote;
 
-use crate::input::Input;
+use crate::input::{Input, VecLayout, nested_type};
 
 pub fn derive(input: &Input) -> TokenStream {
     let name = &input.name;
     let visibility = &input.visibility;
-    let other_derive = &input.derive_with_exceptions();
+    let allow_dead_code = &input.allow_dead_code();
+    let ptr_attrs = &input.attrs.ptr;
+    let ptr_mut_attrs = &input.attrs.ptr_mut;
     let vec_name = &input.vec_name();
     let ptr_name = &input.ptr_name();
     let ptr_mut_name = &input.ptr_mut_name();
     let ref_name = &input.ref_name();
     let ref_mut_name = &input.ref_mut_name();
 
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let generics_a = &input.generics_with_lifetime("'a");
+    let (impl_generics_a, ty_generics_a, _) = generics_a.split_for_impl();
+
     let doc_url = format!("[`{0}`](struct.{0}.html)", name);
     let ptr_doc_url = format!("[`{0}`](struct.{0}.html)", ptr_name);
     let ptr_mut_doc_url = format!("[`{0}`](struct.{0}.html)", ptr_mut_name);
     let ref_doc_url = format!("[`{0}`](struct.{0}.html)", ref_name);
     let ref_mut_doc_url = format!("[`{0}`](struct.{0}.html)", ref_mut_name);
 
-    let fields_names = input.fields.iter()
-                                   .map(|field| field.ident.clone().unwrap())
-                                   .collect::<Vec<_>>();
+    let fields_names = input.fields_names();
+    let fields_vars = &input.fields_vars();
+    let destructure_val = input.destructure(&quote!(val), &quote!(()));
+    let restructure = input.restructure();
     let fields_names_1 = &fields_names;
     let fields_names_2 = &fields_names;
 
-    let fields_types = &input.fields.iter()
-                                    .map(|field| &field.ty)
-                                    .collect::<Vec<_>>();
-
     let fields_doc = fields_names.iter()
-        .map(|field| format!("A pointer to a `{0}` from a [`{1}`](struct.{1}.html)", field, vec_name))
+        .map(|field| format!("A pointer to a `{0}` from a [`{1}`](struct.{1}.html)", quote!(#field), vec_name))
         .collect::<Vec<_>>();
 
     let fields_mut_doc = fields_names.iter()
-        .map(|field| format!("A mutable pointer to a `{0}` from a [`{1}`](struct.{1}.html)", field, vec_name))
+        .map(|field| format!("A mutable pointer to a `{0}` from a [`{1}`](struct.{1}.html)", quote!(#field), vec_name))
         .collect::<Vec<_>>();
 
+    let ptr_body = input.struct_body(&fields_doc, &input.map_fields_nested_or(
+        |_, ty| nested_type(ty, "Ptr", None),
+        |_, ty| quote!(*const #ty),
+    ));
+    let ptr_mut_body = input.struct_body(&fields_mut_doc, &input.map_fields_nested_or(
+        |_, ty| nested_type(ty, "PtrMut", None),
+        |_, ty| quote!(*mut #ty),
+    ));
+
+    let as_ptr = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.as_ptr()),
+        |name, _| quote!(self.#name),
+    );
+    let as_mut_ptr = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.as_mut_ptr()),
+        |name, _| quote!(self.#name),
+    );
+    let const_as_mut_ptr = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.as_mut_ptr()),
+        |name, _| quote!(self.#name as *mut _),
+    );
+
+    // vectors with a single allocation guarantee the alignment of each column
+    let alignment = if input.layout == VecLayout::SingleAlloc {
+        let fields_align = &input.align;
+        let columns = input.column_methods("ALIGN_");
+        let names = columns.iter().map(|(name, _, _)| name).collect::<Vec<_>>();
+        let types = columns.iter().map(|(_, ty, _)| ty).collect::<Vec<_>>();
+        let consts = columns.iter()
+            .map(|(_, _, method)| Ident::new(&method.to_string().to_uppercase(), Span::call_site()))
+            .collect::<Vec<_>>();
+
+        let align_docs = names.iter()
+            .map(|name| format!(
+                "Alignment of the `{0}` pointer in a [`{1}`](struct.{1}.html), including the one requested with `#[soa(align)]`",
+                quote!(#name), vec_name,
+            ))
+            .collect::<Vec<_>>();
+
+        let is_aligned_doc = format!(
+            "Check that all the pointers have the alignment of the corresponding column. This is always \
+            the case for pointers obtained from [`{0}::as_ptr()`](struct.{0}.html#method.as_ptr) and \
+            [`{0}::as_mut_ptr()`](struct.{0}.html#method.as_mut_ptr).",
+            vec_name,
+        );
+
+        let impl_alignment = |ptr_name: &Ident| quote! {
+            #allow_dead_code
+            impl #impl_generics #ptr_name #ty_generics #where_clause {
+                #(
+                    #[doc = #align_docs]
+                    pub const #consts: usize = ::soa_derive::single_alloc::aligned_layout::<#types>(#fields_align).align();
+                )*
+
+                #[doc = #is_aligned_doc]
+                // columns without alignment requirements have ALIGN_X == 1
+                #[allow(clippy::modulo_one)]
+                pub fn is_aligned(&self) -> bool {
+                    true #( && self.#names as usize % Self::#consts == 0)*
+                }
+            }
+        };
+
+        let ptr_alignment = impl_alignment(ptr_name);
+        let ptr_mut_alignment = impl_alignment(ptr_mut_name);
+        quote! {
+            #ptr_alignment
+            #ptr_mut_alignment
+        }
+    } else {
+        quote! {}
+    };
+
     quote! {
         /// An analog of a pointer to
         #[doc = #doc_url]
         /// with struct of array layout.
-        #other_derive
-        #[derive(Copy, Clone)]
-        #visibility struct #ptr_name {
-            #(
-                #[doc = #fields_doc]
-                pub #fields_names_1: *const #fields_types,
-            )*
-        }
+        #(#[#ptr_attrs])*
+        #visibility struct #ptr_name #generics #ptr_body
 
         /// An analog of a mutable pointer to
         #[doc = #doc_url]
         /// with struct of array layout.
-        #other_derive
-        #[derive(Copy, Clone)]
-        #visibility struct #ptr_mut_name {
-            #(
-                #[doc = #fields_mut_doc]
-                pub #fields_names_1: *mut #fields_types,
-            )*
+        #(#[#ptr_mut_attrs])*
+        #visibility struct #ptr_mut_name #generics #ptr_mut_body
+
+        impl #impl_generics Copy for #ptr_name #ty_generics #where_clause {}
+
+        impl #impl_generics Clone for #ptr_name #ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                *self
+            }
         }
 
-        #[allow(dead_code)]
-        impl #ptr_name {
+        impl #impl_generics Copy for #ptr_mut_name #ty_generics #where_clause {}
+
+        impl #impl_generics Clone for #ptr_mut_name #ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        #allow_dead_code
+        impl #impl_generics #ptr_name #ty_generics #where_clause {
             /// Convert a
             #[doc = #ptr_doc_url]
             /// to a
             #[doc = #ptr_mut_doc_url]
             /// ; *i.e.* do a `*const T as *mut T` transformation.
-            #visibility fn as_mut_ptr(&self) -> #ptr_mut_name {
+            #visibility fn as_mut_ptr(&self) -> #ptr_mut_name #ty_generics {
                 #ptr_mut_name {
-                    #(#fields_names_1: self.#fields_names_2 as *mut _, )*
+                    #(#fields_names_1: #const_as_mut_ptr, )*
                 }
             }
 
@@ -82,7 +164,7 @@ pub fn derive(input: &Input) -> TokenStream {
 
             /// Similar to [`*const T::as_ref()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.as_ref),
             /// with the same safety caveats.
-            pub unsafe fn as_ref<'a>(self) -> Option<#ref_name<'a>> {
+            pub unsafe fn as_ref<'a>(self) -> Option<#ref_name #ty_generics_a> {
                 if self.is_null() {
                     None
                 } else {
@@ -94,14 +176,14 @@ pub fn derive(input: &Input) -> TokenStream {
 
             /// Similar to [`*const T::offset()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.offset),
             /// with the same safety caveats.
-            pub unsafe fn offset(self, count: isize) -> #ptr_name {
+            pub unsafe fn offset(self, count: isize) -> #ptr_name #ty_generics {
                 #ptr_name {
                     #(#fields_names_1: self.#fields_names_2.offset(count), )*
                 }
             }
 
             /// Similar to [`*const T::offset()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.offset).
-            pub fn wrapping_offset(self, count: isize) -> #ptr_name {
+            pub fn wrapping_offset(self, count: isize) -> #ptr_name #ty_generics {
                 #ptr_name {
                     #(#fields_names_1: self.#fields_names_2.wrapping_offset(count), )*
                 }
@@ -109,7 +191,7 @@ pub fn derive(input: &Input) -> TokenStream {
 
             /// Similar to [`*const T::add()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.add),
             /// with the same safety caveats.
-            pub unsafe fn add(self, count: usize) -> #ptr_name {
+            pub unsafe fn add(self, count: usize) -> #ptr_name #ty_generics {
                 #ptr_name {
                     #(#fields_names_1: self.#fields_names_2.add(count), )*
                 }
@@ -117,21 +199,21 @@ pub fn derive(input: &Input) -> TokenStream {
 
             /// Similar to [`*const T::sub()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.sub),
             /// with the same safety caveats.
-            pub unsafe fn sub(self, count: usize) -> #ptr_name {
+            pub unsafe fn sub(self, count: usize) -> #ptr_name #ty_generics {
                 #ptr_name {
                     #(#fields_names_1: self.#fields_names_2.sub(count), )*
                 }
             }
 
             /// Similar to [`*const T::wrapping_add()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.wrapping_add).
-            pub fn wrapping_add(self, count: usize) -> #ptr_name {
+            pub fn wrapping_add(self, count: usize) -> #ptr_name #ty_generics {
                 #ptr_name {
                     #(#fields_names_1: self.#fields_names_2.wrapping_add(count), )*
                 }
             }
 
             /// Similar to [`*const T::wrapping_sub()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.wrapping_sub).
-            pub fn wrapping_sub(self, count: usize) -> #ptr_name {
+            pub fn wrapping_sub(self, count: usize) -> #ptr_name #ty_generics {
                 #ptr_name {
                     #(#fields_names_1: self.#fields_names_2.wrapping_sub(count), )*
                 }
@@ -139,39 +221,36 @@ pub fn derive(input: &Input) -> TokenStream {
 
             /// Similar to [`*const T::read()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.read),
             /// with the same safety caveats.
-            pub unsafe fn read(self) -> #name {
-                #name {
-                    #(#fields_names_1: self.#fields_names_2.read(), )*
-                }
+            pub unsafe fn read(self) -> #name #ty_generics {
+                #(let #fields_vars = self.#fields_names_1.read();)*
+                #restructure
             }
 
             /// Similar to [`*const T::read_volatile()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.read_volatile),
             /// with the same safety caveats.
-            pub unsafe fn read_volatile(self) -> #name {
-                #name {
-                    #(#fields_names_1: self.#fields_names_2.read_volatile(), )*
-                }
+            pub unsafe fn read_volatile(self) -> #name #ty_generics {
+                #(let #fields_vars = self.#fields_names_1.read_volatile();)*
+                #restructure
             }
 
             /// Similar to [`*const T::read_unaligned()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.read_unaligned),
             /// with the same safety caveats.
-            pub unsafe fn read_unaligned(self) -> #name {
-                #name {
-                    #(#fields_names_1: self.#fields_names_2.read_unaligned(), )*
-                }
+            pub unsafe fn read_unaligned(self) -> #name #ty_generics {
+                #(let #fields_vars = self.#fields_names_1.read_unaligned();)*
+                #restructure
             }
         }
 
-        #[allow(dead_code)]
-        impl #ptr_mut_name {
+        #allow_dead_code
+        impl #impl_generics #ptr_mut_name #ty_generics #where_clause {
             /// Convert a
             #[doc = #ptr_mut_doc_url]
             /// to a
             #[doc = #ptr_doc_url]
             /// ; *i.e.* do a `*mut T as *const T` transformation
-            #visibility fn as_ptr(&self) -> #ptr_name {
+            #visibility fn as_ptr(&self) -> #ptr_name #ty_generics {
                 #ptr_name {
-                    #(#fields_names_1: self.#fields_names_2, )*
+                    #(#fields_names_1: #as_ptr, )*
                 }
             }
 
@@ -182,7 +261,7 @@ pub fn derive(input: &Input) -> TokenStream {
 
             /// Similar to [`*mut T::as_ref()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.as_ref),
             /// with the same safety caveats.
-            pub unsafe fn as_ref<'a>(self) -> Option<#ref_name<'a>> {
+            pub unsafe fn as_ref<'a>(self) -> Option<#ref_name #ty_generics_a> {
                 if self.is_null() {
                     None
                 } else {
@@ -194,7 +273,7 @@ pub fn derive(input: &Input) -> TokenStream {
 
             /// Similar to [`*mut T::as_mut()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.as_mut),
             /// with the same safety caveats.
-            pub unsafe fn as_mut<'a>(self) -> Option<#ref_mut_name<'a>> {
+            pub unsafe fn as_mut<'a>(self) -> Option<#ref_mut_name #ty_generics_a> {
                 if self.is_null() {
                     None
                 } else {
@@ -206,14 +285,14 @@ pub fn derive(input: &Input) -> TokenStream {
 
             /// Similar to [`*mut T::offset()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.offset),
             /// with the same safety caveats.
-            pub unsafe fn offset(self, count: isize) -> #ptr_mut_name {
+            pub unsafe fn offset(self, count: isize) -> #ptr_mut_name #ty_generics {
                 #ptr_mut_name {
                     #(#fields_names_1: self.#fields_names_2.offset(count), )*
                 }
             }
 
             /// Similar to [`*mut T::wrapping_offset()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.wrapping_offset)
-            pub fn wrapping_offset(self, count: isize) -> #ptr_mut_name {
+            pub fn wrapping_offset(self, count: isize) -> #ptr_mut_name #ty_generics {
                 #ptr_mut_name {
                     #(#fields_names_1: self.#fields_names_2.wrapping_offset(count), )*
                 }
@@ -221,7 +300,7 @@ pub fn derive(input: &Input) -> TokenStream {
 
             /// Similar to [`*mut T::add()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.add),
             /// with the same safety caveats.
-            pub unsafe fn add(self, count: usize) -> #ptr_mut_name {
+            pub unsafe fn add(self, count: usize) -> #ptr_mut_name #ty_generics {
                 #ptr_mut_name {
                     #(#fields_names_1: self.#fields_names_2.add(count), )*
                 }
@@ -229,7 +308,7 @@ pub fn derive(input: &Input) -> TokenStream {
 
             /// Similar to [`*mut T::sub()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.sub),
             /// with the same safety caveats.
-            pub unsafe fn sub(self, count: usize) -> #ptr_mut_name {
+            pub unsafe fn sub(self, count: usize) -> #ptr_mut_name #ty_generics {
                 #ptr_mut_name {
                     #(#fields_names_1: self.#fields_names_2.sub(count), )*
                 }
@@ -237,7 +316,7 @@ pub fn derive(input: &Input) -> TokenStream {
 
             /// Similar to [`*mut T::wrapping_add()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.wrapping_add),
             /// with the same safety caveats.
-            pub fn wrapping_add(self, count: usize) -> #ptr_mut_name {
+            pub fn wrapping_add(self, count: usize) -> #ptr_mut_name #ty_generics {
                 #ptr_mut_name {
                     #(#fields_names_1: self.#fields_names_2.wrapping_add(count), )*
                 }
@@ -245,7 +324,7 @@ pub fn derive(input: &Input) -> TokenStream {
 
             /// Similar to [`*mut T::wrapping_sub()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.wrapping_sub),
             /// with the same safety caveats.
-            pub fn wrapping_sub(self, count: usize) -> #ptr_mut_name {
+            pub fn wrapping_sub(self, count: usize) -> #ptr_mut_name #ty_generics {
                 #ptr_mut_name {
                     #(#fields_names_1: self.#fields_names_2.wrapping_sub(count), )*
                 }
@@ -253,71 +332,71 @@ pub fn derive(input: &Input) -> TokenStream {
 
             /// Similar to [`*mut T::read()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.read),
             /// with the same safety caveats.
-            pub unsafe fn read(self) -> #name {
-                #name {
-                    #(#fields_names_1: self.#fields_names_2.read(), )*
-                }
+            pub unsafe fn read(self) -> #name #ty_generics {
+                #(let #fields_vars = self.#fields_names_1.read();)*
+                #restructure
             }
 
             /// Similar to [`*mut T::read_volatile()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.read_volatile),
             /// with the same safety caveats.
-            pub unsafe fn read_volatile(self) -> #name {
-                #name {
-                    #(#fields_names_1: self.#fields_names_2.read_volatile(), )*
-                }
+            pub unsafe fn read_volatile(self) -> #name #ty_generics {
+                #(let #fields_vars = self.#fields_names_1.read_volatile();)*
+                #restructure
             }
 
             /// Similar to [`*mut T::read_unaligned()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.read_unaligned),
             /// with the same safety caveats.
-            pub unsafe fn read_unaligned(self) -> #name {
-                #name {
-                    #(#fields_names_1: self.#fields_names_2.read_unaligned(), )*
-                }
+            pub unsafe fn read_unaligned(self) -> #name #ty_generics {
+                #(let #fields_vars = self.#fields_names_1.read_unaligned();)*
+                #restructure
             }
 
             /// Similar to [`*mut T::write()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.write),
             /// with the same safety caveats.
-            pub unsafe fn write(self, val: #name) {
-                #(self.#fields_names_1.write(val.#fields_names_2); )*
+            pub unsafe fn write(self, val: #name #ty_generics) {
+                #destructure_val
+                #(self.#fields_names_1.write(#fields_vars); )*
             }
 
             /// Similar to [`*mut T::write_volatile()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.write_volatile),
             /// with the same safety caveats.
-            pub unsafe fn write_volatile(self, val: #name) {
-                #(self.#fields_names_1.write_volatile(val.#fields_names_2); )*
+            pub unsafe fn write_volatile(self, val: #name #ty_generics) {
+                #destructure_val
+                #(self.#fields_names_1.write_volatile(#fields_vars); )*
             }
 
             /// Similar to [`*mut T::write_unaligned()`](https://doc.rust-lang.org/std/primitive.pointer.html#method.write_unaligned),
             /// with the same safety caveats.
-            pub unsafe fn write_unaligned(self, val: #name) {
-                #(self.#fields_names_1.write_unaligned(val.#fields_names_2); )*
+            pub unsafe fn write_unaligned(self, val: #name #ty_generics) {
+                #destructure_val
+                #(self.#fields_names_1.write_unaligned(#fields_vars); )*
             }
         }
 
-        #[allow(dead_code)]
-        impl<'a> #ref_name<'a> {
+        #allow_dead_code
+        impl #impl_generics_a #ref_name #ty_generics_a #where_clause {
             /// Convert a
             #[doc = #ref_doc_url]
             /// to a
             #[doc = #ptr_doc_url]
             /// ; *i.e.* do a `&T as *const T` transformation
-            #visibility fn as_ptr(&self) -> #ptr_name {
+            #visibility fn as_ptr(&self) -> #ptr_name #ty_generics {
                 #ptr_name {
-                    #(#fields_names_1: self.#fields_names_2, )*
+                    #(#fields_names_1: #as_ptr, )*
                 }
             }
         }
 
-        #[allow(dead_code)]
-        impl<'a> #ref_mut_name<'a> {
+        #allow_dead_code
+        impl #impl_generics_a #ref_mut_name #ty_generics_a #where_clause {
             /// Convert a
             #[doc = #ref_mut_doc_url]
             /// to a
             #[doc = #ptr_doc_url]
             /// ; *i.e.* do a `&mut T as *const T` transformation
-            #visibility fn as_ptr(&self) -> #ptr_name {
+            #visibility fn as_ptr(&self) -> #ptr_name #ty_generics {
                 #ptr_name {
-                    #(#fields_names_1: self.#fields_names_2, )*
+                    #(#fields_names_1: #as_ptr, )*
                 }
             }
 
@@ -326,11 +405,13 @@ pub fn derive(input: &Input) -> TokenStream {
             /// to a
             #[doc = #ptr_mut_doc_url]
             /// ; *i.e.* do a `&mut T as *mut T` transformation
-            #visibility fn as_mut_ptr(&mut self) -> #ptr_mut_name {
+            #visibility fn as_mut_ptr(&mut self) -> #ptr_mut_name #ty_generics {
                 #ptr_mut_name {
-                    #(#fields_names_1: self.#fields_names_2, )*
+                    #(#fields_names_1: #as_mut_ptr, )*
                 }
             }
         }
+
+        #alignment
     }
 }
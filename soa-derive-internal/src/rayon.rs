@@ -0,0 +1,429 @@
+use proc_macro2::{Span, TokenStream};
+use syn::{Ident, Visibility, WherePredicate, parse_quote};
+use quote::quote;
+
+use crate::input::{Input, VecLayout};
+
+pub fn derive(input: &Input) -> TokenStream {
+    let name = &input.name;
+    let visibility = &input.visibility;
+    let allow_dead_code = &input.allow_dead_code();
+    // the iterators and functions are defined inside a module, and need to be
+    // visible from the parent module for private structs
+    let detail_visibility = &match *visibility {
+        Visibility::Inherited => quote!(pub(super)),
+        ref visibility => quote!(#visibility),
+    };
+    let lowercase_name = name.to_string().to_lowercase();
+    let detail_mod = Ident::new(&format!("__detail_rayon_{}", lowercase_name), Span::call_site());
+    let chunks_mod = Ident::new(&format!("__detail_chunks_{}", lowercase_name), Span::call_site());
+    let vec_name = &input.vec_name();
+    let slice_name = &input.slice_name();
+    let slice_mut_name = &input.slice_mut_name();
+    let ref_name = &input.ref_name();
+    let ref_mut_name = &input.ref_mut_name();
+    let ptr_mut_name = &input.ptr_mut_name();
+
+    let slice_doc_url = format!("[`{0}`](struct.{0}.html)", slice_name);
+    let slice_mut_doc_url = format!("[`{0}`](struct.{0}.html)", slice_mut_name);
+
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let generics_elided = &input.generics_with_lifetime("'_");
+    let (_, ty_generics_elided, _) = generics_elided.split_for_impl();
+    let generics_a = &input.generics_outliving("'a");
+    let (impl_generics_a, ty_generics_a, _) = generics_a.split_for_impl();
+
+    // rayon requires the iterators, producers and items to be `Send`
+    let bounded_where_clause = |generics: &syn::Generics, predicates: Vec<WherePredicate>| {
+        let mut generics = generics.clone();
+        generics.make_where_clause().predicates.extend(predicates);
+        generics.where_clause
+    };
+    let iter_where_clause = &bounded_where_clause(generics_a, vec![
+        parse_quote!(#slice_name #ty_generics_a: Send),
+        parse_quote!(#ref_name #ty_generics_a: Send),
+    ]);
+    let iter_mut_where_clause = &bounded_where_clause(generics_a, vec![
+        parse_quote!(#slice_mut_name #ty_generics_a: Send),
+        parse_quote!(#ref_mut_name #ty_generics_a: Send),
+    ]);
+    let slice_mut_send_where_clause = &bounded_where_clause(generics_a, vec![
+        parse_quote!(#slice_mut_name #ty_generics_a: Send),
+    ]);
+
+    // moving the values out of a vector zips the parallel iterators over each
+    // column, and then rebuilds the struct in a `fn` pointer
+    let fields_names = &input.fields_names();
+    let fields_types = &input.fields_types();
+    let fields_vars = &input.fields_vars();
+    // the nested vectors are converted through the `SoAIntoParIter` trait,
+    // since the compiler does not use bounds on the nested vector type itself
+    let into_par_iter_types = &input.map_fields_nested_or(
+        |_, ty| quote!(<#ty as ::soa_derive::SoAIntoParIter>::Iter),
+        |_, ty| quote!(::soa_derive::rayon::vec::IntoIter<#ty>),
+    );
+    let into_par_iter_bounds = input.map_fields_nested_or(
+        |_, ty| quote!(#ty: ::soa_derive::SoAIntoParIter,),
+        |_, ty| quote!(#ty: Send,),
+    );
+    let mut zip_type = into_par_iter_types[0].clone();
+    let first_type = &fields_types[0];
+    let mut item_type = quote!(#first_type);
+    let first_var = &fields_vars[0];
+    let mut item_pat = quote!(#first_var);
+    // vectors with a single allocation are split into one `Vec` per field
+    let (split_columns, columns) = if input.layout == VecLayout::SingleAlloc {
+        (quote!(let (#(#fields_vars,)*) = self.into_columns();), fields_vars.iter().map(|var| quote!(#var)).collect())
+    } else {
+        (quote!(), fields_names.iter().map(|field| quote!(self.#field)).collect::<Vec<_>>())
+    };
+    let into_par_iters = columns.iter().zip(fields_types).enumerate().map(|(i, (column, ty))| {
+        if input.is_nested(i) {
+            quote!(<#ty as ::soa_derive::SoAIntoParIter>::into_par_iter(#column))
+        } else {
+            quote!(#column.into_par_iter())
+        }
+    }).collect::<Vec<_>>();
+    let mut create_zip = into_par_iters[0].clone();
+    for (i, into_par_iter) in into_par_iters.iter().enumerate().skip(1) {
+        let field_type = &into_par_iter_types[i];
+        let ty = &fields_types[i];
+        let var = &fields_vars[i];
+        zip_type = quote!(::soa_derive::rayon::iter::Zip<#zip_type, #field_type>);
+        item_type = quote!((#item_type, #ty));
+        item_pat = quote!((#item_pat, #var));
+        create_zip = quote!(#create_zip.zip(#into_par_iter));
+    }
+    let restructure = input.restructure();
+    // the `for<'c>` prevents errors when these bounds are never satisfied for
+    // concrete types, in which case `into_par_iter` is just not available
+    let mut into_par_iter_predicates = Vec::<WherePredicate>::new();
+    for bounds in &into_par_iter_bounds {
+        let bounds: syn::WhereClause = parse_quote!(where #bounds);
+        for bound in bounds.predicates {
+            into_par_iter_predicates.push(parse_quote!(for<'c> #bound));
+        }
+    }
+    into_par_iter_predicates.push(parse_quote!(for<'c> #name #ty_generics: Send));
+    let into_par_iter_where_clause = &bounded_where_clause(generics, into_par_iter_predicates);
+
+    quote! {
+        #allow_dead_code
+        mod #detail_mod {
+            use super::*;
+            use std::marker::PhantomData;
+            use ::soa_derive::rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+            use ::soa_derive::rayon::iter::plumbing::{self, Consumer, Producer, ProducerCallback, UnindexedConsumer};
+
+            /// A parallel iterator over the
+            #[doc = #slice_doc_url]
+            /// elements, splitting all fields at the same index.
+            #[allow(missing_debug_implementations)]
+            #detail_visibility struct ParIter #generics_a (#slice_name #ty_generics_a) #where_clause;
+
+            impl #impl_generics_a ParallelIterator for ParIter #ty_generics_a #iter_where_clause {
+                type Item = #ref_name #ty_generics_a;
+
+                fn drive_unindexed<C>(self, consumer: C) -> C::Result where C: UnindexedConsumer<Self::Item> {
+                    plumbing::bridge(self, consumer)
+                }
+
+                fn opt_len(&self) -> Option<usize> {
+                    Some(self.0.len())
+                }
+            }
+
+            impl #impl_generics_a IndexedParallelIterator for ParIter #ty_generics_a #iter_where_clause {
+                fn len(&self) -> usize {
+                    self.0.len()
+                }
+
+                fn drive<C>(self, consumer: C) -> C::Result where C: Consumer<Self::Item> {
+                    plumbing::bridge(self, consumer)
+                }
+
+                fn with_producer<CB>(self, callback: CB) -> CB::Output where CB: ProducerCallback<Self::Item> {
+                    callback.callback(IterProducer(self.0))
+                }
+            }
+
+            struct IterProducer #generics_a (#slice_name #ty_generics_a) #where_clause;
+
+            impl #impl_generics_a Producer for IterProducer #ty_generics_a #iter_where_clause {
+                type Item = #ref_name #ty_generics_a;
+                type IntoIter = <#name #ty_generics as ::soa_derive::SoAIter<'a>>::Iter;
+
+                fn into_iter(self) -> Self::IntoIter {
+                    self.0.into_iter()
+                }
+
+                fn split_at(self, index: usize) -> (Self, Self) {
+                    let (left, right) = self.0.split_at(index);
+                    (IterProducer(left), IterProducer(right))
+                }
+            }
+
+            /// A parallel iterator over the
+            #[doc = #slice_mut_doc_url]
+            /// elements, splitting all fields at the same index.
+            #[allow(missing_debug_implementations)]
+            #detail_visibility struct ParIterMut #generics_a (#slice_mut_name #ty_generics_a) #where_clause;
+
+            impl #impl_generics_a ParallelIterator for ParIterMut #ty_generics_a #iter_mut_where_clause {
+                type Item = #ref_mut_name #ty_generics_a;
+
+                fn drive_unindexed<C>(self, consumer: C) -> C::Result where C: UnindexedConsumer<Self::Item> {
+                    plumbing::bridge(self, consumer)
+                }
+
+                fn opt_len(&self) -> Option<usize> {
+                    Some(self.0.len())
+                }
+            }
+
+            impl #impl_generics_a IndexedParallelIterator for ParIterMut #ty_generics_a #iter_mut_where_clause {
+                fn len(&self) -> usize {
+                    self.0.len()
+                }
+
+                fn drive<C>(self, consumer: C) -> C::Result where C: Consumer<Self::Item> {
+                    plumbing::bridge(self, consumer)
+                }
+
+                fn with_producer<CB>(self, callback: CB) -> CB::Output where CB: ProducerCallback<Self::Item> {
+                    let mut slice = self.0;
+                    callback.callback(IterMutProducer {
+                        ptr: slice.as_mut_ptr(),
+                        len: slice.len(),
+                        marker: PhantomData,
+                    })
+                }
+            }
+
+            // mutable slices can not be split while keeping their full
+            // lifetime, so the producer is created from raw pointers instead
+            struct IterMutProducer #generics_a #where_clause {
+                ptr: #ptr_mut_name #ty_generics,
+                len: usize,
+                marker: PhantomData<#slice_mut_name #ty_generics_a>,
+            }
+
+            unsafe impl #impl_generics_a Send for IterMutProducer #ty_generics_a #slice_mut_send_where_clause {}
+
+            impl #impl_generics_a Producer for IterMutProducer #ty_generics_a #iter_mut_where_clause {
+                type Item = #ref_mut_name #ty_generics_a;
+                type IntoIter = <#name #ty_generics as ::soa_derive::SoAIter<'a>>::IterMut;
+
+                fn into_iter(self) -> Self::IntoIter {
+                    // SAFETY: the producers created by `split_at` do not overlap
+                    unsafe { #slice_mut_name::from_raw_parts_mut(self.ptr, self.len) }.into_iter()
+                }
+
+                fn split_at(self, index: usize) -> (Self, Self) {
+                    assert!(index <= self.len);
+                    let left = IterMutProducer {
+                        ptr: self.ptr,
+                        len: index,
+                        marker: PhantomData,
+                    };
+                    let right = IterMutProducer {
+                        // SAFETY: index is in bounds
+                        ptr: unsafe { self.ptr.add(index) },
+                        len: self.len - index,
+                        marker: PhantomData,
+                    };
+                    (left, right)
+                }
+            }
+
+            /// A parallel iterator over a
+            #[doc = #slice_mut_doc_url]
+            /// in non-overlapping mutable chunks.
+            #[allow(missing_debug_implementations)]
+            #detail_visibility struct ParChunksMut #generics_a #where_clause {
+                ptr: #ptr_mut_name #ty_generics,
+                len: usize,
+                chunk_size: usize,
+                marker: PhantomData<#slice_mut_name #ty_generics_a>,
+            }
+
+            unsafe impl #impl_generics_a Send for ParChunksMut #ty_generics_a #slice_mut_send_where_clause {}
+
+            impl #impl_generics_a ParallelIterator for ParChunksMut #ty_generics_a #slice_mut_send_where_clause {
+                type Item = #slice_mut_name #ty_generics_a;
+
+                fn drive_unindexed<C>(self, consumer: C) -> C::Result where C: UnindexedConsumer<Self::Item> {
+                    plumbing::bridge(self, consumer)
+                }
+
+                fn opt_len(&self) -> Option<usize> {
+                    Some(IndexedParallelIterator::len(self))
+                }
+            }
+
+            impl #impl_generics_a IndexedParallelIterator for ParChunksMut #ty_generics_a #slice_mut_send_where_clause {
+                fn len(&self) -> usize {
+                    self.len / self.chunk_size + usize::from(self.len % self.chunk_size != 0)
+                }
+
+                fn drive<C>(self, consumer: C) -> C::Result where C: Consumer<Self::Item> {
+                    plumbing::bridge(self, consumer)
+                }
+
+                fn with_producer<CB>(self, callback: CB) -> CB::Output where CB: ProducerCallback<Self::Item> {
+                    callback.callback(self)
+                }
+            }
+
+            impl #impl_generics_a Producer for ParChunksMut #ty_generics_a #slice_mut_send_where_clause {
+                type Item = #slice_mut_name #ty_generics_a;
+                type IntoIter = super::#chunks_mod::ChunksMut #ty_generics_a;
+
+                fn into_iter(self) -> Self::IntoIter {
+                    super::#chunks_mod::ChunksMut {
+                        ptr: self.ptr,
+                        len: self.len,
+                        chunk_size: self.chunk_size,
+                        marker: PhantomData,
+                    }
+                }
+
+                fn split_at(self, index: usize) -> (Self, Self) {
+                    let mid = ::std::cmp::min(index * self.chunk_size, self.len);
+                    let left = ParChunksMut {
+                        ptr: self.ptr,
+                        len: mid,
+                        chunk_size: self.chunk_size,
+                        marker: PhantomData,
+                    };
+                    let right = ParChunksMut {
+                        // SAFETY: mid is in bounds
+                        ptr: unsafe { self.ptr.add(mid) },
+                        len: self.len - mid,
+                        chunk_size: self.chunk_size,
+                        marker: PhantomData,
+                    };
+                    (left, right)
+                }
+            }
+
+            impl #impl_generics_a #slice_name #ty_generics_a #where_clause {
+                /// Get a parallel iterator over the elements in this slice.
+                #detail_visibility fn par_iter(&self) -> ParIter #ty_generics_a {
+                    ParIter(*self)
+                }
+            }
+
+            impl #impl_generics_a #slice_mut_name #ty_generics_a #where_clause {
+                /// Get a parallel iterator over the elements in this slice.
+                #detail_visibility fn par_iter(&self) -> ParIter #ty_generics_elided {
+                    ParIter(self.as_slice())
+                }
+
+                /// Get a mutable parallel iterator over the elements in this
+                /// slice.
+                #detail_visibility fn par_iter_mut(&mut self) -> ParIterMut #ty_generics_elided {
+                    ParIterMut(self.reborrow())
+                }
+
+                /// Similar to [`rayon::slice::ParallelSliceMut::par_chunks_mut()`](https://docs.rs/rayon/1/rayon/slice/trait.ParallelSliceMut.html#method.par_chunks_mut).
+                ///
+                /// # Panics
+                ///
+                /// This function panics if `chunk_size` is 0.
+                #detail_visibility fn par_chunks_mut(&mut self, chunk_size: usize) -> ParChunksMut #ty_generics_elided {
+                    assert!(chunk_size != 0, "chunk size must be non-zero");
+                    ParChunksMut {
+                        ptr: self.as_mut_ptr(),
+                        len: self.len(),
+                        chunk_size: chunk_size,
+                        marker: PhantomData,
+                    }
+                }
+            }
+
+            impl #impl_generics #vec_name #ty_generics #where_clause {
+                /// Get a parallel iterator over the elements in this vector.
+                #detail_visibility fn par_iter(&self) -> ParIter #ty_generics_elided {
+                    ParIter(self.as_slice())
+                }
+
+                /// Get a mutable parallel iterator over the elements in this
+                /// vector.
+                #detail_visibility fn par_iter_mut(&mut self) -> ParIterMut #ty_generics_elided {
+                    ParIterMut(self.as_mut_slice())
+                }
+
+                /// Similar to [`rayon::slice::ParallelSliceMut::par_chunks_mut()`](https://docs.rs/rayon/1/rayon/slice/trait.ParallelSliceMut.html#method.par_chunks_mut).
+                ///
+                /// # Panics
+                ///
+                /// This function panics if `chunk_size` is 0.
+                #detail_visibility fn par_chunks_mut(&mut self, chunk_size: usize) -> ParChunksMut #ty_generics_elided {
+                    assert!(chunk_size != 0, "chunk size must be non-zero");
+                    ParChunksMut {
+                        ptr: self.as_mut_ptr(),
+                        len: self.len(),
+                        chunk_size: chunk_size,
+                        marker: PhantomData,
+                    }
+                }
+            }
+
+            impl #impl_generics_a IntoParallelIterator for #slice_name #ty_generics_a #iter_where_clause {
+                type Item = #ref_name #ty_generics_a;
+                type Iter = ParIter #ty_generics_a;
+
+                fn into_par_iter(self) -> Self::Iter {
+                    ParIter(self)
+                }
+            }
+
+            impl #impl_generics_a IntoParallelIterator for &'a #vec_name #ty_generics #iter_where_clause {
+                type Item = #ref_name #ty_generics_a;
+                type Iter = ParIter #ty_generics_a;
+
+                fn into_par_iter(self) -> Self::Iter {
+                    ParIter(self.as_slice())
+                }
+            }
+
+            impl #impl_generics_a IntoParallelIterator for #slice_mut_name #ty_generics_a #iter_mut_where_clause {
+                type Item = #ref_mut_name #ty_generics_a;
+                type Iter = ParIterMut #ty_generics_a;
+
+                fn into_par_iter(self) -> Self::Iter {
+                    ParIterMut(self)
+                }
+            }
+
+            impl #impl_generics_a IntoParallelIterator for &'a mut #vec_name #ty_generics #iter_mut_where_clause {
+                type Item = #ref_mut_name #ty_generics_a;
+                type Iter = ParIterMut #ty_generics_a;
+
+                fn into_par_iter(self) -> Self::Iter {
+                    ParIterMut(self.as_mut_slice())
+                }
+            }
+
+            impl #impl_generics IntoParallelIterator for #vec_name #ty_generics #into_par_iter_where_clause {
+                type Item = #name #ty_generics;
+                type Iter = ::soa_derive::rayon::iter::Map<#zip_type, fn(#item_type) -> #name #ty_generics>;
+
+                fn into_par_iter(self) -> Self::Iter {
+                    #split_columns
+                    #create_zip.map(|#item_pat| #restructure)
+                }
+            }
+
+            impl #impl_generics ::soa_derive::SoAIntoParIter for #name #ty_generics #into_par_iter_where_clause {
+                type Iter = <#vec_name #ty_generics as IntoParallelIterator>::Iter;
+
+                fn into_par_iter(vec: #vec_name #ty_generics) -> Self::Iter {
+                    vec.into_par_iter()
+                }
+            }
+        }
+    }
+}
@@ -1,72 +1,91 @@
 use proc_macro2::TokenStream;
 use quote::quote;
 
-use crate::input::Input;
+use crate::input::{Input, nested_type};
 
 pub fn derive(input: &Input) -> TokenStream {
     let name = &input.name;
     let visibility = &input.visibility;
-    let other_derive = &input.derive_with_exceptions();
+    let allow_dead_code = &input.allow_dead_code();
+    let ref_attrs = &input.attrs.ref_;
+    let ref_mut_attrs = &input.attrs.ref_mut;
     let vec_name = &input.vec_name();
     let ref_name = &input.ref_name();
     let ref_mut_name = &input.ref_mut_name();
 
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let generics_elided = &input.generics_with_lifetime("'_");
+    let (_, ty_generics_elided, _) = generics_elided.split_for_impl();
+    let generics_a = &input.generics_outliving("'a");
+    let (impl_generics_a, ty_generics_a, _) = generics_a.split_for_impl();
+
     let doc_url = format!("[`{0}`](struct.{0}.html)", name);
     let ref_doc_url = format!("[`{0}`](struct.{0}.html)", ref_name);
     let ref_mut_doc_url = format!("[`{0}`](struct.{0}.html)", ref_mut_name);
 
-    let fields_names = input.fields.iter()
-                                   .map(|field| field.ident.clone().unwrap())
-                                   .collect::<Vec<_>>();
+    let fields_names = input.fields_names();
+    let destructure_ref = input.destructure(&quote!(self), &quote!(&()));
+    // a mutable reference to a zero-sized value can be leaked without allocating
+    let destructure_mut = input.destructure(&quote!(self), &quote!(::std::boxed::Box::leak(::std::boxed::Box::new(()))));
     let fields_names_1 = &fields_names;
-    let fields_names_2 = &fields_names;
-
-    let fields_types = &input.fields.iter()
-                                    .map(|field| &field.ty)
-                                    .collect::<Vec<_>>();
 
     let fields_doc = fields_names.iter()
-                                 .map(|field| format!("A reference to a `{0}` from a [`{1}`](struct.{1}.html)", field, vec_name))
+                                 .map(|field| format!("A reference to a `{0}` from a [`{1}`](struct.{1}.html)", quote!(#field), vec_name))
                                  .collect::<Vec<_>>();
 
     let fields_mut_doc = fields_names.iter()
-                                     .map(|field| format!("A mutable reference to a `{0}` from a [`{1}`](struct.{1}.html)", field, vec_name))
+                                     .map(|field| format!("A mutable reference to a `{0}` from a [`{1}`](struct.{1}.html)", quote!(#field), vec_name))
                                      .collect::<Vec<_>>();
 
+    let ref_body = input.struct_body(&fields_doc, &input.map_fields_nested_or(
+        |_, ty| nested_type(ty, "Ref", Some("'a")),
+        |_, ty| quote!(&'a #ty),
+    ));
+    let ref_mut_body = input.struct_body(&fields_mut_doc, &input.map_fields_nested_or(
+        |_, ty| nested_type(ty, "RefMut", Some("'a")),
+        |_, ty| quote!(&'a mut #ty),
+    ));
+
+    let fields_vars_as_ref = &input.fields_vars().iter().enumerate()
+        .map(|(i, var)| if input.is_nested(i) { quote!(#var.as_ref()) } else { quote!(#var) })
+        .collect::<Vec<_>>();
+    let fields_vars_as_mut = &input.fields_vars().iter().enumerate()
+        .map(|(i, var)| if input.is_nested(i) { quote!(#var.as_mut()) } else { quote!(#var) })
+        .collect::<Vec<_>>();
+
     quote! {
         /// A reference to a
         #[doc = #doc_url]
         /// with struct of array layout.
-        #other_derive
-        #[derive(Copy, Clone)]
-        #visibility struct #ref_name<'a> {
-            #(
-                #[doc = #fields_doc]
-                pub #fields_names_1: &'a #fields_types,
-            )*
-        }
+        #(#[#ref_attrs])*
+        #visibility struct #ref_name #generics_a #ref_body
 
         /// A mutable reference to a
         #[doc = #doc_url]
         /// with struct of array layout.
-        #other_derive
-        #visibility struct #ref_mut_name<'a> {
-            #(
-                #[doc = #fields_mut_doc]
-                pub #fields_names_1: &'a mut #fields_types,
-            )*
+        #(#[#ref_mut_attrs])*
+        #visibility struct #ref_mut_name #generics_a #ref_mut_body
+
+        impl #impl_generics_a Copy for #ref_name #ty_generics_a #where_clause {}
+
+        impl #impl_generics_a Clone for #ref_name #ty_generics_a #where_clause {
+            fn clone(&self) -> Self {
+                *self
+            }
         }
 
-        #[allow(dead_code)]
-        impl #name {
+        #allow_dead_code
+        impl #impl_generics #name #ty_generics #where_clause {
             /// Create a
             #[doc = #ref_doc_url]
             /// from a borrowed
             #[doc = #doc_url]
             /// .
-            #visibility fn as_ref(&self) -> #ref_name {
+            #visibility fn as_ref(&self) -> #ref_name #ty_generics_elided {
+                #destructure_ref
                 #ref_name {
-                    #(#fields_names_1: & self.#fields_names_2, )*
+                    #(#fields_names_1: #fields_vars_as_ref, )*
                 }
             }
 
@@ -75,9 +94,10 @@ pub fn derive(input: &Input) -> TokenStream {
             /// from a mutably borrowed
             #[doc = #doc_url]
             /// .
-            #visibility fn as_mut(&mut self) -> #ref_mut_name {
+            #visibility fn as_mut(&mut self) -> #ref_mut_name #ty_generics_elided {
+                #destructure_mut
                 #ref_mut_name {
-                    #(#fields_names_1: &mut self.#fields_names_2, )*
+                    #(#fields_names_1: #fields_vars_as_mut, )*
                 }
             }
         }
@@ -0,0 +1,256 @@
+use proc_macro2::{Span, TokenStream};
+use syn::{Generics, Ident, WherePredicate, parse_quote};
+use quote::quote;
+
+use crate::input::{Input, SerdeForm, VecLayout, nested_type};
+
+pub fn derive(input: &Input) -> TokenStream {
+    let form = match input.serde {
+        Some(form) => form,
+        None => return TokenStream::new(),
+    };
+
+    let name = &input.name;
+    let vec_name = &input.vec_name();
+    let slice_name = &input.slice_name();
+    let ref_name = &input.ref_name();
+
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    let generics_a = &input.generics_with_lifetime("'a");
+    let (impl_generics_a, ty_generics_a, _) = generics_a.split_for_impl();
+    let generics_b = &input.generics_with_lifetime("'b");
+    let (_, ty_generics_b, _) = generics_b.split_for_impl();
+    let generics_de = &input.generics_with_lifetime("'de");
+    let (impl_generics_de, _, _) = generics_de.split_for_impl();
+
+    let fields_names = &input.fields_names();
+
+    let serialize_bounds = |nested_suffix: &str, lifetime: Option<&str>| -> Vec<WherePredicate> {
+        input.map_fields_nested_or(
+            |_, ty| {
+                let nested = nested_type(ty, nested_suffix, lifetime);
+                quote!(#nested: ::soa_derive::serde::Serialize)
+            },
+            |_, ty| quote!(#ty: ::soa_derive::serde::Serialize),
+        ).into_iter().map(|bound| parse_quote!(#bound)).collect()
+    };
+
+    let ref_where_clause = with_predicates(generics_a, serialize_bounds("Ref", Some("'a")));
+    let name_str = name.to_string();
+    let serialize_ref = if input.is_fieldless() {
+        quote!(::soa_derive::serde::Serializer::serialize_unit_struct(serializer, #name_str))
+    } else {
+        let fields = fields_names.iter().map(|field| quote!(&self.#field)).collect::<Vec<_>>();
+        serialize_struct(input, &name_str, &fields)
+    };
+    let mut generated = quote! {
+        impl #impl_generics_a ::soa_derive::serde::Serialize for #ref_name #ty_generics_a #ref_where_clause {
+            fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+                where S: ::soa_derive::serde::Serializer
+            {
+                #serialize_ref
+            }
+        }
+    };
+
+    // `#[soa(serde = "columns")]` serializes each field as a sequence, and
+    // checks that all the sequences have the same length when deserializing
+    if form == SerdeForm::Columns {
+        let columns = &fields_names.iter().map(|field| quote!(&self.#field)).collect::<Vec<_>>();
+        let serialize_columns = serialize_struct(input, &vec_name.to_string(), columns);
+        // vectors with a single allocation only give access to their columns
+        // through slices
+        let serialize_vec_columns = if input.layout == VecLayout::SingleAlloc {
+            let columns = &fields_names.iter().map(|field| quote!(&self.as_slice().#field)).collect::<Vec<_>>();
+            serialize_struct(input, &vec_name.to_string(), columns)
+        } else {
+            serialize_columns.clone()
+        };
+
+        let slice_where_clause = with_predicates(generics_a, serialize_bounds("Slice", Some("'a")));
+        let vec_where_clause = with_predicates(generics, serialize_bounds("Vec", None));
+
+        let deserialize_bounds = input.map_fields_nested_or(
+            |_, ty| {
+                let vec_type = nested_type(ty, "Vec", None);
+                quote!(#vec_type: ::soa_derive::serde::Deserialize<'de>)
+            },
+            |_, ty| quote!(#ty: ::soa_derive::serde::Deserialize<'de>),
+        );
+        let deserialize_where_clause = with_predicates(
+            generics,
+            deserialize_bounds.iter().map(|bound| parse_quote!(#bound)).collect(),
+        );
+        let serde_bounds = quote!(#(#deserialize_bounds,)*).to_string();
+        let vec_name_str = vec_name.to_string();
+
+        let columns_types = input.map_fields_nested_or(
+            |_, ty| nested_type(ty, "Vec", None),
+            |_, ty| quote!(::std::vec::Vec<#ty>),
+        );
+        let columns_attrs = &serde_attrs(input);
+        let where_clause = &generics.where_clause;
+        let columns_body = if input.is_tuple() {
+            quote!{ ( #(#columns_attrs #columns_types,)* ) #where_clause; }
+        } else {
+            quote!{ #where_clause { #(#columns_attrs #fields_names: #columns_types,)* } }
+        };
+
+        // structs without fields only have a single `len` column, and no
+        // `from_columns` function
+        let from_columns = if input.is_fieldless() {
+            quote!(Ok(#vec_name { len: columns.len }))
+        } else {
+            quote! {
+                #vec_name::from_columns(#(columns.#fields_names),*)
+                    .map_err(<D::Error as ::soa_derive::serde::de::Error>::custom)
+            }
+        };
+
+        generated.extend(quote! {
+            impl #impl_generics_a ::soa_derive::serde::Serialize for #slice_name #ty_generics_a #slice_where_clause {
+                fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+                    where S: ::soa_derive::serde::Serializer
+                {
+                    #serialize_columns
+                }
+            }
+
+            impl #impl_generics ::soa_derive::serde::Serialize for #vec_name #ty_generics #vec_where_clause {
+                fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+                    where S: ::soa_derive::serde::Serializer
+                {
+                    #serialize_vec_columns
+                }
+            }
+
+            impl #impl_generics_de ::soa_derive::serde::Deserialize<'de> for #vec_name #ty_generics #deserialize_where_clause {
+                fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+                    where D: ::soa_derive::serde::Deserializer<'de>
+                {
+                    #[derive(::soa_derive::serde::Deserialize)]
+                    #[serde(crate = "::soa_derive::serde", rename = #vec_name_str, bound(deserialize = #serde_bounds))]
+                    struct Columns #generics #columns_body
+
+                    let columns = <Columns #ty_generics as ::soa_derive::serde::Deserialize<'de>>::deserialize(deserializer)?;
+                    #from_columns
+                }
+            }
+        });
+    } else {
+        // `#[soa(serde = "rows")]` uses the same format as `Vec<Foo>`
+        let slice_where_clause = with_predicates(generics_a, vec![
+            parse_quote!(#ref_name #ty_generics_a: ::soa_derive::serde::Serialize),
+        ]);
+        // the nested references types are not well formed for all `'b` when
+        // they are accessed through `SoAIter<'b>`, so the bounds for nested
+        // fields use the reference type of this struct instead
+        let vec_where_clause = with_predicates(generics, input.map_fields_nested_or(
+            |_, _| quote!(for<'b> #ref_name #ty_generics_b: ::soa_derive::serde::Serialize),
+            |_, ty| quote!(#ty: ::soa_derive::serde::Serialize),
+        ).into_iter().map(|bound| parse_quote!(#bound)).collect());
+        let deserialize_where_clause = with_predicates(generics, vec![
+            parse_quote!(#name #ty_generics: ::soa_derive::serde::Deserialize<'de>),
+        ]);
+        let visitor_where_clause = &deserialize_where_clause;
+        let where_clause = &generics.where_clause;
+        let expecting = format!("a sequence of {}", name);
+
+        generated.extend(quote! {
+            impl #impl_generics_a ::soa_derive::serde::Serialize for #slice_name #ty_generics_a #slice_where_clause {
+                fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+                    where S: ::soa_derive::serde::Serializer
+                {
+                    serializer.collect_seq(::std::iter::IntoIterator::into_iter(*self))
+                }
+            }
+
+            impl #impl_generics ::soa_derive::serde::Serialize for #vec_name #ty_generics #vec_where_clause {
+                fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+                    where S: ::soa_derive::serde::Serializer
+                {
+                    serializer.collect_seq(self.iter())
+                }
+            }
+
+            impl #impl_generics_de ::soa_derive::serde::Deserialize<'de> for #vec_name #ty_generics #deserialize_where_clause {
+                fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+                    where D: ::soa_derive::serde::Deserializer<'de>
+                {
+                    struct Visitor #generics (::std::marker::PhantomData<fn() -> #vec_name #ty_generics>) #where_clause;
+
+                    impl #impl_generics_de ::soa_derive::serde::de::Visitor<'de> for Visitor #ty_generics #visitor_where_clause {
+                        type Value = #vec_name #ty_generics;
+
+                        fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                            formatter.write_str(#expecting)
+                        }
+
+                        fn visit_seq<A>(self, mut seq: A) -> ::std::result::Result<Self::Value, A::Error>
+                            where A: ::soa_derive::serde::de::SeqAccess<'de>
+                        {
+                            // do not trust the size hint too much, it could
+                            // come from a malicious input
+                            let capacity = ::std::cmp::min(seq.size_hint().unwrap_or(0), 4096);
+                            let mut vec = #vec_name::with_capacity(capacity);
+                            while let Some(value) = seq.next_element::<#name #ty_generics>()? {
+                                vec.push(value);
+                            }
+                            Ok(vec)
+                        }
+                    }
+
+                    deserializer.deserialize_seq(Visitor(::std::marker::PhantomData))
+                }
+            }
+        });
+    }
+
+    generated
+}
+
+/// Add the `predicates` to the where clause of `generics`
+fn with_predicates(generics: &Generics, predicates: Vec<WherePredicate>) -> Option<syn::WhereClause> {
+    let mut generics = generics.clone();
+    generics.make_where_clause().predicates.extend(predicates);
+    generics.where_clause
+}
+
+/// Get the `#[serde(...)]` attributes of the fields of the input struct, which
+/// are forwarded to the helper structs used for serialization. Structs without
+/// fields have a single `len` column without attributes.
+fn serde_attrs(input: &Input) -> Vec<TokenStream> {
+    if input.is_fieldless() {
+        return vec![TokenStream::new()];
+    }
+
+    input.fields.iter().map(|field| {
+        let attrs = field.attrs.iter().filter(|attr| attr.path.is_ident("serde"));
+        quote!(#(#attrs)*)
+    }).collect()
+}
+
+/// Generate code serializing `values` as the fields of a struct named `name`,
+/// in the same way `#[derive(Serialize)]` would for the input struct. The
+/// values are serialized through a helper struct deriving `Serialize`, with the
+/// `#[serde(...)]` attributes of the corresponding fields.
+fn serialize_struct(input: &Input, name: &str, values: &[TokenStream]) -> TokenStream {
+    let fields_names = &input.fields_names();
+    let attrs = &serde_attrs(input);
+    let types = &(0..values.len()).map(|i| Ident::new(&format!("T{}", i), Span::call_site())).collect::<Vec<_>>();
+
+    let helper = if input.is_tuple() {
+        quote!(struct Fields<#(#types),*>(#(#attrs #types),*);)
+    } else {
+        quote!(struct Fields<#(#types),*> { #(#attrs #fields_names: #types),* })
+    };
+
+    quote! {
+        #[derive(::soa_derive::serde::Serialize)]
+        #[serde(crate = "::soa_derive::serde", rename = #name)]
+        #helper
+
+        ::soa_derive::serde::Serialize::serialize(&Fields { #(#fields_names: #values),* }, serializer)
+    }
+}
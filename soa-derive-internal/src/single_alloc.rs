@@ -0,0 +1,659 @@
+use proc_macro2::{Span, TokenStream};
+use syn::{Ident, Index, Meta, WherePredicate, parse_quote};
+use quote::TokenStreamExt;
+use quote::quote;
+
+use crate::input::Input;
+use crate::vec::check_columns_length;
+
+/// Generate the vector struct and the functions managing its memory for
+/// `#[soa(layout = "single_alloc")]`. All the columns are stored one after the
+/// other in a single `::soa_derive::single_alloc::SingleAlloc`, and share the
+/// same length and capacity. The functions which do not depend on the memory
+/// layout are generated in `vec.rs`.
+pub fn derive(input: &Input) -> TokenStream {
+    let name = &input.name;
+    let vec_name_str = format!("Vec<{}>", name);
+    let visibility = &input.visibility;
+    let allow_dead_code = &input.allow_dead_code();
+    let vec_name = &input.vec_name();
+    let slice_name = &input.slice_name();
+    let slice_mut_name = &input.slice_mut_name();
+    let ref_name = &input.ref_name();
+    let ptr_name = &input.ptr_name();
+    let ptr_mut_name = &input.ptr_mut_name();
+    let ptr_is_aligned_doc = format!("[`{0}::is_aligned()`](struct.{0}.html#method.is_aligned).", ptr_name);
+    let ptr_mut_is_aligned_doc = format!("[`{0}::is_aligned()`](struct.{0}.html#method.is_aligned).", ptr_mut_name);
+
+    // the traits in `#[soa_derive]` are implemented manually below
+    let vec_attrs = input.attrs.vec.iter()
+        .filter(|meta| !matches!(meta, Meta::List(list) if list.path.is_ident("derive")))
+        .collect::<Vec<_>>();
+
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let generics_elided = &input.generics_with_lifetime("'_");
+    let (_, ty_generics_elided, _) = generics_elided.split_for_impl();
+    let generics_a = &input.generics_with_lifetime("'a");
+    let (impl_generics_a, ty_generics_a, _) = generics_a.split_for_impl();
+
+    let fields_names = &input.fields_names();
+    let fields_vars = &input.fields_vars();
+    let fields_types = &input.fields_types();
+    let fields_align = &input.align;
+    let first_field = &fields_names[0];
+    let columns_indexes = &(0..fields_names.len()).map(Index::from).collect::<Vec<_>>();
+    let count = fields_names.len();
+    let destructure_value = input.destructure(&quote!(value), &quote!(()));
+
+    let check_columns_length = check_columns_length(input);
+
+    let accessors = if input.is_tuple() {
+        Vec::new()
+    } else {
+        fields_names.iter().zip(fields_types).map(|(name, ty)| {
+            let name_mut = Ident::new(&format!("{}_mut", quote!(#name)), Span::call_site());
+            let doc = format!("Get the values of the `{}` field", quote!(#name));
+            let doc_mut = format!("Get mutable access to the values of the `{}` field", quote!(#name));
+            quote! {
+                #[doc = #doc]
+                pub fn #name(&self) -> &[#ty] {
+                    self.as_slice().#name
+                }
+
+                #[doc = #doc_mut]
+                pub fn #name_mut(&mut self) -> &mut [#ty] {
+                    self.as_mut_slice().#name
+                }
+            }
+        }).collect()
+    };
+
+    let mut generated = quote! {
+        /// An analog to `
+        #[doc = #vec_name_str]
+        /// ` with Struct of Array (SoA) layout, storing all the fields in a
+        /// single allocation
+        #allow_dead_code
+        #(#[#vec_attrs])*
+        #visibility struct #vec_name #generics #where_clause {
+            data: ::soa_derive::single_alloc::SingleAlloc<#count>,
+            len: usize,
+            marker: ::std::marker::PhantomData<(#(#fields_types,)*)>,
+        }
+
+        #allow_dead_code
+        impl #impl_generics #vec_name #ty_generics #where_clause {
+            /// Memory layout of the elements of each field, including the
+            /// alignment from `#[soa(align)]`
+            const COLUMNS: [::std::alloc::Layout; #count] = [
+                #(::soa_derive::single_alloc::aligned_layout::<#fields_types>(#fields_align),)*
+            ];
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::new()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.new)
+            pub fn new() -> #vec_name #ty_generics {
+                #vec_name {
+                    data: ::soa_derive::single_alloc::SingleAlloc::new(&Self::COLUMNS),
+                    len: 0,
+                    marker: ::std::marker::PhantomData,
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::with_capacity()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.with_capacity),
+            /// allocating memory for all fields at once. With `#[soa(align)]`,
+            /// the capacity is rounded up so that every field ends on its
+            /// alignment.
+            pub fn with_capacity(capacity: usize) -> #vec_name #ty_generics {
+                let mut vec = #vec_name::new();
+                vec.data.reallocate(&Self::COLUMNS, 0, capacity);
+                vec
+            }
+
+            /// Create a new vector from the values of each field, checking
+            /// that all of them have the same length. The values are moved
+            /// to a single allocation.
+            pub fn from_columns(#(mut #fields_vars: Vec<#fields_types>),*) -> Result<#vec_name #ty_generics, ::soa_derive::LengthMismatch> {
+                #check_columns_length
+                let mut vec = #vec_name::with_capacity(expected);
+                unsafe {
+                    let ptr = vec.as_mut_ptr();
+                    #(
+                        ::std::ptr::copy_nonoverlapping(#fields_vars.as_ptr(), ptr.#fields_names, expected);
+                        // the values are now owned by `vec`
+                        #fields_vars.set_len(0);
+                    )*
+                }
+                vec.len = expected;
+                Ok(vec)
+            }
+
+            /// Split this vector into the values of each field, in the same
+            /// order as the fields of the struct.
+            pub fn into_columns(mut self) -> (#(Vec<#fields_types>,)*) {
+                let len = self.len;
+                // the values are moved out of `self`, which must not drop them
+                self.len = 0;
+                let ptr = self.as_mut_ptr();
+                #(
+                    let mut #fields_vars = Vec::with_capacity(len);
+                    unsafe {
+                        ::std::ptr::copy_nonoverlapping(ptr.#fields_names, #fields_vars.as_mut_ptr(), len);
+                        #fields_vars.set_len(len);
+                    }
+                )*
+                (#(#fields_vars,)*)
+            }
+
+            #(#accessors)*
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::capacity()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.capacity),
+            /// all the fields share the same capacity.
+            pub fn capacity(&self) -> usize {
+                self.data.capacity()
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::reserve()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.reserve),
+            /// reallocating all fields at once if needed. With `#[soa(align)]`,
+            /// the capacity is rounded up so that every field ends on its
+            /// alignment.
+            pub fn reserve(&mut self, additional: usize) {
+                let required = self.len.checked_add(additional).expect("capacity overflow");
+                if required > self.capacity() {
+                    let capacity = ::std::cmp::max(required, self.capacity().saturating_mul(2));
+                    let capacity = ::std::cmp::max(capacity, 4);
+                    self.data.reallocate(&Self::COLUMNS, self.len, capacity);
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::reserve_exact()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.reserve_exact)
+            /// reallocating all fields at once if needed.
+            pub fn reserve_exact(&mut self, additional: usize) {
+                let required = self.len.checked_add(additional).expect("capacity overflow");
+                if required > self.capacity() {
+                    self.data.reallocate(&Self::COLUMNS, self.len, required);
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::shrink_to_fit()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.shrink_to_fit)
+            /// shrinking all fields at once.
+            pub fn shrink_to_fit(&mut self) {
+                if self.capacity() > self.len {
+                    self.data.reallocate(&Self::COLUMNS, self.len, self.len);
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::truncate()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.truncate)
+            /// truncating all fields.
+            pub fn truncate(&mut self, len: usize) {
+                if len >= self.len {
+                    return;
+                }
+
+                let old_len = self.len;
+                // leak the remaining values if one of the destructors panics
+                self.len = len;
+                unsafe {
+                    let ptr = self.as_mut_ptr();
+                    #(
+                        ::std::ptr::drop_in_place(::std::ptr::slice_from_raw_parts_mut(ptr.#fields_names.add(len), old_len - len));
+                    )*
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::push()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.push).
+            pub fn push(&mut self, value: #name #ty_generics) {
+                #destructure_value
+                self.push_columns(#(#fields_vars),*);
+            }
+
+            /// Add the values of each field at the end of the vector
+            fn push_columns(&mut self, #(#fields_vars: #fields_types),*) {
+                if self.len == self.capacity() {
+                    self.reserve(1);
+                }
+                unsafe {
+                    let ptr = self.as_mut_ptr();
+                    #(ptr.#fields_names.add(self.len).write(#fields_vars);)*
+                }
+                self.len += 1;
+            }
+
+            /// Add the values of each field produced by `iter` at the end
+            /// of the vector, writing them directly in the columns and only
+            /// updating the length once all values have been written.
+            fn extend_columns<I: Iterator<Item=(#(#fields_types,)*)>>(&mut self, iter: I) {
+                self.reserve(iter.size_hint().0);
+                let mut ptr = self.as_mut_ptr();
+                let mut len = self.len;
+                // the values written after the last update of the length are
+                // leaked if the iterator panics
+                for values in iter {
+                    if len == self.capacity() {
+                        self.len = len;
+                        self.reserve(1);
+                        ptr = self.as_mut_ptr();
+                    }
+                    unsafe {
+                        #(ptr.#fields_names.add(len).write(values.#columns_indexes);)*
+                    }
+                    len += 1;
+                }
+                self.len = len;
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::len()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.len),
+            /// all the fields share the same length.
+            pub fn len(&self) -> usize {
+                self.len
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::is_empty()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.is_empty),
+            /// all the fields share the same length.
+            pub fn is_empty(&self) -> bool {
+                self.len == 0
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::swap_remove()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.swap_remove).
+            pub fn swap_remove(&mut self, index: usize) -> #name #ty_generics {
+                let len = self.len;
+                assert!(index < len, "swap_remove index (is {}) should be < len (is {})", index, len);
+                unsafe {
+                    let ptr = self.as_mut_ptr();
+                    let value = ptr.add(index).read();
+                    #(::std::ptr::copy(ptr.#fields_names.add(len - 1), ptr.#fields_names.add(index), 1);)*
+                    self.len = len - 1;
+                    value
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::insert()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.insert).
+            pub fn insert(&mut self, index: usize, element: #name #ty_generics) {
+                let len = self.len;
+                assert!(index <= len, "insertion index (is {}) should be <= len (is {})", index, len);
+                if len == self.capacity() {
+                    self.reserve(1);
+                }
+                unsafe {
+                    let ptr = self.as_mut_ptr();
+                    #(::std::ptr::copy(ptr.#fields_names.add(index), ptr.#fields_names.add(index + 1), len - index);)*
+                    ptr.add(index).write(element);
+                }
+                self.len = len + 1;
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::remove()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.remove).
+            pub fn remove(&mut self, index: usize) -> #name #ty_generics {
+                let len = self.len;
+                assert!(index < len, "removal index (is {}) should be < len (is {})", index, len);
+                unsafe {
+                    let ptr = self.as_mut_ptr();
+                    let value = ptr.add(index).read();
+                    #(::std::ptr::copy(ptr.#fields_names.add(index + 1), ptr.#fields_names.add(index), len - index - 1);)*
+                    self.len = len - 1;
+                    value
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::pop()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.pop).
+            pub fn pop(&mut self) -> Option<#name #ty_generics> {
+                if self.is_empty() {
+                    None
+                } else {
+                    self.len -= 1;
+                    unsafe {
+                        Some(self.as_mut_ptr().add(self.len).read())
+                    }
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::append()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.append).
+            pub fn append(&mut self, other: &mut #vec_name #ty_generics) {
+                let count = other.len;
+                self.reserve(count);
+                unsafe {
+                    let ptr = self.as_mut_ptr();
+                    let other_ptr = other.as_mut_ptr();
+                    #(::std::ptr::copy_nonoverlapping(other_ptr.#fields_names, ptr.#fields_names.add(self.len), count);)*
+                }
+                other.len = 0;
+                self.len += count;
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::clear()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.clear).
+            pub fn clear(&mut self) {
+                self.truncate(0);
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::split_off()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.split_off).
+            pub fn split_off(&mut self, at: usize) -> #vec_name #ty_generics {
+                let len = self.len;
+                assert!(at <= len, "`at` split index (is {}) should be <= len (is {})", at, len);
+                let count = len - at;
+                let mut other = #vec_name::with_capacity(count);
+                unsafe {
+                    let ptr = self.as_mut_ptr();
+                    let other_ptr = other.as_mut_ptr();
+                    #(::std::ptr::copy_nonoverlapping(ptr.#fields_names.add(at), other_ptr.#fields_names, count);)*
+                }
+                self.len = at;
+                other.len = count;
+                other
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_slice()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.as_slice).
+            pub fn as_slice(&self) -> #slice_name #ty_generics_elided {
+                let ptr = self.as_ptr();
+                unsafe {
+                    #slice_name {
+                        #(#fields_names: ::std::slice::from_raw_parts(ptr.#fields_names, self.len),)*
+                    }
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_mut_slice()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.as_mut_slice).
+            pub fn as_mut_slice(&mut self) -> #slice_mut_name #ty_generics_elided {
+                let ptr = self.as_mut_ptr();
+                unsafe {
+                    #slice_mut_name {
+                        #(#fields_names: ::std::slice::from_raw_parts_mut(ptr.#fields_names, self.len),)*
+                    }
+                }
+            }
+
+            /// Create a slice of this vector matching the given `range`. This
+            /// is analogous to `Index<Range<usize>>`.
+            pub fn slice(&self, range: ::std::ops::Range<usize>) -> #slice_name #ty_generics_elided {
+                let slice = self.as_slice();
+                #slice_name {
+                    #(#fields_names: &slice.#fields_names[range.clone()],)*
+                }
+            }
+
+            /// Create a mutable slice of this vector matching the given
+            /// `range`. This is analogous to `IndexMut<Range<usize>>`.
+            pub fn slice_mut(&mut self, range: ::std::ops::Range<usize>) -> #slice_mut_name #ty_generics_elided {
+                let #slice_mut_name { #(#fields_names: #fields_vars,)* } = self.as_mut_slice();
+                #slice_mut_name {
+                    #(#fields_names: &mut #fields_vars[range.clone()],)*
+                }
+            }
+
+            /// Retain only the elements for which the corresponding value in
+            /// `mask` is `true`, removing all the other elements. The retained
+            /// elements are moved in each field in turn, keeping their order.
+            ///
+            /// If dropping one of the removed elements panics, the elements
+            /// are leaked, and the vector is left empty.
+            ///
+            /// # Panics
+            ///
+            /// This function panics if `mask` and this vector have different
+            /// lengths.
+            pub fn retain_mask(&mut self, mask: &[bool]) {
+                assert_eq!(mask.len(), self.len(), "the mask length must be the same as the vector length");
+
+                // leak all the values if one of the destructors panics
+                self.len = 0;
+                let ptr = self.as_mut_ptr();
+                #({
+                    let mut kept = 0;
+                    for (i, &keep) in mask.iter().enumerate() {
+                        unsafe {
+                            if keep {
+                                if i != kept {
+                                    ::std::ptr::copy_nonoverlapping(ptr.#fields_names.add(i), ptr.#fields_names.add(kept), 1);
+                                }
+                                kept += 1;
+                            } else {
+                                ::std::ptr::drop_in_place(ptr.#fields_names.add(i));
+                            }
+                        }
+                    }
+                })*
+                self.len = mask.iter().filter(|&&keep| keep).count();
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_ptr()`](https://doc.rust-lang.org/std/struct.Vec.html#method.as_ptr).
+            /// Each pointer is aligned as required by `#[soa(align)]`, see
+            #[doc = #ptr_is_aligned_doc]
+            pub fn as_ptr(&self) -> #ptr_name #ty_generics {
+                let [#(#fields_vars),*] = self.data.columns();
+                #ptr_name {
+                    #(#fields_names: #fields_vars.cast::<#fields_types>() as *const #fields_types,)*
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_mut_ptr()`](https://doc.rust-lang.org/std/struct.Vec.html#method.as_mut_ptr).
+            /// Each pointer is aligned as required by `#[soa(align)]`, see
+            #[doc = #ptr_mut_is_aligned_doc]
+            pub fn as_mut_ptr(&mut self) -> #ptr_mut_name #ty_generics {
+                let [#(#fields_vars),*] = self.data.columns();
+                #ptr_mut_name {
+                    #(#fields_names: #fields_vars.cast::<#fields_types>(),)*
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::set_len()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.set_len),
+            /// all the fields share the same length.
+            pub unsafe fn set_len(&mut self, new_len: usize) {
+                debug_assert!(new_len <= self.capacity());
+                self.len = new_len;
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::from_raw_parts()`](https://doc.rust-lang.org/std/struct.Vec.html#method.from_raw_parts).
+            ///
+            /// All the pointers in `data` must come from the same vector,
+            /// with the given `capacity`.
+            pub unsafe fn from_raw_parts(data: #ptr_mut_name #ty_generics, len: usize, capacity: usize) -> #vec_name #ty_generics {
+                let vec = #vec_name {
+                    data: ::soa_derive::single_alloc::SingleAlloc::from_raw_parts(&Self::COLUMNS, data.#first_field.cast(), capacity),
+                    len: len,
+                    marker: ::std::marker::PhantomData,
+                };
+                #(debug_assert_eq!(vec.as_ptr().#fields_names, data.#fields_names as *const _);)*
+                vec
+            }
+        }
+
+        impl #impl_generics Drop for #vec_name #ty_generics #where_clause {
+            fn drop(&mut self) {
+                self.truncate(0);
+                self.data.deallocate(&Self::COLUMNS);
+            }
+        }
+    };
+
+    let with_bounds = |bound: TokenStream| {
+        let mut generics = generics.clone();
+        let predicates = fields_types.iter().map(|ty| -> WherePredicate { parse_quote!(#ty: #bound) });
+        generics.make_where_clause().predicates.extend(predicates);
+        generics.where_clause
+    };
+
+    let clone_where_clause = with_bounds(quote!(::std::clone::Clone));
+    // the bounds of the functions extending the vector with clones are only
+    // checked when they are used, since the fields are not required to
+    // implement `Clone`
+    let extend_where_clause = {
+        let mut generics = generics.clone();
+        let predicates = fields_types.iter().map(|ty| -> WherePredicate { parse_quote!(for<'c> #ty: ::std::clone::Clone) });
+        generics.make_where_clause().predicates.extend(predicates);
+        generics.where_clause
+    };
+    if input.attrs.vec_derives("Clone") {
+        generated.append_all(quote! {
+            impl #impl_generics ::std::clone::Clone for #vec_name #ty_generics #clone_where_clause {
+                fn clone(&self) -> Self {
+                    let mut vec = #vec_name::with_capacity(self.len);
+                    vec.extend_from_slice(self.as_slice());
+                    vec
+                }
+            }
+
+            #allow_dead_code
+            impl #impl_generics #vec_name #ty_generics #clone_where_clause {
+                /// Similar to [`
+                #[doc = #vec_name_str]
+                /// ::resize()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.resize).
+                pub fn resize(&mut self, new_len: usize, value: #name #ty_generics) {
+                    let len = self.len;
+                    if new_len <= len {
+                        self.truncate(new_len);
+                        return;
+                    }
+
+                    self.reserve(new_len - len);
+                    #destructure_value
+                    for _ in len + 1..new_len {
+                        self.push_columns(#(::std::clone::Clone::clone(&#fields_vars)),*);
+                    }
+                    self.push_columns(#(#fields_vars),*);
+                }
+            }
+        });
+    }
+
+    generated.append_all(quote! {
+        impl #impl_generics ::std::iter::Extend<#name #ty_generics> for #vec_name #ty_generics #where_clause {
+            fn extend<I: IntoIterator<Item=#name #ty_generics>>(&mut self, iter: I) {
+                self.extend_columns(iter.into_iter().map(|value| {
+                    #destructure_value
+                    (#(#fields_vars,)*)
+                }));
+            }
+        }
+
+        #allow_dead_code
+        impl #impl_generics #vec_name #ty_generics #extend_where_clause {
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::extend_from_slice()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.extend_from_slice),
+            /// cloning and appending all the elements in `other` to this
+            /// vector, one field at a time.
+            pub fn extend_from_slice(&mut self, other: #slice_name #ty_generics_elided) {
+                let len = other.len();
+                self.reserve(len);
+                let ptr = self.as_mut_ptr();
+                // the length is only updated once all the values are cloned,
+                // leaking them if one of the clones panics
+                #(
+                    for (i, value) in other.#fields_names.iter().enumerate() {
+                        unsafe {
+                            ptr.#fields_names.add(self.len + i).write(::std::clone::Clone::clone(value));
+                        }
+                    }
+                )*
+                self.len += len;
+            }
+        }
+
+        impl #impl_generics_a ::std::iter::Extend<#ref_name #ty_generics_a> for #vec_name #ty_generics #extend_where_clause {
+            fn extend<I: IntoIterator<Item=#ref_name #ty_generics_a>>(&mut self, iter: I) {
+                self.extend_columns(iter.into_iter().map(|value| (#(::std::clone::Clone::clone(value.#fields_names),)*)));
+            }
+        }
+    });
+
+    if input.attrs.vec_derives("Debug") {
+        let debug_where_clause = with_bounds(quote!(::std::fmt::Debug));
+        let debug = if input.is_tuple() {
+            quote! {
+                f.debug_tuple(stringify!(#vec_name))
+                    #(.field(&slice.#fields_names))*
+                    .finish()
+            }
+        } else {
+            let fields_names_str = fields_names.iter().map(|field| quote!(#field).to_string());
+            quote! {
+                f.debug_struct(stringify!(#vec_name))
+                    #(.field(#fields_names_str, &slice.#fields_names))*
+                    .finish()
+            }
+        };
+        generated.append_all(quote! {
+            impl #impl_generics ::std::fmt::Debug for #vec_name #ty_generics #debug_where_clause {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    let slice = self.as_slice();
+                    #debug
+                }
+            }
+        });
+    }
+
+    if input.attrs.vec_derives("Default") {
+        generated.append_all(quote! {
+            impl #impl_generics ::std::default::Default for #vec_name #ty_generics #where_clause {
+                fn default() -> Self {
+                    #vec_name::new()
+                }
+            }
+        });
+    }
+
+    if input.attrs.vec_derives("PartialEq") {
+        let partial_eq_where_clause = with_bounds(quote!(::std::cmp::PartialEq));
+        generated.append_all(quote! {
+            impl #impl_generics ::std::cmp::PartialEq for #vec_name #ty_generics #partial_eq_where_clause {
+                fn eq(&self, other: &Self) -> bool {
+                    let (slice, other) = (self.as_slice(), other.as_slice());
+                    #(slice.#fields_names == other.#fields_names)&&*
+                }
+            }
+        });
+    }
+
+    if input.attrs.vec_derives("Eq") {
+        let eq_where_clause = with_bounds(quote!(::std::cmp::Eq));
+        generated.append_all(quote! {
+            impl #impl_generics ::std::cmp::Eq for #vec_name #ty_generics #eq_where_clause {}
+        });
+    }
+
+    return generated;
+}
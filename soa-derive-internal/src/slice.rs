@@ -3,61 +3,81 @@ use syn::Ident;
 use quote::TokenStreamExt;
 use quote::quote;
 
-use crate::input::Input;
+use crate::input::{Input, VecLayout, is_primitive_float, nested_type};
 
 pub fn derive(input: &Input) -> TokenStream {
-    let other_derive = &input.derive_with_exceptions();
+    let slice_attrs = &input.attrs.slice;
     let visibility = &input.visibility;
+    let allow_dead_code = &input.allow_dead_code();
     let slice_name = &input.slice_name();
     let vec_name = &input.vec_name();
     let ref_name = &input.ref_name();
     let ptr_name = &input.ptr_name();
 
+    let generics = &input.generics;
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+    let generics_a = &input.generics_outliving("'a");
+    let (impl_generics_a, ty_generics_a, _) = generics_a.split_for_impl();
+    let generics_b = &input.generics_with_lifetime("'b");
+    let (_, ty_generics_b, _) = generics_b.split_for_impl();
+
     let slice_name_str = format!("[{}]", input.name);
     let doc_url = format!("[`{0}`](struct.{0}.html)", input.name);
     let vec_doc_url = format!("[`{0}`](struct.{0}.html)", input.vec_name());
 
-    let fields_names = input.fields.iter()
-                                   .map(|field| field.ident.clone().unwrap())
-                                   .collect::<Vec<_>>();
+    let fields_names = input.fields_names();
     let fields_names_1 = &fields_names;
     let fields_names_2 = &fields_names;
     let first_field = &fields_names[0];
-    let slice_names_1 = &input.fields.iter()
-        .map(|field| field.ident.as_ref().unwrap().to_string())
+    let fields_vars = &input.fields_vars();
+    let slice_names_1 = &fields_vars.iter()
         .map(|ident| Ident::new(&format!("{}_slice_1", ident), Span::call_site()))
         .collect::<Vec<_>>();
-    let slice_names_2 = &input.fields.iter()
-        .map(|field| field.ident.as_ref().unwrap().to_string())
+    let slice_names_2 = &fields_vars.iter()
         .map(|ident| Ident::new(&format!("{}_slice_2", ident), Span::call_site()))
         .collect::<Vec<_>>();
 
-    let fields_types = &input.fields.iter()
-                                    .map(|field| &field.ty)
-                                    .collect::<Vec<_>>();
-
     let fields_doc = fields_names.iter()
-                                 .map(|field| format!("A slice of `{0}` from a [`{1}`](struct.{1}.html)", field, vec_name))
+                                 .map(|field| format!("A slice of `{0}` from a [`{1}`](struct.{1}.html)", quote!(#field), vec_name))
                                  .collect::<Vec<_>>();
 
+
+    let slice_body = input.struct_body(&fields_doc, &input.map_fields_nested_or(
+        |_, ty| nested_type(ty, "Slice", Some("'a")),
+        |_, ty| quote!(&'a [#ty]),
+    ));
+
+    let reborrow = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.reborrow()),
+        |name, _| quote!(&self.#name),
+    );
+    let from_raw_parts = &input.map_fields_nested_or(
+        |name, ty| {
+            let slice_type = nested_type(ty, "Slice", Some("'_"));
+            quote!(<#slice_type>::from_raw_parts(data.#name, len))
+        },
+        |name, _| quote!(::std::slice::from_raw_parts(data.#name, len)),
+    );
     let mut generated = quote! {
         /// A slice of
         #[doc = #doc_url]
         /// inside a
         #[doc = #vec_doc_url]
         /// .
-        #[allow(dead_code)]
-        #[derive(Copy, Clone)]
-        #other_derive
-        #visibility struct #slice_name<'a> {
-            #(
-                #[doc = #fields_doc]
-                pub #fields_names_1: &'a [#fields_types],
-            )*
+        #allow_dead_code
+        #(#[#slice_attrs])*
+        #visibility struct #slice_name #generics_a #slice_body
+
+        impl #impl_generics_a Copy for #slice_name #ty_generics_a #where_clause {}
+
+        impl #impl_generics_a Clone for #slice_name #ty_generics_a #where_clause {
+            fn clone(&self) -> Self {
+                *self
+            }
         }
 
-        #[allow(dead_code)]
-        impl<'a> #slice_name<'a> {
+        #allow_dead_code
+        impl #impl_generics_a #slice_name #ty_generics_a #where_clause {
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::len()`](https://doc.rust-lang.org/std/primitive.slice.html#method.len),
@@ -81,28 +101,28 @@ pub fn derive(input: &Input) -> TokenStream {
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::first()`](https://doc.rust-lang.org/std/primitive.slice.html#method.first).
-            pub fn first(&self) -> Option<#ref_name<'a>> {
+            pub fn first(&self) -> Option<#ref_name #ty_generics_a> {
                 if self.is_empty() {
                     None
                 } else {
                     #(
-                        let #fields_names_1 = self.#fields_names_2.first().unwrap();
+                        let #fields_vars = self.#fields_names_1.first().unwrap();
                     )*
-                    Some(#ref_name{#(#fields_names_1: #fields_names_2),*})
+                    Some(#ref_name{#(#fields_names_1: #fields_vars),*})
                 }
             }
 
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::split_first()`](https://doc.rust-lang.org/std/primitive.slice.html#method.split_first).
-            pub fn split_first(&self) -> Option<(#ref_name<'a>, #slice_name<'a>)> {
+            pub fn split_first(&self) -> Option<(#ref_name #ty_generics_a, #slice_name #ty_generics_a)> {
                 if self.is_empty() {
                     None
                 } else {
                     #(
-                        let (#fields_names_1, #slice_names_1) = self.#fields_names_2.split_first().unwrap();
+                        let (#fields_vars, #slice_names_1) = self.#fields_names_1.split_first().unwrap();
                     )*
-                    let ref_ = #ref_name{#(#fields_names_1: #fields_names_2),*};
+                    let ref_ = #ref_name{#(#fields_names_1: #fields_vars),*};
                     let slice = #slice_name{#(#fields_names_1: #slice_names_1),*};
                     Some((ref_, slice))
                 }
@@ -111,28 +131,28 @@ pub fn derive(input: &Input) -> TokenStream {
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::last()`](https://doc.rust-lang.org/std/primitive.slice.html#method.last).
-            pub fn last(&self) -> Option<#ref_name<'a>> {
+            pub fn last(&self) -> Option<#ref_name #ty_generics_a> {
                 if self.is_empty() {
                     None
                 } else {
                     #(
-                        let #fields_names_1 = self.#fields_names_2.last().unwrap();
+                        let #fields_vars = self.#fields_names_1.last().unwrap();
                     )*
-                    Some(#ref_name{#(#fields_names_1: #fields_names_2),*})
+                    Some(#ref_name{#(#fields_names_1: #fields_vars),*})
                 }
             }
 
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::split_last()`](https://doc.rust-lang.org/std/primitive.slice.html#method.split_last).
-            pub fn split_last(&self) -> Option<(#ref_name<'a>, #slice_name<'a>)> {
+            pub fn split_last(&self) -> Option<(#ref_name #ty_generics_a, #slice_name #ty_generics_a)> {
                 if self.is_empty() {
                     None
                 } else {
                     #(
-                        let (#fields_names_1, #slice_names_1) = self.#fields_names_2.split_last().unwrap();
+                        let (#fields_vars, #slice_names_1) = self.#fields_names_1.split_last().unwrap();
                     )*
-                    let ref_ = #ref_name{#(#fields_names_1: #fields_names_2),*};
+                    let ref_ = #ref_name{#(#fields_names_1: #fields_vars),*};
                     let slice = #slice_name{#(#fields_names_1: #slice_names_1),*};
                     Some((ref_, slice))
                 }
@@ -141,7 +161,7 @@ pub fn derive(input: &Input) -> TokenStream {
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::split_at()`](https://doc.rust-lang.org/std/primitive.slice.html#method.split_at).
-            pub fn split_at(&self, mid: usize) -> (#slice_name<'a>, #slice_name<'a>) {
+            pub fn split_at(&self, mid: usize) -> (#slice_name #ty_generics_a, #slice_name #ty_generics_a) {
                 #(
                     let (#slice_names_1, #slice_names_2) = self.#fields_names_2.split_at(mid);
                 )*
@@ -155,10 +175,10 @@ pub fn derive(input: &Input) -> TokenStream {
             /// ::get()`](https://doc.rust-lang.org/std/primitive.slice.html#method.get).
             pub fn get<'b, I>(&'b self, index: I) -> Option<I::RefOutput>
             where
-                I: ::soa_derive::SoAIndex<#slice_name<'b>>,
+                I: ::soa_derive::SoAIndex<#slice_name #ty_generics_b>,
                 'a: 'b
             {
-                let slice: #slice_name<'b> = self.reborrow();
+                let slice: #slice_name #ty_generics_b = self.reborrow();
                 index.get(slice)
             }
 
@@ -167,10 +187,10 @@ pub fn derive(input: &Input) -> TokenStream {
             /// ::get_unchecked()`](https://doc.rust-lang.org/std/primitive.slice.html#method.get_unchecked).
             pub unsafe fn get_unchecked<'b, I>(&'b self, index: I) -> I::RefOutput
             where
-                I: ::soa_derive::SoAIndex<#slice_name<'b>>,
+                I: ::soa_derive::SoAIndex<#slice_name #ty_generics_b>,
                 'a: 'b
             {
-                let slice: #slice_name<'b> = self.reborrow();
+                let slice: #slice_name #ty_generics_b = self.reborrow();
                 index.get_unchecked(slice)
             }
 
@@ -180,52 +200,63 @@ pub fn derive(input: &Input) -> TokenStream {
             /// This is required because we cannot implement `std::ops::Index` directly since it requires returning a reference.
             pub fn index<'b, I>(&'b self, index: I) -> I::RefOutput
             where
-                I: ::soa_derive::SoAIndex<#slice_name<'b>>,
+                I: ::soa_derive::SoAIndex<#slice_name #ty_generics_b>,
                 'a: 'b
             {
-                let slice: #slice_name<'b> = self.reborrow();
+                let slice: #slice_name #ty_generics_b = self.reborrow();
                 index.index(slice)
             }
 
             /// Reborrows the slices in a narrower lifetime
-            pub fn reborrow<'b>(&'b self) -> #slice_name<'b>
+            pub fn reborrow<'b>(&'b self) -> #slice_name #ty_generics_b
             where
                 'a: 'b
             {
                 #slice_name {
-                    #(#fields_names_1: &self.#fields_names_2,)*
+                    #(#fields_names_1: #reborrow,)*
                 }
             }
 
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::as_ptr()`](https://doc.rust-lang.org/std/primitive.slice.html#method.as_ptr).
-            pub fn as_ptr(&self) -> #ptr_name {
+            pub fn as_ptr(&self) -> #ptr_name #ty_generics {
                 #ptr_name {
                     #(#fields_names_1: self.#fields_names_2.as_ptr(),)*
                 }
             }
 
             /// Similar to [`std::slice::from_raw_parts()`](https://doc.rust-lang.org/std/slice/fn.from_raw_parts.html).
-            pub unsafe fn from_raw_parts<'b>(data: #ptr_name, len: usize) -> #slice_name<'b> {
+            pub unsafe fn from_raw_parts<'b>(data: #ptr_name #ty_generics, len: usize) -> #slice_name #ty_generics_b {
                 #slice_name {
-                    #(#fields_names_1: ::std::slice::from_raw_parts(data.#fields_names_2, len),)*
+                    #(#fields_names_1: #from_raw_parts,)*
                 }
             }
         }
     };
 
-    if input.derives.contains(&Ident::new("Clone", Span::call_site())) {
+    if input.attrs.vec_derives("Clone") {
+        let to_vec = if input.layout == VecLayout::SingleAlloc {
+            quote! {
+                let mut vec = #vec_name::with_capacity(self.len());
+                vec.extend_from_slice(*self);
+                vec
+            }
+        } else {
+            quote! {
+                #vec_name {
+                    #(#fields_names_1: self.#fields_names_2.to_vec(),)*
+                }
+            }
+        };
         generated.append_all(quote!{
-            #[allow(dead_code)]
-            impl<'a> #slice_name<'a> {
+            #allow_dead_code
+            impl #impl_generics_a #slice_name #ty_generics_a #where_clause {
                 /// Similar to [`
                 #[doc = #slice_name_str]
                 /// ::to_vec()`](https://doc.rust-lang.org/std/primitive.slice.html#method.to_vec).
-                pub fn to_vec(&self) -> #vec_name {
-                    #vec_name {
-                        #(#fields_names_1: self.#fields_names_2.to_vec(),)*
-                    }
+                pub fn to_vec(&self) -> #vec_name #ty_generics {
+                    #to_vec
                 }
             }
         });
@@ -235,70 +266,145 @@ pub fn derive(input: &Input) -> TokenStream {
 }
 
 pub fn derive_mut(input: &Input) -> TokenStream {
-    let other_derive = &input.derive_with_exceptions();
+    let slice_mut_attrs = &input.attrs.slice_mut;
     let visibility = &input.visibility;
+    let allow_dead_code = &input.allow_dead_code();
     let slice_name = &input.slice_name();
     let slice_mut_name = &input.slice_mut_name();
     let vec_name = &input.vec_name();
+    let ref_name = &input.ref_name();
     let ref_mut_name = &input.ref_mut_name();
     let ptr_name = &input.ptr_name();
     let ptr_mut_name = &input.ptr_mut_name();
 
+    let generics = &input.generics;
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+    let generics_elided = &input.generics_with_lifetime("'_");
+    let (_, ty_generics_elided, _) = generics_elided.split_for_impl();
+    let generics_a = &input.generics_outliving("'a");
+    let (impl_generics_a, ty_generics_a, _) = generics_a.split_for_impl();
+    let generics_b = &input.generics_with_lifetime("'b");
+    let (_, ty_generics_b, _) = generics_b.split_for_impl();
+
     let slice_name_str = format!("[{}]", input.name);
     let doc_url = format!("[`{0}`](struct.{0}.html)", input.name);
     let slice_doc_url = format!("[`{0}`](struct.{0}.html)", slice_name);
     let slice_mut_doc_url = format!("[`{0}`](struct.{0}.html)", slice_mut_name);
     let vec_doc_url = format!("[`{0}`](struct.{0}.html)", input.vec_name());
 
-    let fields_names = input.fields.iter()
-                                   .map(|field| field.ident.clone().unwrap())
-                                   .collect::<Vec<_>>();
+    let fields_names = input.fields_names();
     let fields_names_1 = &fields_names;
     let fields_names_2 = &fields_names;
     let first_field = &fields_names[0];
-    let slice_names_1 = &input.fields.iter()
-        .map(|field| field.ident.as_ref().unwrap().to_string())
+    let fields_vars = &input.fields_vars();
+    let slice_names_1 = &fields_vars.iter()
         .map(|ident| Ident::new(&format!("{}_slice_1", ident), Span::call_site()))
         .collect::<Vec<_>>();
-    let slice_names_2 = &input.fields.iter()
-        .map(|field| field.ident.as_ref().unwrap().to_string())
+    let slice_names_2 = &fields_vars.iter()
         .map(|ident| Ident::new(&format!("{}_slice_2", ident), Span::call_site()))
         .collect::<Vec<_>>();
 
-    let fields_types = &input.fields.iter()
-                                    .map(|field| &field.ty)
-                                    .collect::<Vec<_>>();
-
     let fields_doc = fields_names.iter()
-                                 .map(|field| format!("A mutable slice of `{0}` from a [`{1}`](struct.{1}.html)", field, vec_name))
+                                 .map(|field| format!("A mutable slice of `{0}` from a [`{1}`](struct.{1}.html)", quote!(#field), vec_name))
                                  .collect::<Vec<_>>();
 
+
+    let slice_mut_body = input.struct_body(&fields_doc, &input.map_fields_nested_or(
+        |_, ty| nested_type(ty, "SliceMut", Some("'a")),
+        |_, ty| quote!(&'a mut [#ty]),
+    ));
+
+    let as_ref = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.as_ref()),
+        |name, _| quote!(self.#name),
+    );
+    let as_slice = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.as_slice()),
+        |name, _| quote!(&self.#name),
+    );
+    let reborrow = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.reborrow()),
+        |name, _| quote!(&mut *self.#name),
+    );
+    let from_raw_parts_mut = &input.map_fields_nested_or(
+        |name, ty| {
+            let slice_type = nested_type(ty, "SliceMut", Some("'_"));
+            quote!(<#slice_type>::from_raw_parts_mut(data.#name, len))
+        },
+        |name, _| quote!(::std::slice::from_raw_parts_mut(data.#name, len)),
+    );
+    // the methods are named `sort_by_field_<field>` to not collide with
+    // `sort_by_key` for a field named `key`
+    let sort_by_fields = input.column_methods("sort_by_field_").into_iter().map(|(name, ty, method)| {
+        let doc = format!(
+            "Sort this slice by the values of the `{}` field, using only this \
+            column to compute the permutation before moving the elements of \
+            all fields. This sort is stable.", quote!(#name)
+        );
+        let (compare, compare_doc, where_clause) = if is_primitive_float(&ty) {
+            let total_cmp_doc = format!(
+                "[`{0}::total_cmp`](https://doc.rust-lang.org/std/primitive.{0}.html#method.total_cmp),",
+                quote!(#ty)
+            );
+            (
+                quote!(column[a].total_cmp(&column[b])),
+                quote! {
+                    ///
+                    /// The values are compared with
+                    #[doc = #total_cmp_doc]
+                    /// which places positive NaN after all other values and
+                    /// negative NaN before them.
+                },
+                quote!(),
+            )
+        } else {
+            (
+                quote!(column[a].cmp(&column[b])),
+                quote! {
+                    ///
+                    /// This function is only available when the type of the
+                    /// field implements `Ord`.
+                },
+                // the bound is only checked when the function is used, since
+                // the fields are not required to implement `Ord`
+                quote!(where for<'c> #ty: ::std::cmp::Ord),
+            )
+        };
+        quote! {
+            #[doc = #doc]
+            #compare_doc
+            pub fn #method(&mut self) #where_clause {
+                let mut permutation: Vec<usize> = (0..self.len()).collect();
+                {
+                    let column = &self.#name;
+                    permutation.sort_by(|&a, &b| #compare);
+                }
+                self.apply_permutation(&permutation);
+            }
+        }
+    }).collect::<Vec<_>>();
+
     let mut generated = quote! {
         /// A mutable slice of
         #[doc = #doc_url]
         /// inside a
         #[doc = #vec_doc_url]
         /// .
-        #[allow(dead_code)]
-        #other_derive
-        #visibility struct #slice_mut_name<'a> {
-            #(
-                #[doc = #fields_doc]
-                pub #fields_names_1: &'a mut [#fields_types],
-            )*
-        }
+        #allow_dead_code
+        #(#[#slice_mut_attrs])*
+        #visibility struct #slice_mut_name #generics_a #slice_mut_body
 
-        #[allow(dead_code)]
-        impl<'a> #slice_mut_name<'a> {
+        #allow_dead_code
+        impl #impl_generics_a #slice_mut_name #ty_generics_a #where_clause {
             /// Convert a
             #[doc = #slice_mut_doc_url]
             /// to a
             #[doc = #slice_doc_url]
             /// in order to be able to use the methods on the non mutable
             /// version of the slices.
-            pub fn as_ref(&self) -> #slice_name {
+            pub fn as_ref(&self) -> #slice_name #ty_generics_elided {
                 #slice_name {
-                    #(#fields_names_1: self.#fields_names_2,)*
+                    #(#fields_names_1: #as_ref,)*
                 }
             }
 
@@ -325,28 +431,28 @@ pub fn derive_mut(input: &Input) -> TokenStream {
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::first_mut()`](https://doc.rust-lang.org/std/primitive.slice.html#method.first_mut).
-            pub fn first_mut(&mut self) -> Option<#ref_mut_name> {
+            pub fn first_mut(&mut self) -> Option<#ref_mut_name #ty_generics_elided> {
                 if self.is_empty() {
                     None
                 } else {
                     #(
-                        let #fields_names_1 = self.#fields_names_2.first_mut().unwrap();
+                        let #fields_vars = self.#fields_names_1.first_mut().unwrap();
                     )*
-                    Some(#ref_mut_name{#(#fields_names_1: #fields_names_2),*})
+                    Some(#ref_mut_name{#(#fields_names_1: #fields_vars),*})
                 }
             }
 
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::split_first_mut()`](https://doc.rust-lang.org/std/primitive.slice.html#method.split_first_mut).
-            pub fn split_first_mut(&mut self) -> Option<(#ref_mut_name, #slice_mut_name)> {
+            pub fn split_first_mut(&mut self) -> Option<(#ref_mut_name #ty_generics_elided, #slice_mut_name #ty_generics_elided)> {
                 if self.is_empty() {
                     None
                 } else {
                     #(
-                        let (#fields_names_1, #slice_names_1) = self.#fields_names_2.split_first_mut().unwrap();
+                        let (#fields_vars, #slice_names_1) = self.#fields_names_1.split_first_mut().unwrap();
                     )*
-                    let ref_ = #ref_mut_name{#(#fields_names_1: #fields_names_2),*};
+                    let ref_ = #ref_mut_name{#(#fields_names_1: #fields_vars),*};
                     let slice = #slice_mut_name{#(#fields_names_1: #slice_names_1),*};
                     Some((ref_, slice))
                 }
@@ -355,28 +461,28 @@ pub fn derive_mut(input: &Input) -> TokenStream {
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::last_mut()`](https://doc.rust-lang.org/std/primitive.slice.html#method.last_mut).
-            pub fn last_mut(&mut self) -> Option<#ref_mut_name> {
+            pub fn last_mut(&mut self) -> Option<#ref_mut_name #ty_generics_elided> {
                 if self.is_empty() {
                     None
                 } else {
                     #(
-                        let #fields_names_1 = self.#fields_names_2.last_mut().unwrap();
+                        let #fields_vars = self.#fields_names_1.last_mut().unwrap();
                     )*
-                    Some(#ref_mut_name{#(#fields_names_1: #fields_names_2),*})
+                    Some(#ref_mut_name{#(#fields_names_1: #fields_vars),*})
                 }
             }
 
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::last_mut()`](https://doc.rust-lang.org/std/primitive.slice.html#method.last_mut).
-            pub fn split_last_mut(&mut self) -> Option<(#ref_mut_name, #slice_mut_name)> {
+            pub fn split_last_mut(&mut self) -> Option<(#ref_mut_name #ty_generics_elided, #slice_mut_name #ty_generics_elided)> {
                 if self.is_empty() {
                     None
                 } else {
                     #(
-                        let (#fields_names_1, #slice_names_1) = self.#fields_names_2.split_last_mut().unwrap();
+                        let (#fields_vars, #slice_names_1) = self.#fields_names_1.split_last_mut().unwrap();
                     )*
-                    let ref_ = #ref_mut_name{#(#fields_names_1: #fields_names_2),*};
+                    let ref_ = #ref_mut_name{#(#fields_names_1: #fields_vars),*};
                     let slice = #slice_mut_name{#(#fields_names_1: #slice_names_1),*};
                     Some((ref_, slice))
                 }
@@ -385,7 +491,7 @@ pub fn derive_mut(input: &Input) -> TokenStream {
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::split_at_mut()`](https://doc.rust-lang.org/std/primitive.slice.html#method.split_at_mut).
-            pub fn split_at_mut(&mut self, mid: usize) -> (#slice_mut_name, #slice_mut_name) {
+            pub fn split_at_mut(&mut self, mid: usize) -> (#slice_mut_name #ty_generics_elided, #slice_mut_name #ty_generics_elided) {
                 #(
                     let (#slice_names_1, #slice_names_2) = self.#fields_names_2.split_at_mut(mid);
                 )*
@@ -403,15 +509,109 @@ pub fn derive_mut(input: &Input) -> TokenStream {
                 )*
             }
 
+            /// Similar to [`
+            #[doc = #slice_name_str]
+            /// ::sort_by()`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_by).
+            ///
+            /// This sort is stable, and sorts a vector of indexes before
+            /// moving the elements of all fields with `apply_permutation`.
+            pub fn sort_by<F>(&mut self, mut compare: F)
+            where
+                F: FnMut(#ref_name #ty_generics_elided, #ref_name #ty_generics_elided) -> ::std::cmp::Ordering
+            {
+                let mut permutation: Vec<usize> = (0..self.len()).collect();
+                {
+                    let slice = self.as_slice();
+                    permutation.sort_by(|&a, &b| compare(slice.index(a), slice.index(b)));
+                }
+                self.apply_permutation(&permutation);
+            }
+
+            /// Similar to [`
+            #[doc = #slice_name_str]
+            /// ::sort_by_key()`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_by_key).
+            ///
+            /// This sort is stable, and sorts a vector of indexes before
+            /// moving the elements of all fields with `apply_permutation`.
+            pub fn sort_by_key<F, K>(&mut self, mut f: F)
+            where
+                F: FnMut(#ref_name #ty_generics_elided) -> K,
+                K: Ord,
+            {
+                let mut permutation: Vec<usize> = (0..self.len()).collect();
+                {
+                    let slice = self.as_slice();
+                    permutation.sort_by_key(|&i| f(slice.index(i)));
+                }
+                self.apply_permutation(&permutation);
+            }
+
+            /// Similar to [`
+            #[doc = #slice_name_str]
+            /// ::sort_unstable_by()`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_unstable_by).
+            ///
+            /// This sorts a vector of indexes before moving the elements of
+            /// all fields with `apply_permutation`.
+            pub fn sort_unstable_by<F>(&mut self, mut compare: F)
+            where
+                F: FnMut(#ref_name #ty_generics_elided, #ref_name #ty_generics_elided) -> ::std::cmp::Ordering
+            {
+                let mut permutation: Vec<usize> = (0..self.len()).collect();
+                {
+                    let slice = self.as_slice();
+                    permutation.sort_unstable_by(|&a, &b| compare(slice.index(a), slice.index(b)));
+                }
+                self.apply_permutation(&permutation);
+            }
+
+            /// Reorder the elements of this slice in place, so that the
+            /// element at index `i` is the one that was previously at index
+            /// `permutation[i]`. This uses `O(n)` extra memory, and follows
+            /// each cycle of the permutation with at most `len - 1` swaps in
+            /// every field.
+            ///
+            /// # Panics
+            ///
+            /// This function panics if `permutation` is not a permutation
+            /// of `0..self.len()`.
+            pub fn apply_permutation(&mut self, permutation: &[usize]) {
+                let len = self.len();
+                assert_eq!(permutation.len(), len, "the permutation length must be the same as the slice length");
+                let mut pending = vec![false; len];
+                for &index in permutation {
+                    assert!(index < len && !pending[index], "invalid permutation: index {} is out of bounds or repeated", index);
+                    pending[index] = true;
+                }
+
+                for start in 0..len {
+                    if !pending[start] {
+                        continue;
+                    }
+                    pending[start] = false;
+                    let mut current = start;
+                    loop {
+                        let next = permutation[current];
+                        if next == start {
+                            break;
+                        }
+                        self.swap(current, next);
+                        pending[next] = false;
+                        current = next;
+                    }
+                }
+            }
+
+            #(#sort_by_fields)*
+
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::get()`](https://doc.rust-lang.org/std/primitive.slice.html#method.get).
             pub fn get<'b, I>(&'b self, index: I) -> Option<I::RefOutput>
             where
-                I: ::soa_derive::SoAIndex<#slice_name<'b>>,
+                I: ::soa_derive::SoAIndex<#slice_name #ty_generics_b>,
                 'a: 'b
             {
-                let slice: #slice_name<'b> = self.as_slice();
+                let slice: #slice_name #ty_generics_b = self.as_slice();
                 index.get(slice)
             }
 
@@ -420,10 +620,10 @@ pub fn derive_mut(input: &Input) -> TokenStream {
             /// ::get_unchecked()`](https://doc.rust-lang.org/std/primitive.slice.html#method.get_unchecked).
             pub unsafe fn get_unchecked<'b, I>(&'b self, index: I) -> I::RefOutput
             where
-                I: ::soa_derive::SoAIndex<#slice_name<'b>>,
+                I: ::soa_derive::SoAIndex<#slice_name #ty_generics_b>,
                 'a: 'b
             {
-                let slice: #slice_name<'b> = self.as_slice();
+                let slice: #slice_name #ty_generics_b = self.as_slice();
                 index.get_unchecked(slice)
             }
 
@@ -434,10 +634,10 @@ pub fn derive_mut(input: &Input) -> TokenStream {
             /// This is required because we cannot implement that trait.
             pub fn index<'b, I>(&'b self, index: I) -> I::RefOutput
             where
-                I: ::soa_derive::SoAIndex<#slice_name<'b>>,
+                I: ::soa_derive::SoAIndex<#slice_name #ty_generics_b>,
                 'a: 'b
             {
-                let slice: #slice_name<'b> = self.as_slice();
+                let slice: #slice_name #ty_generics_b = self.as_slice();
                 index.index(slice)
             }
 
@@ -446,10 +646,10 @@ pub fn derive_mut(input: &Input) -> TokenStream {
             /// ::get_mut()`](https://doc.rust-lang.org/std/primitive.slice.html#method.get_mut).
             pub fn get_mut<'b, I>(&'b mut self, index: I) -> Option<I::MutOutput>
             where
-                I: ::soa_derive::SoAIndexMut<#slice_mut_name<'b>>,
+                I: ::soa_derive::SoAIndexMut<#slice_mut_name #ty_generics_b>,
                 'a: 'b
             {
-                let slice: #slice_mut_name<'b> = self.reborrow();
+                let slice: #slice_mut_name #ty_generics_b = self.reborrow();
                 index.get_mut(slice)
             }
 
@@ -458,10 +658,10 @@ pub fn derive_mut(input: &Input) -> TokenStream {
             /// ::get_unchecked_mut()`](https://doc.rust-lang.org/std/primitive.slice.html#method.get_unchecked_mut).
             pub unsafe fn get_unchecked_mut<'b, I>(&'b mut self, index: I) -> I::MutOutput
             where
-                I: ::soa_derive::SoAIndexMut<#slice_mut_name<'b>>,
+                I: ::soa_derive::SoAIndexMut<#slice_mut_name #ty_generics_b>,
                 'a: 'b
             {
-                let slice: #slice_mut_name<'b> = self.reborrow();
+                let slice: #slice_mut_name #ty_generics_b = self.reborrow();
                 index.get_unchecked_mut(slice)
             }
 
@@ -471,37 +671,37 @@ pub fn derive_mut(input: &Input) -> TokenStream {
             /// This is required because we cannot implement `std::ops::IndexMut` directly since it requires returning a mutable reference.
             pub fn index_mut<'b, I>(&'b mut self, index: I) -> I::MutOutput
             where
-                I: ::soa_derive::SoAIndexMut<#slice_mut_name<'b>>,
+                I: ::soa_derive::SoAIndexMut<#slice_mut_name #ty_generics_b>,
                 'a: 'b
             {
-                let slice: #slice_mut_name<'b> = self.reborrow();
+                let slice: #slice_mut_name #ty_generics_b = self.reborrow();
                 index.index_mut(slice)
             }
 
             /// Returns a non-mutable slice from this mutable slice.
-            pub fn as_slice<'b>(&'b self) -> #slice_name<'b>
+            pub fn as_slice<'b>(&'b self) -> #slice_name #ty_generics_b
             where
                 'a: 'b
             {
                 #slice_name {
-                    #(#fields_names_1: &self.#fields_names_2,)*
+                    #(#fields_names_1: #as_slice,)*
                 }
             }
 
             /// Reborrows the slices in a narrower lifetime
-            pub fn reborrow<'b>(&'b mut self) -> #slice_mut_name<'b>
+            pub fn reborrow<'b>(&'b mut self) -> #slice_mut_name #ty_generics_b
             where
                 'a: 'b
             {
                 #slice_mut_name {
-                    #(#fields_names_1: &mut *self.#fields_names_2,)*
+                    #(#fields_names_1: #reborrow,)*
                 }
             }
 
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::as_ptr()`](https://doc.rust-lang.org/std/primitive.slice.html#method.as_ptr).
-            pub fn as_ptr(&self) -> #ptr_name {
+            pub fn as_ptr(&self) -> #ptr_name #ty_generics {
                 #ptr_name {
                     #(#fields_names_1: self.#fields_names_2.as_ptr(),)*
                 }
@@ -510,32 +710,43 @@ pub fn derive_mut(input: &Input) -> TokenStream {
             /// Similar to [`
             #[doc = #slice_name_str]
             /// ::as_mut_ptr()`](https://doc.rust-lang.org/std/primitive.slice.html#method.as_mut_ptr).
-            pub fn as_mut_ptr(&mut self) -> #ptr_mut_name {
+            pub fn as_mut_ptr(&mut self) -> #ptr_mut_name #ty_generics {
                 #ptr_mut_name {
                     #(#fields_names_1: self.#fields_names_2.as_mut_ptr(),)*
                 }
             }
 
             /// Similar to [`std::slice::from_raw_parts_mut()`](https://doc.rust-lang.org/std/slice/fn.from_raw_parts_mut.html).
-            pub unsafe fn from_raw_parts_mut<'b>(data: #ptr_mut_name, len: usize) -> #slice_mut_name<'b> {
+            pub unsafe fn from_raw_parts_mut<'b>(data: #ptr_mut_name #ty_generics, len: usize) -> #slice_mut_name #ty_generics_b {
                 #slice_mut_name {
-                    #(#fields_names_1: ::std::slice::from_raw_parts_mut(data.#fields_names_2, len),)*
+                    #(#fields_names_1: #from_raw_parts_mut,)*
                 }
             }
         }
     };
 
-    if input.derives.contains(&Ident::new("Clone", Span::call_site())) {
+    if input.attrs.vec_derives("Clone") {
+        let to_vec = if input.layout == VecLayout::SingleAlloc {
+            quote! {
+                let mut vec = #vec_name::with_capacity(self.len());
+                vec.extend_from_slice(self.as_slice());
+                vec
+            }
+        } else {
+            quote! {
+                #vec_name {
+                    #(#fields_names_1: self.#fields_names_2.to_vec(),)*
+                }
+            }
+        };
         generated.append_all(quote!{
-            #[allow(dead_code)]
-            impl<'a> #slice_mut_name<'a> {
+            #allow_dead_code
+            impl #impl_generics_a #slice_mut_name #ty_generics_a #where_clause {
                 /// Similar to [`
                 #[doc = #slice_name_str]
                 /// ::to_vec()`](https://doc.rust-lang.org/std/primitive.slice.html#method.to_vec).
-                pub fn to_vec(&self) -> #vec_name {
-                    #vec_name {
-                        #(#fields_names_1: self.#fields_names_2.to_vec(),)*
-                    }
+                pub fn to_vec(&self) -> #vec_name #ty_generics {
+                    #to_vec
                 }
             }
         });
@@ -0,0 +1,533 @@
+use proc_macro2::{Span, TokenStream};
+use syn::{Ident, WherePredicate, parse_quote};
+use quote::TokenStreamExt;
+use quote::quote;
+
+use crate::input::Input;
+
+/// Generate the small vector type for `#[soa(small_vec)]`. The elements are
+/// stored inline in the array type generated for `#[soa(array)]` until there
+/// are more than `N` of them, and are then moved to the vector type.
+pub fn derive(input: &Input) -> TokenStream {
+    if !input.small_vec {
+        return TokenStream::new();
+    }
+
+    let name = &input.name;
+    let visibility = &input.visibility;
+    let allow_dead_code = &input.allow_dead_code();
+    let small_vec_name = &input.small_vec_name();
+    let vec_name = &input.vec_name();
+    let array_name = &input.array_name();
+    let slice_name = &input.slice_name();
+    let slice_mut_name = &input.slice_mut_name();
+    let ptr_name = &input.ptr_name();
+    let ptr_mut_name = &input.ptr_mut_name();
+    let iter_mod = Ident::new(&format!("__detail_iter_{}", name.to_string().to_lowercase()), Span::call_site());
+    let detail_mod = Ident::new(&format!("__detail_small_vec_{}", name.to_string().to_lowercase()), Span::call_site());
+
+    let doc_url = format!("[`{0}`](struct.{0}.html)", name);
+    let vec_doc_url = format!("[`{0}`](struct.{0}.html)", vec_name);
+    let array_doc_url = format!("[`{0}`](struct.{0}.html)", array_name);
+    let vec_name_str = format!("Vec<{}>", name);
+
+    let generics = &input.generics;
+    let where_clause = &generics.where_clause;
+    let generics_array = &input.array_generics(None);
+    let (impl_generics, ty_generics_array, _) = generics_array.split_for_impl();
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let generics_elided = &input.generics_with_lifetime("'_");
+    let (_, ty_generics_elided, _) = generics_elided.split_for_impl();
+    let generics_a = &input.generics_with_lifetime("'a");
+    let (_, ty_generics_a, _) = generics_a.split_for_impl();
+    let generics_array_a = &input.array_generics(Some("'a"));
+    let (impl_generics_a, _, _) = generics_array_a.split_for_impl();
+
+    let fields_names = &input.fields_names();
+    let fields_types = &input.fields_types();
+
+    let mut generated = quote! {
+        #allow_dead_code
+        mod #detail_mod {
+            use super::*;
+
+            /// Storage of the elements in a small vector
+            #[allow(missing_debug_implementations)]
+            pub enum Storage #generics_array #where_clause {
+                /// Up to `N` elements, stored inline
+                Inline(#array_name #ty_generics_array),
+                /// Elements moved to the heap, once the small vector contained
+                /// more than `N` of them
+                Heap(#vec_name #ty_generics),
+            }
+        }
+
+        /// An analog to `
+        #[doc = #vec_name_str]
+        /// ` with Struct of Array (SoA) layout, storing up to `N` values of
+        #[doc = #doc_url]
+        /// inline in a
+        #[doc = #array_doc_url]
+        /// , and moving them to a
+        #[doc = #vec_doc_url]
+        /// on the heap when more values are added.
+        #allow_dead_code
+        #visibility struct #small_vec_name #generics_array #where_clause {
+            storage: #detail_mod::Storage #ty_generics_array,
+        }
+
+        #allow_dead_code
+        impl #impl_generics #small_vec_name #ty_generics_array #where_clause {
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::new()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.new),
+            /// creating an empty small vector storing its elements inline.
+            pub fn new() -> #small_vec_name #ty_generics_array {
+                #small_vec_name {
+                    storage: #detail_mod::Storage::Inline(#array_name::new()),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::with_capacity()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.with_capacity),
+            /// only allocating memory on the heap if `capacity` is larger
+            /// than `N`.
+            pub fn with_capacity(capacity: usize) -> #small_vec_name #ty_generics_array {
+                let mut small_vec = #small_vec_name::new();
+                small_vec.reserve(capacity);
+                small_vec
+            }
+
+            /// Are the elements of this small vector stored on the heap?
+            pub fn spilled(&self) -> bool {
+                matches!(self.storage, #detail_mod::Storage::Heap(_))
+            }
+
+            /// Move the elements to a vector on the heap with at least the
+            /// given `capacity`, if they are currently stored inline.
+            fn spill(&mut self, capacity: usize) {
+                if let #detail_mod::Storage::Inline(ref mut array) = self.storage {
+                    let len = array.len;
+                    // the values are moved out of `array`, which must not drop
+                    // them
+                    array.len = 0;
+                    let mut vec = #vec_name::with_capacity(::std::cmp::max(capacity, len));
+                    let ptr = array.as_mut_ptr();
+                    for i in 0..len {
+                        vec.push(unsafe { ptr.add(i).read() });
+                    }
+                    self.storage = #detail_mod::Storage::Heap(vec);
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::capacity()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.capacity),
+            /// this is `N` while the elements are stored inline.
+            pub fn capacity(&self) -> usize {
+                match self.storage {
+                    #detail_mod::Storage::Inline(_) => N,
+                    #detail_mod::Storage::Heap(ref vec) => vec.capacity(),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::reserve()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.reserve),
+            /// moving the elements to the heap if they would not fit inline.
+            pub fn reserve(&mut self, additional: usize) {
+                let required = self.len().checked_add(additional).expect("capacity overflow");
+                match self.storage {
+                    #detail_mod::Storage::Inline(_) => {
+                        if required > N {
+                            self.spill(required);
+                        }
+                    }
+                    #detail_mod::Storage::Heap(ref mut vec) => vec.reserve(additional),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::shrink_to_fit()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.shrink_to_fit),
+            /// moving the elements back inline if they fit.
+            pub fn shrink_to_fit(&mut self) {
+                if let #detail_mod::Storage::Heap(ref mut vec) = self.storage {
+                    if vec.len() <= N {
+                        let len = vec.len();
+                        // the values are moved out of `vec`, which must not
+                        // drop them
+                        let ptr = vec.as_mut_ptr();
+                        unsafe { vec.set_len(0) };
+                        let mut array = #array_name::new();
+                        for i in 0..len {
+                            array.push(unsafe { ptr.add(i).read() });
+                        }
+                        self.storage = #detail_mod::Storage::Inline(array);
+                    } else {
+                        vec.shrink_to_fit();
+                    }
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::len()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.len),
+            /// all the fields share the same length.
+            pub fn len(&self) -> usize {
+                match self.storage {
+                    #detail_mod::Storage::Inline(ref array) => array.len(),
+                    #detail_mod::Storage::Heap(ref vec) => vec.len(),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::is_empty()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.is_empty),
+            /// all the fields share the same length.
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::push()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.push),
+            /// moving the elements to the heap if there are already `N` of them.
+            pub fn push(&mut self, value: #name #ty_generics) {
+                if let #detail_mod::Storage::Inline(ref array) = self.storage {
+                    if array.is_full() {
+                        self.spill(2 * N + 1);
+                    }
+                }
+
+                match self.storage {
+                    #detail_mod::Storage::Inline(ref mut array) => array.push(value),
+                    #detail_mod::Storage::Heap(ref mut vec) => vec.push(value),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::pop()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.pop).
+            pub fn pop(&mut self) -> Option<#name #ty_generics> {
+                match self.storage {
+                    #detail_mod::Storage::Inline(ref mut array) => array.pop(),
+                    #detail_mod::Storage::Heap(ref mut vec) => vec.pop(),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::insert()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.insert),
+            /// moving the elements to the heap if there are already `N` of them.
+            pub fn insert(&mut self, index: usize, element: #name #ty_generics) {
+                if let #detail_mod::Storage::Inline(ref array) = self.storage {
+                    if array.is_full() {
+                        self.spill(2 * N + 1);
+                    }
+                }
+
+                match self.storage {
+                    #detail_mod::Storage::Inline(ref mut array) => array.insert(index, element),
+                    #detail_mod::Storage::Heap(ref mut vec) => vec.insert(index, element),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::remove()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.remove).
+            pub fn remove(&mut self, index: usize) -> #name #ty_generics {
+                match self.storage {
+                    #detail_mod::Storage::Inline(ref mut array) => array.remove(index),
+                    #detail_mod::Storage::Heap(ref mut vec) => vec.remove(index),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::swap_remove()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.swap_remove).
+            pub fn swap_remove(&mut self, index: usize) -> #name #ty_generics {
+                match self.storage {
+                    #detail_mod::Storage::Inline(ref mut array) => array.swap_remove(index),
+                    #detail_mod::Storage::Heap(ref mut vec) => vec.swap_remove(index),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::truncate()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.truncate)
+            /// truncating all fields.
+            pub fn truncate(&mut self, len: usize) {
+                match self.storage {
+                    #detail_mod::Storage::Inline(ref mut array) => array.truncate(len),
+                    #detail_mod::Storage::Heap(ref mut vec) => vec.truncate(len),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::clear()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.clear),
+            /// keeping the elements on the heap if they were already there.
+            pub fn clear(&mut self) {
+                self.truncate(0);
+            }
+
+            /// Convert this small vector to a
+            #[doc = #vec_doc_url]
+            /// , moving the elements to the heap if needed.
+            pub fn into_vec(self) -> #vec_name #ty_generics {
+                let mut small_vec = self;
+                small_vec.spill(0);
+                match small_vec.storage {
+                    #detail_mod::Storage::Inline(_) => unreachable!("the elements should be on the heap"),
+                    #detail_mod::Storage::Heap(vec) => vec,
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_slice()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.as_slice).
+            pub fn as_slice(&self) -> #slice_name #ty_generics_elided {
+                match self.storage {
+                    #detail_mod::Storage::Inline(ref array) => array.as_slice(),
+                    #detail_mod::Storage::Heap(ref vec) => vec.as_slice(),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_mut_slice()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.as_mut_slice).
+            pub fn as_mut_slice(&mut self) -> #slice_mut_name #ty_generics_elided {
+                match self.storage {
+                    #detail_mod::Storage::Inline(ref mut array) => array.as_mut_slice(),
+                    #detail_mod::Storage::Heap(ref mut vec) => vec.as_mut_slice(),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_ptr()`](https://doc.rust-lang.org/std/struct.Vec.html#method.as_ptr).
+            /// The pointers are invalidated when the elements move to the heap.
+            pub fn as_ptr(&self) -> #ptr_name #ty_generics {
+                match self.storage {
+                    #detail_mod::Storage::Inline(ref array) => array.as_ptr(),
+                    #detail_mod::Storage::Heap(ref vec) => vec.as_ptr(),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_mut_ptr()`](https://doc.rust-lang.org/std/struct.Vec.html#method.as_mut_ptr).
+            /// The pointers are invalidated when the elements move to the heap.
+            pub fn as_mut_ptr(&mut self) -> #ptr_mut_name #ty_generics {
+                match self.storage {
+                    #detail_mod::Storage::Inline(ref mut array) => array.as_mut_ptr(),
+                    #detail_mod::Storage::Heap(ref mut vec) => vec.as_mut_ptr(),
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::get<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.get).
+            pub fn get<'a, I>(&'a self, index: I) -> Option<I::RefOutput>
+            where
+                I: ::soa_derive::SoAIndex<&'a #small_vec_name #ty_generics_array>
+            {
+                index.get(self)
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::get_unchecked<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.get_unchecked).
+            pub unsafe fn get_unchecked<'a, I>(&'a self, index: I) -> I::RefOutput
+            where
+                I: ::soa_derive::SoAIndex<&'a #small_vec_name #ty_generics_array>
+            {
+                index.get_unchecked(self)
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::index<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.index).
+            pub fn index<'a, I>(&'a self, index: I) -> I::RefOutput
+            where
+                I: ::soa_derive::SoAIndex<&'a #small_vec_name #ty_generics_array>
+            {
+                index.index(self)
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::get_mut<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.get_mut).
+            pub fn get_mut<'a, I>(&'a mut self, index: I) -> Option<I::MutOutput>
+            where
+                I: ::soa_derive::SoAIndexMut<&'a mut #small_vec_name #ty_generics_array>
+            {
+                index.get_mut(self)
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::get_unchecked_mut<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.get_unchecked_mut).
+            pub unsafe fn get_unchecked_mut<'a, I>(&'a mut self, index: I) -> I::MutOutput
+            where
+                I: ::soa_derive::SoAIndexMut<&'a mut #small_vec_name #ty_generics_array>
+            {
+                index.get_unchecked_mut(self)
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::index_mut<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.index_mut).
+            pub fn index_mut<'a, I>(&'a mut self, index: I) -> I::MutOutput
+            where
+                I: ::soa_derive::SoAIndexMut<&'a mut #small_vec_name #ty_generics_array>
+            {
+                index.index_mut(self)
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::iter()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.iter).
+            pub fn iter(&self) -> #iter_mod::Iter #ty_generics_elided {
+                self.as_slice().into_iter()
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::iter_mut()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.iter_mut).
+            pub fn iter_mut(&mut self) -> #iter_mod::IterMut #ty_generics_elided {
+                self.as_mut_slice().into_iter()
+            }
+        }
+
+        impl #impl_generics ::std::default::Default for #small_vec_name #ty_generics_array #where_clause {
+            fn default() -> Self {
+                #small_vec_name::new()
+            }
+        }
+
+        impl #impl_generics ::std::iter::Extend<#name #ty_generics> for #small_vec_name #ty_generics_array #where_clause {
+            fn extend<I: IntoIterator<Item=#name #ty_generics>>(&mut self, iter: I) {
+                let mut iter = iter.into_iter().peekable();
+                self.reserve(iter.size_hint().0);
+                if let #detail_mod::Storage::Inline(ref mut array) = self.storage {
+                    while !array.is_full() {
+                        match iter.next() {
+                            Some(value) => array.push(value),
+                            None => return,
+                        }
+                    }
+                }
+
+                // the remaining values are added to the vector one field at
+                // a time
+                if iter.peek().is_some() {
+                    let len = self.len();
+                    self.spill(::std::cmp::max(len + iter.size_hint().0, 2 * N + 1));
+                    if let #detail_mod::Storage::Heap(ref mut vec) = self.storage {
+                        vec.extend(iter);
+                    }
+                }
+            }
+        }
+
+        impl #impl_generics ::std::iter::FromIterator<#name #ty_generics> for #small_vec_name #ty_generics_array #where_clause {
+            fn from_iter<I: IntoIterator<Item=#name #ty_generics>>(iter: I) -> Self {
+                let mut result = #small_vec_name::new();
+                result.extend(iter);
+                result
+            }
+        }
+
+        impl #impl_generics_a IntoIterator for &'a #small_vec_name #ty_generics_array #where_clause {
+            type Item = <#slice_name #ty_generics_a as IntoIterator>::Item;
+            type IntoIter = <#slice_name #ty_generics_a as IntoIterator>::IntoIter;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.as_slice().into_iter()
+            }
+        }
+
+        impl #impl_generics_a IntoIterator for &'a mut #small_vec_name #ty_generics_array #where_clause {
+            type Item = <#slice_mut_name #ty_generics_a as IntoIterator>::Item;
+            type IntoIter = <#slice_mut_name #ty_generics_a as IntoIterator>::IntoIter;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.as_mut_slice().into_iter()
+            }
+        }
+    };
+
+    let with_bounds = |bound: TokenStream| {
+        let mut generics = generics_array.clone();
+        let predicates = fields_types.iter().map(|ty| -> WherePredicate { parse_quote!(#ty: #bound) });
+        generics.make_where_clause().predicates.extend(predicates);
+        generics.where_clause
+    };
+
+    // the traits derived for the vector are also implemented for the array,
+    // and the small vector uses the implementation of its current storage
+    if input.attrs.vec_derives("Clone") {
+        let clone_where_clause = with_bounds(quote!(::std::clone::Clone));
+        generated.append_all(quote! {
+            impl #impl_generics ::std::clone::Clone for #small_vec_name #ty_generics_array #clone_where_clause {
+                fn clone(&self) -> Self {
+                    let storage = match self.storage {
+                        #detail_mod::Storage::Inline(ref array) => #detail_mod::Storage::Inline(array.clone()),
+                        #detail_mod::Storage::Heap(ref vec) => #detail_mod::Storage::Heap(vec.clone()),
+                    };
+                    #small_vec_name { storage: storage }
+                }
+            }
+        });
+    }
+
+    if input.attrs.vec_derives("Debug") {
+        let debug_where_clause = with_bounds(quote!(::std::fmt::Debug));
+        let debug = if input.is_tuple() {
+            quote! {
+                f.debug_tuple(stringify!(#small_vec_name))
+                    #(.field(&slice.#fields_names))*
+                    .finish()
+            }
+        } else {
+            let fields_names_str = fields_names.iter().map(|field| quote!(#field).to_string());
+            quote! {
+                f.debug_struct(stringify!(#small_vec_name))
+                    #(.field(#fields_names_str, &slice.#fields_names))*
+                    .finish()
+            }
+        };
+        generated.append_all(quote! {
+            impl #impl_generics ::std::fmt::Debug for #small_vec_name #ty_generics_array #debug_where_clause {
+                fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                    let slice = self.as_slice();
+                    #debug
+                }
+            }
+        });
+    }
+
+    if input.attrs.vec_derives("PartialEq") {
+        let partial_eq_where_clause = with_bounds(quote!(::std::cmp::PartialEq));
+        generated.append_all(quote! {
+            impl #impl_generics ::std::cmp::PartialEq for #small_vec_name #ty_generics_array #partial_eq_where_clause {
+                fn eq(&self, other: &Self) -> bool {
+                    let (slice, other) = (self.as_slice(), other.as_slice());
+                    #(slice.#fields_names == other.#fields_names)&&*
+                }
+            }
+        });
+    }
+
+    if input.attrs.vec_derives("Eq") {
+        let eq_where_clause = with_bounds(quote!(::std::cmp::Eq));
+        generated.append_all(quote! {
+            impl #impl_generics ::std::cmp::Eq for #small_vec_name #ty_generics_array #eq_where_clause {}
+        });
+    }
+
+    return generated;
+}
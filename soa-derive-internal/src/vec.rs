@@ -1,58 +1,242 @@
 use proc_macro2::{Span, TokenStream};
-use syn::Ident;
+use syn::{Ident, WherePredicate, parse_quote};
 use quote::TokenStreamExt;
 use quote::quote;
 
-use crate::input::Input;
+use crate::input::{Input, VecLayout, is_primitive_float, nested_type};
+use crate::single_alloc;
 
 pub fn derive(input: &Input) -> TokenStream {
     let name = &input.name;
     let vec_name_str = format!("Vec<{}>", name);
-    let other_derive = &input.derive();
+    let slice_name_str = format!("[{}]", name);
+    let vec_attrs = &input.attrs.vec;
     let visibility = &input.visibility;
+    let allow_dead_code = &input.allow_dead_code();
     let vec_name = &input.vec_name();
     let slice_name = &input.slice_name();
     let slice_mut_name = &input.slice_mut_name();
     let ref_name = &input.ref_name();
+    let ref_mut_name = &input.ref_mut_name();
     let ptr_name = &input.ptr_name();
     let ptr_mut_name = &input.ptr_mut_name();
+    let slice_mut_doc_url = format!("[`{0}::apply_permutation`](struct.{0}.html#method.apply_permutation)", slice_mut_name);
 
-    let fields_names = input.fields.iter()
-                                   .map(|field| field.ident.clone().unwrap())
-                                   .collect::<Vec<_>>();
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let generics_elided = &input.generics_with_lifetime("'_");
+    let (_, ty_generics_elided, _) = generics_elided.split_for_impl();
+    let generics_a = &input.generics_outliving("'a");
+    let (impl_generics_a, ty_generics_a, _) = generics_a.split_for_impl();
+
+    let fields_names = input.fields_names();
+    let fields_vars = &input.fields_vars();
     let fields_names_1 = &fields_names;
     let fields_names_2 = &fields_names;
     let first_field = &fields_names[0];
+    let destructure_value = input.destructure(&quote!(value), &quote!(()));
+    let destructure_element = input.destructure(&quote!(element), &quote!(()));
+    let restructure = input.restructure();
 
     let fields_doc = fields_names.iter()
-                                 .map(|field| format!("A vector of `{0}` from a [`{1}`](struct.{1}.html)", field, name))
+                                 .map(|field| format!("A vector of `{0}` from a [`{1}`](struct.{1}.html)", quote!(#field), name))
                                  .collect::<Vec<_>>();
 
-    let fields_types = &input.fields.iter()
-                                    .map(|field| &field.ty)
-                                    .collect::<Vec<_>>();
+    let vec_types = &input.map_fields_nested_or(
+        |_, ty| nested_type(ty, "Vec", None),
+        |_, ty| quote!(Vec<#ty>),
+    );
+    // with `#[soa(private_fields)]`, the columns can only be modified through
+    // the methods of the vector, and always have the same length
+    let fields_visibility = if input.private_fields { quote!() } else { quote!(pub) };
+    let vec_body = input.struct_body_with_visibility(&fields_doc, vec_types, &fields_visibility);
+
+    let vec_new = &input.map_fields_nested_or(
+        |_, ty| {
+            let vec_type = nested_type(ty, "Vec", None);
+            quote!(<#vec_type>::new())
+        },
+        |_, _| quote!(Vec::new()),
+    );
+    let vec_with_capacity = &input.map_fields_nested_or(
+        |_, ty| {
+            let vec_type = nested_type(ty, "Vec", None);
+            quote!(<#vec_type>::with_capacity(capacity))
+        },
+        |_, _| quote!(Vec::with_capacity(capacity)),
+    );
+    let vec_from_raw_parts = &input.map_fields_nested_or(
+        |name, ty| {
+            let vec_type = nested_type(ty, "Vec", None);
+            quote!(<#vec_type>::from_raw_parts(data.#name, len, capacity))
+        },
+        |name, _| quote!(Vec::from_raw_parts(data.#name, len, capacity)),
+    );
+    let as_slice = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.as_slice()),
+        |name, _| quote!(&self.#name),
+    );
+    let as_mut_slice = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.as_mut_slice()),
+        |name, _| quote!(&mut self.#name),
+    );
+    let slice = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.slice(range.clone())),
+        |name, _| quote!(&self.#name[range.clone()]),
+    );
+    let slice_mut = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.slice_mut(range.clone())),
+        |name, _| quote!(&mut self.#name[range.clone()]),
+    );
+    let extend_from_slice = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.extend(other.#name)),
+        |name, _| quote!(self.#name.extend_from_slice(other.#name)),
+    );
+    let extend_from_ref = &input.map_fields_nested_or(
+        |name, _| quote!(self.#name.extend(::std::iter::once(value.#name))),
+        |name, _| quote!(self.#name.push(::std::clone::Clone::clone(value.#name))),
+    );
+    let clone_where_clause = {
+        let mut generics = generics_a.clone();
+        let predicates = input.map_fields_nested_or(
+            |_, ty| {
+                let vec_type = nested_type(ty, "Vec", None);
+                let ref_type = nested_type(ty, "Ref", Some("'a"));
+                quote!(#vec_type: ::std::iter::Extend<#ref_type>)
+            },
+            // the bound is only checked when the functions are used, since
+            // the fields are not required to implement `Clone`
+            |_, ty| quote!(for<'c> #ty: ::std::clone::Clone),
+        );
+        generics.make_where_clause().predicates.extend(predicates.iter().map(|predicate| -> WherePredicate { parse_quote!(#predicate) }));
+        generics.where_clause
+    };
+
+    let fields_names_str = fields_names.iter().map(|field| quote!(#field).to_string()).collect::<Vec<_>>();
+    let len_messages = fields_names_str.iter()
+        .map(|field| format!("the `{}` field has a different length than the others", field))
+        .collect::<Vec<_>>();
+
+    // structs without fields only have a private `len` column
+    let columns = if input.is_fieldless() {
+        quote!()
+    } else {
+        let check_columns_length = check_columns_length(input);
+        quote! {
+            /// Create a new vector from the values of each field, checking
+            /// that all of them have the same length.
+            pub fn from_columns(#(#fields_vars: #vec_types),*) -> Result<#vec_name #ty_generics, ::soa_derive::LengthMismatch> {
+                #check_columns_length
+                Ok(#vec_name {
+                    #(#fields_names_1: #fields_vars,)*
+                })
+            }
+
+            /// Split this vector into the values of each field, in the same
+            /// order as the fields of the struct.
+            pub fn into_columns(self) -> (#(#vec_types,)*) {
+                (#(self.#fields_names_1,)*)
+            }
+        }
+    };
+
+    // accessors for the fields of vectors with `#[soa(private_fields)]`. The
+    // returned slices can not change the length of the columns.
+    let accessors = if input.private_fields {
+        input.map_fields_nested_or(
+            |name, ty| {
+                let slice_type = nested_type(ty, "Slice", Some("'_"));
+                let slice_mut_type = nested_type(ty, "SliceMut", Some("'_"));
+                let name_mut = Ident::new(&format!("{}_mut", quote!(#name)), Span::call_site());
+                let doc = format!("Get the values of the `{}` field", quote!(#name));
+                let doc_mut = format!("Get mutable access to the values of the `{}` field", quote!(#name));
+                quote! {
+                    #[doc = #doc]
+                    pub fn #name(&self) -> #slice_type {
+                        self.#name.as_slice()
+                    }
+
+                    #[doc = #doc_mut]
+                    pub fn #name_mut(&mut self) -> #slice_mut_type {
+                        self.#name.as_mut_slice()
+                    }
+                }
+            },
+            |name, ty| {
+                let name_mut = Ident::new(&format!("{}_mut", quote!(#name)), Span::call_site());
+                let doc = format!("Get the values of the `{}` field", quote!(#name));
+                let doc_mut = format!("Get mutable access to the values of the `{}` field", quote!(#name));
+                quote! {
+                    #[doc = #doc]
+                    pub fn #name(&self) -> &[#ty] {
+                        &self.#name
+                    }
+
+                    #[doc = #doc_mut]
+                    pub fn #name_mut(&mut self) -> &mut [#ty] {
+                        &mut self.#name
+                    }
+                }
+            },
+        )
+    } else {
+        Vec::new()
+    };
 
-    let mut generated = quote! {
+    let sort_by_fields = input.column_methods("sort_by_field_").into_iter().map(|(name, ty, method)| {
+        let doc = format!(
+            "Sort this vector by the values of the `{}` field, using only this \
+            column to compute the permutation before moving the elements of \
+            all fields. See", quote!(#name)
+        );
+        let slice_doc_url = format!("[`{0}::{1}`](struct.{0}.html#method.{1})", slice_mut_name, method);
+        let where_clause = if is_primitive_float(&ty) {
+            quote!()
+        } else {
+            quote!(where for<'c> #ty: ::std::cmp::Ord)
+        };
+        quote! {
+            #[doc = #doc]
+            #[doc = #slice_doc_url]
+            /// for more information.
+            pub fn #method(&mut self) #where_clause {
+                self.as_mut_slice().#method();
+            }
+        }
+    }).collect::<Vec<_>>();
+
+    let retain_by_fields = input.column_methods("retain_by_").into_iter().map(|(name, ty, method)| {
+        let doc = format!(
+            "Retain only the elements for which `f` returns `true` when \
+            called with the value of their `{}` field. Only this column is \
+            read to create the mask used by `retain_mask`, which is a \
+            `Vec<bool>` with one value per element.", quote!(#name)
+        );
+        quote! {
+            #[doc = #doc]
+            pub fn #method<F>(&mut self, f: F) where F: FnMut(&#ty) -> bool {
+                let mask = self.as_slice().#name.iter().map(f).collect::<Vec<_>>();
+                self.retain_mask(&mask);
+            }
+        }
+    }).collect::<Vec<_>>();
+
+    let storage = quote! {
         /// An analog to `
         #[doc = #vec_name_str]
         /// ` with Struct of Array (SoA) layout
-        #[allow(dead_code)]
-        #other_derive
-        #visibility struct #vec_name {
-            #(
-                #[doc = #fields_doc]
-                pub #fields_names_1: Vec<#fields_types>,
-            )*
-        }
+        #allow_dead_code
+        #(#[#vec_attrs])*
+        #visibility struct #vec_name #generics #vec_body
 
-        #[allow(dead_code)]
-        impl #vec_name {
+        #allow_dead_code
+        impl #impl_generics #vec_name #ty_generics #where_clause {
             /// Similar to [`
             #[doc = #vec_name_str]
             /// ::new()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.new)
-            pub fn new() -> #vec_name {
+            pub fn new() -> #vec_name #ty_generics {
                 #vec_name {
-                    #(#fields_names_1 : Vec::new(),)*
+                    #(#fields_names_1 : #vec_new,)*
                 }
             }
 
@@ -60,12 +244,16 @@ pub fn derive(input: &Input) -> TokenStream {
             #[doc = #vec_name_str]
             /// ::with_capacity()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.with_capacity),
             /// initializing all fields with the given `capacity`.
-            pub fn with_capacity(capacity: usize) -> #vec_name {
+            pub fn with_capacity(capacity: usize) -> #vec_name #ty_generics {
                 #vec_name {
-                    #(#fields_names_1 : Vec::with_capacity(capacity),)*
+                    #(#fields_names_1 : #vec_with_capacity,)*
                 }
             }
 
+            #columns
+
+            #(#accessors)*
+
             /// Similar to [`
             #[doc = #vec_name_str]
             /// ::capacity()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.capacity),
@@ -111,9 +299,9 @@ pub fn derive(input: &Input) -> TokenStream {
             /// Similar to [`
             #[doc = #vec_name_str]
             /// ::push()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.push).
-            pub fn push(&mut self, value: #name) {
-                let #name{#(#fields_names_1),*} = value;
-                #(self.#fields_names_1.push(#fields_names_2);)*
+            pub fn push(&mut self, value: #name #ty_generics) {
+                #destructure_value
+                #(self.#fields_names_1.push(#fields_vars);)*
             }
 
             /// Similar to [`
@@ -122,7 +310,7 @@ pub fn derive(input: &Input) -> TokenStream {
             /// all the fields should have the same length.
             pub fn len(&self) -> usize {
                 let len = self.#first_field.len();
-                #(debug_assert_eq!(self.#fields_names_1.len(), len);)*
+                #(debug_assert_eq!(self.#fields_names_1.len(), len, #len_messages);)*
                 len
             }
 
@@ -139,49 +327,49 @@ pub fn derive(input: &Input) -> TokenStream {
             /// Similar to [`
             #[doc = #vec_name_str]
             /// ::swap_remove()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.swap_remove).
-            pub fn swap_remove(&mut self, index: usize) -> #name {
+            pub fn swap_remove(&mut self, index: usize) -> #name #ty_generics {
                 #(
-                    let #fields_names_1 = self.#fields_names_2.swap_remove(index);
+                    let #fields_vars = self.#fields_names_1.swap_remove(index);
                 )*
-                #name{#(#fields_names_1: #fields_names_2),*}
+                #restructure
             }
 
             /// Similar to [`
             #[doc = #vec_name_str]
             /// ::insert()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.insert).
-            pub fn insert(&mut self, index: usize, element: #name) {
-                let #name{#(#fields_names_1),*} = element;
-                #(self.#fields_names_1.insert(index, #fields_names_2);)*
+            pub fn insert(&mut self, index: usize, element: #name #ty_generics) {
+                #destructure_element
+                #(self.#fields_names_1.insert(index, #fields_vars);)*
             }
 
             /// Similar to [`
             #[doc = #vec_name_str]
             /// ::remove()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.remove).
-            pub fn remove(&mut self, index: usize) -> #name {
+            pub fn remove(&mut self, index: usize) -> #name #ty_generics {
                 #(
-                    let #fields_names_1 = self.#fields_names_2.remove(index);
+                    let #fields_vars = self.#fields_names_1.remove(index);
                 )*
-                #name{#(#fields_names_1: #fields_names_2),*}
+                #restructure
             }
 
             /// Similar to [`
             #[doc = #vec_name_str]
             /// ::pop()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.pop).
-            pub fn pop(&mut self) -> Option<#name> {
+            pub fn pop(&mut self) -> Option<#name #ty_generics> {
                 if self.is_empty() {
                     None
                 } else {
                     #(
-                        let #fields_names_1 = self.#fields_names_2.pop().unwrap();
+                        let #fields_vars = self.#fields_names_1.pop().unwrap();
                     )*
-                    Some(#name{#(#fields_names_1: #fields_names_2),*})
+                    Some(#restructure)
                 }
             }
 
             /// Similar to [`
             #[doc = #vec_name_str]
             /// ::append()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.append).
-            pub fn append(&mut self, other: &mut #vec_name) {
+            pub fn append(&mut self, other: &mut #vec_name #ty_generics) {
                 #(
                     self.#fields_names_1.append(&mut other.#fields_names_2);
                 )*
@@ -197,7 +385,7 @@ pub fn derive(input: &Input) -> TokenStream {
             /// Similar to [`
             #[doc = #vec_name_str]
             /// ::split_off()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.split_off).
-            pub fn split_off(&mut self, at: usize) -> #vec_name {
+            pub fn split_off(&mut self, at: usize) -> #vec_name #ty_generics {
                 #vec_name {
                     #(#fields_names_1 : self.#fields_names_2.split_off(at), )*
                 }
@@ -206,65 +394,241 @@ pub fn derive(input: &Input) -> TokenStream {
             /// Similar to [`
             #[doc = #vec_name_str]
             /// ::as_slice()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.as_slice).
-            pub fn as_slice(&self) -> #slice_name {
+            pub fn as_slice(&self) -> #slice_name #ty_generics_elided {
                 #slice_name {
-                    #(#fields_names_1 : &self.#fields_names_2, )*
+                    #(#fields_names_1 : #as_slice, )*
                 }
             }
 
             /// Similar to [`
             #[doc = #vec_name_str]
             /// ::as_mut_slice()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.as_mut_slice).
-            pub fn as_mut_slice(&mut self) -> #slice_mut_name {
+            pub fn as_mut_slice(&mut self) -> #slice_mut_name #ty_generics_elided {
                 #slice_mut_name {
-                    #(#fields_names_1 : &mut self.#fields_names_2, )*
+                    #(#fields_names_1 : #as_mut_slice, )*
                 }
             }
 
             /// Create a slice of this vector matching the given `range`. This
             /// is analogous to `Index<Range<usize>>`.
-            pub fn slice(&self, range: ::std::ops::Range<usize>) -> #slice_name {
+            pub fn slice(&self, range: ::std::ops::Range<usize>) -> #slice_name #ty_generics_elided {
                 #slice_name {
-                    #(#fields_names_1 : &self.#fields_names_2[range.clone()], )*
+                    #(#fields_names_1 : #slice, )*
                 }
             }
 
             /// Create a mutable slice of this vector matching the given
             /// `range`. This is analogous to `IndexMut<Range<usize>>`.
-            pub fn slice_mut(&mut self, range: ::std::ops::Range<usize>) -> #slice_mut_name {
+            pub fn slice_mut(&mut self, range: ::std::ops::Range<usize>) -> #slice_mut_name #ty_generics_elided {
                 #slice_mut_name {
-                    #(#fields_names_1 : &mut self.#fields_names_2[range.clone()], )*
+                    #(#fields_names_1 : #slice_mut, )*
+                }
+            }
+
+            /// Retain only the elements for which the corresponding value in
+            /// `mask` is `true`, removing all the other elements. The retained
+            /// elements are moved one row at a time, keeping their order.
+            ///
+            /// If dropping one of the removed elements panics, the vector only
+            /// contains the elements retained before this one, and the
+            /// elements after it are leaked.
+            ///
+            /// # Panics
+            ///
+            /// This function panics if `mask` and this vector have different
+            /// lengths.
+            pub fn retain_mask(&mut self, mask: &[bool]) {
+                assert_eq!(mask.len(), self.len(), "the mask length must be the same as the vector length");
+
+                let ptr = self.as_mut_ptr();
+                // the length only covers the elements already moved to their
+                // final position, the other ones are leaked if a destructor
+                // panics
+                unsafe { self.set_len(0) };
+                let mut kept = 0;
+                for (i, &keep) in mask.iter().enumerate() {
+                    unsafe {
+                        if keep {
+                            if i != kept {
+                                ptr.add(kept).write(ptr.add(i).read());
+                            }
+                            kept += 1;
+                            self.set_len(kept);
+                        } else {
+                            let _ = ptr.add(i).read();
+                        }
+                    }
                 }
             }
 
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_ptr()`](https://doc.rust-lang.org/std/struct.Vec.html#method.as_ptr).
+            pub fn as_ptr(&self) -> #ptr_name #ty_generics {
+                #ptr_name {
+                    #(#fields_names_1: self.#fields_names_2.as_ptr(),)*
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::as_mut_ptr()`](https://doc.rust-lang.org/std/struct.Vec.html#method.as_mut_ptr).
+            pub fn as_mut_ptr(&mut self) -> #ptr_mut_name #ty_generics {
+                #ptr_mut_name {
+                    #(#fields_names_1: self.#fields_names_2.as_mut_ptr(),)*
+                }
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::set_len()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.set_len),
+            /// setting the length of all fields.
+            pub unsafe fn set_len(&mut self, new_len: usize) {
+                #(self.#fields_names_1.set_len(new_len);)*
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::from_raw_parts()`](https://doc.rust-lang.org/std/struct.Vec.html#method.from_raw_parts).
+            pub unsafe fn from_raw_parts(data: #ptr_mut_name #ty_generics, len: usize, capacity: usize) -> #vec_name #ty_generics {
+                #vec_name {
+                    #(#fields_names_1: #vec_from_raw_parts,)*
+                }
+            }
+        }
+    };
+
+    let mut generated = if input.layout == VecLayout::SingleAlloc {
+        single_alloc::derive(input)
+    } else {
+        storage
+    };
+
+    generated.append_all(quote! {
+        #allow_dead_code
+        impl #impl_generics #vec_name #ty_generics #where_clause {
             /// Similar to [`
             #[doc = #vec_name_str]
             /// ::retain()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.retain).
-            pub fn retain<F>(&mut self, mut f: F) where F: FnMut(#ref_name) -> bool {
+            ///
+            /// The predicate is called once for each element in order to
+            /// create a mask, which is then used to remove elements from all
+            /// fields with `retain_mask`. The mask is a `Vec<bool>` with one
+            /// value per element, allocated for each call.
+            pub fn retain<F>(&mut self, f: F) where F: FnMut(#ref_name #ty_generics_elided) -> bool {
+                let mask = self.iter().map(f).collect::<Vec<_>>();
+                self.retain_mask(&mask);
+            }
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::retain_mut()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.retain_mut).
+            ///
+            /// The predicate is called once for each element in order to
+            /// create a mask, which is then used to remove elements from all
+            /// fields with `retain_mask`. The mask is a `Vec<bool>` with one
+            /// value per element, allocated for each call.
+            pub fn retain_mut<F>(&mut self, f: F) where F: FnMut(#ref_mut_name #ty_generics_elided) -> bool {
+                let mask = self.iter_mut().map(f).collect::<Vec<_>>();
+                self.retain_mask(&mask);
+            }
+
+            #(#retain_by_fields)*
+
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::dedup_by()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.dedup_by).
+            ///
+            /// `same_bucket` is called with the current element and the
+            /// previous element kept in the vector, and the current element is
+            /// removed if it returns `true`.
+            pub fn dedup_by<F>(&mut self, mut same_bucket: F) where F: FnMut(#ref_mut_name #ty_generics_elided, #ref_mut_name #ty_generics_elided) -> bool {
                 let len = self.len();
-                let mut del = 0;
+                if len <= 1 {
+                    return;
+                }
 
+                let mut kept = 1;
                 {
                     let mut slice = self.as_mut_slice();
-                    for i in 0..len {
-                        if !f(slice.get(i).unwrap()) {
-                            del += 1;
-                        } else if del > 0 {
-                            slice.swap(i - del, i);
+                    for i in 1..len {
+                        let duplicate = {
+                            let (mut previous, mut current) = slice.split_at_mut(i);
+                            same_bucket(current.index_mut(0), previous.index_mut(kept - 1))
+                        };
+                        if !duplicate {
+                            if i != kept {
+                                slice.swap(i, kept);
+                            }
+                            kept += 1;
                         }
                     }
                 }
-                if del > 0 {
-                    self.truncate(len - del);
-                }
+                self.truncate(kept);
             }
 
+            /// Similar to [`
+            #[doc = #vec_name_str]
+            /// ::dedup_by_key()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.dedup_by_key).
+            pub fn dedup_by_key<F, K>(&mut self, mut key: F)
+            where
+                F: FnMut(#ref_mut_name #ty_generics_elided) -> K,
+                K: PartialEq,
+            {
+                self.dedup_by(|a, b| key(a) == key(b));
+            }
+
+            /// Similar to [`
+            #[doc = #slice_name_str]
+            /// ::sort_by()`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_by),
+            /// moving the elements of all fields.
+            pub fn sort_by<F>(&mut self, compare: F)
+            where
+                F: FnMut(#ref_name #ty_generics_elided, #ref_name #ty_generics_elided) -> ::std::cmp::Ordering
+            {
+                self.as_mut_slice().sort_by(compare);
+            }
+
+            /// Similar to [`
+            #[doc = #slice_name_str]
+            /// ::sort_by_key()`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_by_key),
+            /// moving the elements of all fields.
+            pub fn sort_by_key<F, K>(&mut self, f: F)
+            where
+                F: FnMut(#ref_name #ty_generics_elided) -> K,
+                K: Ord,
+            {
+                self.as_mut_slice().sort_by_key(f);
+            }
+
+            /// Similar to [`
+            #[doc = #slice_name_str]
+            /// ::sort_unstable_by()`](https://doc.rust-lang.org/std/primitive.slice.html#method.sort_unstable_by),
+            /// moving the elements of all fields.
+            pub fn sort_unstable_by<F>(&mut self, compare: F)
+            where
+                F: FnMut(#ref_name #ty_generics_elided, #ref_name #ty_generics_elided) -> ::std::cmp::Ordering
+            {
+                self.as_mut_slice().sort_unstable_by(compare);
+            }
+
+            /// Reorder the elements of this vector in place, so that the
+            /// element at index `i` is the one that was previously at index
+            /// `permutation[i]`. See
+            #[doc = #slice_mut_doc_url]
+            /// for more information.
+            pub fn apply_permutation(&mut self, permutation: &[usize]) {
+                self.as_mut_slice().apply_permutation(permutation);
+            }
+
+            #(#sort_by_fields)*
+
             /// Similar to [`
             #[doc = #vec_name_str]
             /// ::get<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.get).
             pub fn get<'a, I>(&'a self, index: I) -> Option<I::RefOutput>
             where
-                I: ::soa_derive::SoAIndex<&'a #vec_name>
+                I: ::soa_derive::SoAIndex<&'a #vec_name #ty_generics>
             {
                 index.get(self)
             }
@@ -274,7 +638,7 @@ pub fn derive(input: &Input) -> TokenStream {
             /// ::get_unchecked<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.get_unchecked).
             pub unsafe fn get_unchecked<'a, I>(&'a self, index: I) -> I::RefOutput
             where
-                I: ::soa_derive::SoAIndex<&'a #vec_name>
+                I: ::soa_derive::SoAIndex<&'a #vec_name #ty_generics>
             {
                 index.get_unchecked(self)
             }
@@ -284,7 +648,7 @@ pub fn derive(input: &Input) -> TokenStream {
             /// ::index<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.index).
             pub fn index<'a, I>(&'a self, index: I) -> I::RefOutput
             where
-                I: ::soa_derive::SoAIndex<&'a #vec_name>
+                I: ::soa_derive::SoAIndex<&'a #vec_name #ty_generics>
             {
                 index.index(self)
             }
@@ -294,7 +658,7 @@ pub fn derive(input: &Input) -> TokenStream {
             /// ::get_mut<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.get_mut).
             pub fn get_mut<'a, I>(&'a mut self, index: I) -> Option<I::MutOutput>
             where
-                I: ::soa_derive::SoAIndexMut<&'a mut #vec_name>
+                I: ::soa_derive::SoAIndexMut<&'a mut #vec_name #ty_generics>
             {
                 index.get_mut(self)
             }
@@ -304,7 +668,7 @@ pub fn derive(input: &Input) -> TokenStream {
             /// ::get_unchecked_mut<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.get_unchecked_mut).
             pub unsafe fn get_unchecked_mut<'a, I>(&'a mut self, index: I) -> I::MutOutput
             where
-                I: ::soa_derive::SoAIndexMut<&'a mut #vec_name>
+                I: ::soa_derive::SoAIndexMut<&'a mut #vec_name #ty_generics>
             {
                 index.get_unchecked_mut(self)
             }
@@ -314,50 +678,63 @@ pub fn derive(input: &Input) -> TokenStream {
             /// ::index_mut<I>()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.index_mut).
             pub fn index_mut<'a, I>(&'a mut self, index: I) -> I::MutOutput
             where
-                I: ::soa_derive::SoAIndexMut<&'a mut #vec_name>
+                I: ::soa_derive::SoAIndexMut<&'a mut #vec_name #ty_generics>
             {
                 index.index_mut(self)
             }
+        }
+    });
 
-            /// Similar to [`
-            #[doc = #vec_name_str]
-            /// ::as_ptr()`](https://doc.rust-lang.org/std/struct.Vec.html#method.as_ptr).
-            pub fn as_ptr(&self) -> #ptr_name {
-                #ptr_name {
-                    #(#fields_names_1: self.#fields_names_2.as_ptr(),)*
+    if input.layout == VecLayout::Separate {
+        generated.append_all(quote!{
+            impl #impl_generics ::std::iter::Extend<#name #ty_generics> for #vec_name #ty_generics #where_clause {
+                fn extend<I: IntoIterator<Item=#name #ty_generics>>(&mut self, iter: I) {
+                    let iter = iter.into_iter();
+                    // reserve space in all the columns at once, and then push
+                    // each field directly to its column
+                    self.reserve(iter.size_hint().0);
+                    for value in iter {
+                        #destructure_value
+                        #(self.#fields_names_1.push(#fields_vars);)*
+                    }
                 }
             }
 
-            /// Similar to [`
-            #[doc = #vec_name_str]
-            /// ::as_mut_ptr()`](https://doc.rust-lang.org/std/struct.Vec.html#method.as_mut_ptr).
-            pub fn as_mut_ptr(&mut self) -> #ptr_mut_name {
-                #ptr_mut_name {
-                    #(#fields_names_1: self.#fields_names_2.as_mut_ptr(),)*
+            #allow_dead_code
+            impl #impl_generics_a #vec_name #ty_generics #clone_where_clause {
+                /// Similar to [`
+                #[doc = #vec_name_str]
+                /// ::extend_from_slice()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.extend_from_slice),
+                /// cloning and appending all the elements in `other` to this
+                /// vector, one field at a time.
+                pub fn extend_from_slice(&mut self, other: #slice_name #ty_generics_a) {
+                    #(#extend_from_slice;)*
                 }
             }
 
-            /// Similar to [`
-            #[doc = #vec_name_str]
-            /// ::from_raw_parts()`](https://doc.rust-lang.org/std/struct.Vec.html#method.from_raw_parts).
-            pub unsafe fn from_raw_parts(data: #ptr_mut_name, len: usize, capacity: usize) -> #vec_name {
-                #vec_name {
-                    #(#fields_names_1: Vec::from_raw_parts(data.#fields_names_2, len, capacity),)*
+            impl #impl_generics_a ::std::iter::Extend<#ref_name #ty_generics_a> for #vec_name #ty_generics #clone_where_clause {
+                fn extend<I: IntoIterator<Item=#ref_name #ty_generics_a>>(&mut self, iter: I) {
+                    let iter = iter.into_iter();
+                    self.reserve(iter.size_hint().0);
+                    for value in iter {
+                        #(#extend_from_ref;)*
+                    }
                 }
             }
-        }
-    };
+        });
+    }
 
-    if input.derives.contains(&Ident::new("Clone", Span::call_site())) {
+    if input.layout == VecLayout::Separate && input.attrs.vec_derives("Clone") {
         generated.append_all(quote!{
-            #[allow(dead_code)]
-            impl #vec_name {
+            #allow_dead_code
+            impl #impl_generics #vec_name #ty_generics #where_clause {
                 /// Similar to [`
                 #[doc = #vec_name_str]
                 /// ::resize()`](https://doc.rust-lang.org/std/vec/struct.Vec.html#method.resize).
-                pub fn resize<T>(&mut self, new_len: usize, value: #name) {
+                pub fn resize(&mut self, new_len: usize, value: #name #ty_generics) {
+                    #destructure_value
                     #(
-                        self.#fields_names_1.resize(new_len, value.#fields_names_2);
+                        self.#fields_names_1.resize(new_len, #fields_vars);
                     )*
                 }
             }
@@ -366,3 +743,28 @@ pub fn derive(input: &Input) -> TokenStream {
 
     return generated;
 }
+
+/// Generate code checking that the local variables named after `fields_vars()`
+/// all have the same length, returning a `LengthMismatch` error otherwise. The
+/// length of the first field is stored in `expected`.
+pub fn check_columns_length(input: &Input) -> TokenStream {
+    let fields_vars = input.fields_vars();
+    let first_var = &fields_vars[0];
+    let other_vars = &fields_vars[1..];
+    let other_fields_str = input.fields_names()[1..].iter()
+        .map(|field| quote!(#field).to_string())
+        .collect::<Vec<_>>();
+
+    quote! {
+        let expected = #first_var.len();
+        #(
+            if #other_vars.len() != expected {
+                return Err(::soa_derive::LengthMismatch {
+                    field: #other_fields_str,
+                    len: #other_vars.len(),
+                    expected: expected,
+                });
+            }
+        )*
+    }
+}
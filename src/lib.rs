@@ -4,7 +4,6 @@
 //!
 //! ```
 //! # #[macro_use] extern crate soa_derive;
-//! # fn main() {
 //! #[derive(StructOfArray)]
 //! pub struct Cheese {
 //!     pub smell: f64,
@@ -12,7 +11,7 @@
 //!     pub with_mushrooms: bool,
 //!     pub name: String,
 //! }
-//! # }
+//! # fn main() {}
 //! ```
 //!
 //! will generate a `CheeseVec` struct that looks like this:
@@ -40,7 +39,6 @@
 //!
 //! ```
 //! # #[macro_use] extern crate soa_derive;
-//! # fn main() {
 //! #[derive(Debug, PartialEq, StructOfArray)]
 //! #[soa_derive = "Debug, PartialEq"]
 //! pub struct Cheese {
@@ -49,6 +47,278 @@
 //!     pub with_mushrooms: bool,
 //!     pub name: String,
 //! }
+//! # fn main() {}
+//! ```
+//!
+//! To add attributes to a single one of the generated types, you can use
+//! `#[soa_attr(Type, attribute)]`, where `Type` is one of `Vec`, `Slice`,
+//! `SliceMut`, `Ref`, `RefMut`, `Ptr` or `PtrMut`. `#[soa_derive]` does not
+//! derive `Clone` and `Deserialize` for the types other than `Vec`, since they
+//! already implement `Copy` and `Clone`, and can not be deserialized.
+//!
+//! ```
+//! # #[macro_use] extern crate soa_derive;
+//! #[derive(Debug, PartialEq, StructOfArray)]
+//! #[soa_derive = "Debug"]
+//! #[soa_attr(Vec, derive(Default, Clone))]
+//! #[soa_attr(Ref, derive(PartialEq, PartialOrd))]
+//! pub struct Cheese {
+//!     pub smell: f64,
+//!     pub name: String,
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! The names of the generated types can be changed with
+//! `#[soa(vec = "Particles", slice = "ParticlesView", ref = "ParticleView")]`,
+//! using the `vec`, `slice`, `slice_mut`, `ref`, `ref_mut`, `ptr` and `ptr_mut`
+//! keys.
+//!
+//! Tuple structs are supported as well, and generate tuple structs: deriving
+//! `StructOfArray` for `struct Point(f64, f64)` creates
+//! `PointVec(pub Vec<f64>, pub Vec<f64>)`, `PointRef<'a>(pub &'a f64, pub &'a
+//! f64)`, *etc.*
+//!
+//! Structs without any field (such as `struct Marker;`) are supported too: the
+//! generated containers then only keep track of the number of elements, in the
+//! same way as a `Vec<()>` would.
+//!
+//! Fields marked with `#[soa(skip)]` are not stored in the generated types,
+//! which is useful for rarely used data that should not be part of the hot
+//! arrays. Since their value is lost when adding an element to a vector, these
+//! fields must implement `Default`, which is used to reconstruct the struct in
+//! `pop`, `remove`, *etc.*
+//!
+//! Since the fields of `CheeseVec` are public vectors, pushing to a single one
+//! of them breaks the invariant that all fields have the same length. A vector
+//! can be created from existing columns with `CheeseVec::from_columns(smell,
+//! color, with_mushrooms, name)`, which checks the length of all columns and
+//! returns a `LengthMismatch` error otherwise; and `into_columns()` does the
+//! opposite operation. With `#[soa(private_fields)]`, the fields of the vector
+//! are private and can only be accessed through `smell()` and `smell_mut()`
+//! methods returning slices, which can not change the length of the columns.
+//! Fields whose accessors would have the same name as a method of the vector
+//! (such as `len` or `iter`) are rejected in this case.
+//!
+//! Generic structs are also supported, and all the generated types will carry
+//! the same generic parameters, bounds and where clauses:
+//!
+//! ```
+//! # #[macro_use] extern crate soa_derive;
+//! #[derive(StructOfArray)]
+//! pub struct Particle<T: Copy> {
+//!     pub position: [T; 3],
+//!     pub mass: T,
+//! }
+//!
+//! # fn main() {
+//! let mut particles: ParticleVec<f32> = ParticleVec::new();
+//! particles.push(Particle { position: [0.0; 3], mass: 1.0 });
+//! # }
+//! ```
+//!
+//! ## Nested struct of arrays
+//!
+//! Fields whose type also derives `StructOfArray` can be marked with
+//! `#[nested_soa]`, and will then be stored using the corresponding generated
+//! types instead of a `Vec`:
+//!
+//! ```
+//! # #[macro_use] extern crate soa_derive;
+//! #[derive(Debug, PartialEq, StructOfArray)]
+//! #[soa_derive = "Debug, PartialEq"]
+//! pub struct Vector3 {
+//!     pub x: f64,
+//!     pub y: f64,
+//!     pub z: f64,
+//! }
+//!
+//! #[derive(Debug, PartialEq, StructOfArray)]
+//! #[soa_derive = "Debug, PartialEq"]
+//! pub struct Particle {
+//!     #[nested_soa]
+//!     pub position: Vector3,
+//!     pub mass: f64,
+//! }
+//!
+//! # fn main() {
+//! let mut particles = ParticleVec::new();
+//! particles.push(Particle { position: Vector3 { x: 1.0, y: 2.0, z: 3.0 }, mass: 1.0 });
+//!
+//! // the x coordinates of all particles are stored in a contiguous Vec<f64>
+//! let x: &Vec<f64> = &particles.position.x;
+//! let position: Vector3Ref = particles.index(0).position;
+//! # }
+//! ```
+//!
+//! The nested type must derive the same traits with `#[soa_derive]` as the
+//! outer type. The types generated for it (`Vector3Vec`, `Vector3Ref`, *etc.*)
+//! are found through the [`StructOfArray`] and [`SoAIter`] implementations of
+//! the field type, so they do not need to be in scope.
+//!
+//! ## Serialization
+//!
+//! With the `serde` feature enabled, `#[soa(serde = "columns")]` implements
+//! `Serialize` for `CheeseVec`, `CheeseSlice` and `CheeseRef`, and
+//! `Deserialize` for `CheeseVec`. Vectors and slices are then serialized with
+//! one sequence for each field (`{"smell": [...], "name": [...]}`), and
+//! deserializing a vector fails if these sequences have different lengths.
+//! `#[soa(serde = "rows")]` uses the same format as `Vec<Cheese>` instead
+//! (`[{"smell": ..., "name": ...}, ...]`), and requires `Cheese` to implement
+//! `Deserialize`. Fields marked with `#[soa(skip)]` are not serialized, and
+//! `#[nested_soa]` fields use the serialization of the nested type. The
+//! `#[serde(...)]` attributes of the fields, such as `rename` or `skip`, also
+//! apply to the corresponding columns and references. A column skipped with
+//! `#[serde(skip)]` is deserialized as an empty column, so only empty vectors
+//! can be deserialized in this case.
+//!
+//! ## Memory layout
+//!
+//! With `#[soa(layout = "single_alloc")]`, all the fields of `CheeseVec` are
+//! stored in a single allocation instead of one `Vec` for each field. Each
+//! field starts at an offset aligned for its type, and all fields share the same
+//! length and capacity: `reserve`, `with_capacity` and `shrink_to_fit` only
+//! allocate once. The generated vector has the same API, but no public fields:
+//! the columns are accessed through `smell()` and `smell_mut()`, or through the
+//! slices returned by `as_slice()` and `as_mut_slice()`. This layout does not
+//! support `#[nested_soa]` fields or structs without fields, and the vector can
+//! only derive `Clone`, `Debug`, `Default`, `PartialEq` and `Eq`.
+//!
+//! ```
+//! # #[macro_use] extern crate soa_derive;
+//! #[derive(StructOfArray)]
+//! #[soa(layout = "single_alloc")]
+//! pub struct Cheese {
+//!     pub smell: f64,
+//!     pub name: String,
+//! }
+//!
+//! # fn main() {
+//! let mut vec = CheeseVec::with_capacity(100);
+//! vec.push(Cheese { smell: 3.0, name: "brie".into() });
+//! assert_eq!(vec.smell(), [3.0]);
+//! # }
+//! ```
+//!
+//! To use SIMD instructions on the columns, `#[soa(align = 64)]` makes every
+//! field start on a 64 bytes boundary, and `#[soa(align = 32)]` on a single field
+//! only changes this field. The alignment must be a power of two, and implies the
+//! single allocation layout. The capacity is then rounded up so that every column
+//! also ends on its alignment, and the pointers returned by `as_ptr()` and
+//! `as_mut_ptr()` are guaranteed to be aligned: `CheesePtr::ALIGN_SMELL` gives
+//! the alignment of the `smell` column, and `CheesePtr::is_aligned()` checks all
+//! the pointers.
+//!
+//! ```
+//! # #[macro_use] extern crate soa_derive;
+//! #[derive(StructOfArray)]
+//! #[soa(align = 64)]
+//! pub struct Particle {
+//!     pub mass: f32,
+//!     #[soa(align = 16)]
+//!     pub charge: i8,
+//! }
+//!
+//! # fn main() {
+//! let mut vec = ParticleVec::with_capacity(1);
+//! assert_eq!(vec.capacity(), 16);
+//! assert!(vec.as_ptr().is_aligned());
+//! assert_eq!(vec.as_ptr().mass as usize % ParticlePtr::ALIGN_MASS, 0);
+//! # }
+//! ```
+//!
+//! ## SIMD lanes
+//!
+//! With `#[soa(lanes = "array")]`, the fields with a primitive numeric type
+//! (`f64`, `u32`, *etc.*) can also be loaded in groups of `N` consecutive
+//! elements, to be processed with SIMD instructions. `CheeseLanes<N>` contains
+//! one `[f64; N]` array for each of these fields, and the other fields are not
+//! included. `CheeseSlice::lanes::<N>()` iterates over all the full groups in a
+//! slice, and the last elements are available with `remainder()` on the
+//! iterator. The values can be written back with `CheeseLanes::copy_to_slice`,
+//! for example using `chunks_exact_mut(N)` on a mutable slice. With the
+//! `nightly` feature and a nightly compiler, `#[soa(lanes = "simd")]` uses
+//! `std::simd::Simd<f64, N>` instead of arrays, which requires
+//! `#![feature(portable_simd)]` in the crate using it.
+//!
+//! ```
+//! # #[macro_use] extern crate soa_derive;
+//! #[derive(StructOfArray)]
+//! #[soa(lanes = "array")]
+//! pub struct Cheese {
+//!     pub smell: f64,
+//!     pub age: u32,
+//!     pub name: String,
+//! }
+//!
+//! # fn main() {
+//! let mut vec = CheeseVec::new();
+//! for age in 0..10 {
+//!     vec.push(Cheese { smell: 1.0, age: age, name: "comte".into() });
+//! }
+//! let slice = vec.as_slice();
+//! let mut lanes = slice.lanes::<4>();
+//! assert_eq!(lanes.next().unwrap().age, [0, 1, 2, 3]);
+//! assert_eq!(lanes.remainder().age, [8, 9]);
+//! # }
+//! ```
+//!
+//! ## Fixed capacity arrays
+//!
+//! With `#[soa(array)]`, a fixed capacity `CheeseArray<N>` is also generated,
+//! storing up to `N` elements inline in one `[MaybeUninit<T>; N]` array for each
+//! field, without any heap allocation. It provides `push` (which panics when the
+//! array is full), `try_push`, `pop`, `truncate`, `iter`, `get`/`index`, and uses
+//! `CheeseSlice` and `CheeseSliceMut` as views with `as_slice()` and
+//! `as_mut_slice()`. The `Clone`, `Debug`, `PartialEq` and `Eq` traits are
+//! implemented if they are derived for the vector. This option does not support
+//! `#[nested_soa]` fields or structs without fields.
+//!
+//! ```
+//! # #[macro_use] extern crate soa_derive;
+//! #[derive(StructOfArray)]
+//! #[soa(array)]
+//! pub struct Cheese {
+//!     pub smell: f64,
+//!     pub name: String,
+//! }
+//!
+//! # fn main() {
+//! let mut batch = CheeseArray::<16>::new();
+//! batch.push(Cheese { smell: 3.0, name: "brie".into() });
+//! assert_eq!(batch.as_slice().smell, [3.0]);
+//! assert_eq!(batch.index(0).name, "brie");
+//! # }
+//! ```
+//!
+//! ## Small vectors
+//!
+//! With `#[soa(small_vec)]`, a `CheeseSmallVec<N>` is generated alongside
+//! `CheeseVec` and `CheeseArray<N>`. It stores up to `N` elements inline in a
+//! `CheeseArray<N>`, and moves them to a `CheeseVec` on the heap when more
+//! elements are added. It exposes the same API as `CheeseVec` (`push`, `pop`,
+//! `insert`, `remove`, `iter`, `get`/`index`, `as_slice`, ...), and `spilled()`
+//! tells if the elements are currently stored on the heap. `shrink_to_fit()`
+//! moves the elements back inline when they fit. This option implies
+//! `#[soa(array)]`, with the same restrictions.
+//!
+//! ```
+//! # #[macro_use] extern crate soa_derive;
+//! #[derive(StructOfArray)]
+//! #[soa(small_vec)]
+//! pub struct Cheese {
+//!     pub smell: f64,
+//!     pub name: String,
+//! }
+//!
+//! # fn main() {
+//! let mut neighbors = CheeseSmallVec::<2>::new();
+//! neighbors.push(Cheese { smell: 3.0, name: "brie".into() });
+//! neighbors.push(Cheese { smell: 6.0, name: "comté".into() });
+//! assert!(!neighbors.spilled());
+//! neighbors.push(Cheese { smell: 9.0, name: "roquefort".into() });
+//! assert!(neighbors.spilled());
+//! assert_eq!(neighbors.as_slice().smell, [3.0, 6.0, 9.0]);
 //! # }
 //! ```
 //!
@@ -82,7 +352,6 @@
 //!
 //! ```no_run
 //! # #[macro_use] extern crate soa_derive;
-//! # fn main() {
 //! # #[derive(Debug, PartialEq, StructOfArray)]
 //! # pub struct Cheese {
 //! #     pub smell: f64,
@@ -91,6 +360,7 @@
 //! #     pub name: String,
 //! # }
 //! # impl Cheese { fn new(name: &str) -> Cheese { unimplemented!() } }
+//! # fn main() {
 //! let mut vec = CheeseVec::new();
 //! vec.push(Cheese::new("stilton"));
 //! vec.push(Cheese::new("brie"));
@@ -110,7 +380,6 @@
 //!
 //! ```no_run
 //! # #[macro_use] extern crate soa_derive;
-//! # fn main() {
 //! # #[derive(Debug, PartialEq, StructOfArray)]
 //! # pub struct Cheese {
 //! #     pub smell: f64,
@@ -119,6 +388,7 @@
 //! #     pub name: String,
 //! # }
 //! # impl Cheese { fn new(name: &str) -> Cheese { unimplemented!() } }
+//! # fn main() {
 //! # let mut vec = CheeseVec::new();
 //! # vec.push(Cheese::new("stilton"));
 //! # vec.push(Cheese::new("brie"));
@@ -135,7 +405,6 @@
 //!
 //! ```no_run
 //! # #[macro_use] extern crate soa_derive;
-//! # fn main() {
 //! # #[derive(Debug, PartialEq, StructOfArray)]
 //! # pub struct Cheese {
 //! #     pub smell: f64,
@@ -144,6 +413,7 @@
 //! #     pub name: String,
 //! # }
 //! # impl Cheese { fn new(name: &str) -> Cheese { unimplemented!() } }
+//! # fn main() {
 //! # let mut vec = CheeseVec::new();
 //! # vec.push(Cheese::new("stilton"));
 //! # vec.push(Cheese::new("brie"));
@@ -160,12 +430,89 @@
 // macro_rules macro.
 pub use soa_derive_internal::StructOfArray;
 
+// Re-export rayon for the parallel iterators in the generated code
+#[cfg(feature = "rayon")]
+#[doc(hidden)]
+pub use rayon;
+
+// Re-export serde for the Serialize and Deserialize implementations in the
+// generated code
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub use serde;
+
+// Storage for the vectors generated with `#[soa(layout = "single_alloc")]`
+#[doc(hidden)]
+pub mod single_alloc;
+
+/// Error returned by the generated `from_columns` functions when the columns
+/// do not all have the same length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LengthMismatch {
+    /// Name of the first field with a different length
+    pub field: &'static str,
+    /// Length of this field
+    pub len: usize,
+    /// Length of the first field of the struct
+    pub expected: usize,
+}
+
+impl std::fmt::Display for LengthMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f, "all the fields must have the same length, but `{}` has {} elements instead of {}",
+            self.field, self.len, self.expected
+        )
+    }
+}
+
+impl std::error::Error for LengthMismatch {}
+
 /// Any struct derived by StructOfArray will auto impl this trait
 /// You can use `<Cheese as StructOfArray>::Type`
 /// instead of explicit named type `CheeseVec`; This will helpful in generics programing
 /// that generate struct can be expressed as `<T as StructOfArray>::Type`
 pub trait StructOfArray {
     type Type;
+    /// The pointer type, *e.g.* `CheesePtr`
+    type Ptr;
+    /// The mutable pointer type, *e.g.* `CheesePtrMut`
+    type PtrMut;
+}
+
+/// Any struct derived by StructOfArray will also implement this trait, giving
+/// access to the slice, reference and iterator types generated for this
+/// struct. This is used to store, iterate over and drain fields marked with
+/// `#[nested_soa]`.
+pub trait SoAIter<'a> {
+    /// The slice type, *e.g.* `CheeseSlice<'a>`
+    type Slice;
+    /// The mutable slice type, *e.g.* `CheeseSliceMut<'a>`
+    type SliceMut;
+    /// The reference type, *e.g.* `CheeseRef<'a>`
+    type Ref;
+    /// The mutable reference type, *e.g.* `CheeseRefMut<'a>`
+    type RefMut;
+    /// The iterator over references, returned by `CheeseVec::iter()`
+    type Iter: 'a + Iterator<Item=Self::Ref>;
+    /// The iterator over mutable references, returned by `CheeseVec::iter_mut()`
+    type IterMut: 'a + Iterator<Item=Self::RefMut>;
+    /// The draining iterator, returned by `CheeseVec::drain()`
+    type Drain: 'a + Iterator<Item=Self>;
+}
+
+/// Any struct derived by StructOfArray will also implement this trait with the
+/// `rayon` feature, when the vector can be converted to a parallel iterator.
+/// This is used to move the values out of fields marked with `#[nested_soa]`
+/// in parallel.
+#[cfg(feature = "rayon")]
+pub trait SoAIntoParIter: StructOfArray + Sized {
+    /// The parallel iterator moving values out of the vector, returned by
+    /// `CheeseVec::into_par_iter()`
+    type Iter: rayon::iter::IndexedParallelIterator<Item=Self>;
+
+    /// Convert the `vec` to a parallel iterator
+    fn into_par_iter(vec: Self::Type) -> Self::Iter;
 }
 
 
@@ -196,6 +543,11 @@ pub trait SoAIndex<T>: private_soa_indexs::Sealed {
     /// Returns the reference output in this location if in bounds, `None` otherwise.
     fn get(self, soa: T) -> Option<Self::RefOutput>;
     /// Returns the reference output in this location without performing any bounds check.
+    ///
+    /// # Safety
+    ///
+    /// Calling this function with an out-of-bounds index is undefined
+    /// behavior, even if the resulting output is not used.
     unsafe fn get_unchecked(self, soa: T) -> Self::RefOutput;
     /// Returns the reference output in this location. Panics if it is not in bounds.
     fn index(self, soa: T) -> Self::RefOutput;
@@ -210,6 +562,11 @@ pub trait SoAIndexMut<T>: private_soa_indexs::Sealed {
     /// Returns the mutable reference output in this location if in bounds, `None` otherwise.
     fn get_mut(self, soa: T) -> Option<Self::MutOutput>;
     /// Returns the mutable reference output in this location without performing any bounds check.
+    ///
+    /// # Safety
+    ///
+    /// Calling this function with an out-of-bounds index is undefined
+    /// behavior, even if the resulting output is not used.
     unsafe fn get_unchecked_mut(self, soa: T) -> Self::MutOutput;
     /// Returns the mutable reference output in this location. Panics if it is not in bounds.
     fn index_mut(self, soa: T) -> Self::MutOutput;
@@ -220,11 +577,11 @@ pub trait SoAIndexMut<T>: private_soa_indexs::Sealed {
 /// This macro takes two main arguments: the array/slice container, and a list
 /// of fields to use, inside square brackets. The iterator will give references
 /// to the fields, which can be mutable references if the field name is prefixed
-/// with `mut`.
+/// with `mut`. Fields of tuple structs are selected with their index, as in
+/// `soa_zip!(&mut vec, [0, mut 2])`.
 ///
 /// ```
 /// # #[macro_use] extern crate soa_derive;
-/// # fn main() {
 /// #[derive(StructOfArray)]
 /// struct Cheese {
 ///     size: f64,
@@ -233,6 +590,7 @@ pub trait SoAIndexMut<T>: private_soa_indexs::Sealed {
 ///     name: String,
 /// }
 ///
+/// # fn main() {
 /// let mut vec = CheeseVec::new();
 /// // fill the vector
 ///
@@ -255,7 +613,6 @@ pub trait SoAIndexMut<T>: private_soa_indexs::Sealed {
 ///
 /// ```
 /// # #[macro_use] extern crate soa_derive;
-/// # fn main() {
 /// # #[derive(StructOfArray)]
 /// # struct Cheese {
 /// #     size: f64,
@@ -264,6 +621,7 @@ pub trait SoAIndexMut<T>: private_soa_indexs::Sealed {
 /// #     name: String,
 /// # }
 /// # #[derive(Debug)] struct Cellar;
+/// # fn main() {
 /// let mut vec = CheeseVec::new();
 /// let mut cellars = Vec::<Cellar>::new();
 ///
@@ -306,20 +664,95 @@ macro_rules! soa_zip_impl {
     };
 
     // Eat the last `mut $field` and then emit code
-    (@munch $self: expr, {mut $field: ident} -> [$($output: tt)*] $($ext: expr ,)*) => {
+    (@munch $self: expr, {mut $field: tt} -> [$($output: tt)*] $($ext: expr ,)*) => {
         $crate::soa_zip_impl!(@last $($output)*, $self.$field.iter_mut(), $($ext, )*)
     };
     // Eat the last `$field` and then emit code
-    (@munch $self: expr, {$field: ident} -> [$($output: tt)*] $($ext: expr ,)*) => {
+    (@munch $self: expr, {$field: tt} -> [$($output: tt)*] $($ext: expr ,)*) => {
         $crate::soa_zip_impl!(@last $($output)*, $self.$field.iter(), $($ext, )*)
     };
 
     // Eat the next `mut $field` and then recurse
-    (@munch $self: expr, {mut $field: ident, $($tail: tt)*} -> [$($output: tt)*] $($ext: expr ,)*) => {
+    (@munch $self: expr, {mut $field: tt, $($tail: tt)*} -> [$($output: tt)*] $($ext: expr ,)*) => {
         $crate::soa_zip_impl!(@munch $self, {$($tail)*} -> [$($output)*, $self.$field.iter_mut()] $($ext, )*)
     };
     // Eat the next `$field` and then recurse
-    (@munch $self: expr, {$field: ident, $($tail: tt)*} -> [$($output: tt)*] $($ext: expr ,)*) => {
+    (@munch $self: expr, {$field: tt, $($tail: tt)*} -> [$($output: tt)*] $($ext: expr ,)*) => {
         $crate::soa_zip_impl!(@munch $self, {$($tail)*} -> [$($output)*, $self.$field.iter()] $($ext, )*)
     };
 }
+
+/// Create a parallel iterator over multiple fields in a Struct of array style
+/// vector, using [rayon](https://docs.rs/rayon). This macro is only available
+/// with the `rayon` feature.
+///
+/// This is the parallel version of [soa_zip!](macro.soa_zip.html), and takes
+/// the same arguments. All the fields are split at the same indexes, and
+/// external parallel iterators must be indexed as well.
+///
+/// ```
+/// # #[macro_use] extern crate soa_derive;
+/// use rayon::prelude::*;
+///
+/// #[derive(StructOfArray)]
+/// struct Particle {
+///     mass: f64,
+///     velocity: f64,
+///     kinetic_energy: f64,
+/// }
+///
+/// # fn main() {
+/// let mut vec = ParticleVec::new();
+/// // fill the vector
+///
+/// soa_par_zip!(&mut vec, [mass, velocity, mut kinetic_energy]).for_each(|(mass, velocity, energy)| {
+///     *energy = 0.5 * mass * velocity * velocity;
+/// });
+/// # }
+/// ```
+#[cfg(feature = "rayon")]
+#[macro_export]
+macro_rules! soa_par_zip {
+    ($self: expr, [$($fields: tt)*] $(, $external: expr)* $(,)*) => {{
+        use $crate::rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+        #[allow(unused_imports)]
+        use $crate::rayon::iter::{IntoParallelRefIterator, IntoParallelRefMutIterator};
+        let this = $self;
+        $crate::soa_par_zip_impl!(@munch this, {$($fields)*} -> [] $($external ,)*)
+    }};
+}
+
+#[cfg(feature = "rayon")]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! soa_par_zip_impl {
+    // The main code is emmited here: we create a parallel iterator, zip it and
+    // then map the zipped iterator to flatten it
+    (@last , $first: expr, $($tail: expr,)*) => {
+        IntoParallelIterator::into_par_iter($first)
+            $(
+                .zip($tail)
+            )*
+            .map(
+                $crate::soa_zip_impl!(@flatten a => (a) $( , $tail )*)
+            )
+    };
+
+    // Eat the last `mut $field` and then emit code
+    (@munch $self: expr, {mut $field: tt} -> [$($output: tt)*] $($ext: expr ,)*) => {
+        $crate::soa_par_zip_impl!(@last $($output)*, $self.$field.par_iter_mut(), $($ext, )*)
+    };
+    // Eat the last `$field` and then emit code
+    (@munch $self: expr, {$field: tt} -> [$($output: tt)*] $($ext: expr ,)*) => {
+        $crate::soa_par_zip_impl!(@last $($output)*, $self.$field.par_iter(), $($ext, )*)
+    };
+
+    // Eat the next `mut $field` and then recurse
+    (@munch $self: expr, {mut $field: tt, $($tail: tt)*} -> [$($output: tt)*] $($ext: expr ,)*) => {
+        $crate::soa_par_zip_impl!(@munch $self, {$($tail)*} -> [$($output)*, $self.$field.par_iter_mut()] $($ext, )*)
+    };
+    // Eat the next `$field` and then recurse
+    (@munch $self: expr, {$field: tt, $($tail: tt)*} -> [$($output: tt)*] $($ext: expr ,)*) => {
+        $crate::soa_par_zip_impl!(@munch $self, {$($tail)*} -> [$($output)*, $self.$field.par_iter()] $($ext, )*)
+    };
+}
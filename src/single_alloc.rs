@@ -0,0 +1,203 @@
+//! Memory management for the vectors generated with
+//! `#[soa(layout = "single_alloc")]`.
+
+use std::alloc::{self, Layout};
+use std::ptr::{self, NonNull};
+
+/// Get the layout of a single element in a column of type `T`, with at least
+/// the given `align`ment.
+///
+/// # Panics
+///
+/// If `align` is not a power of two.
+pub const fn aligned_layout<T>(align: usize) -> Layout {
+    let natural = std::mem::align_of::<T>();
+    let align = if align > natural { align } else { natural };
+    match Layout::from_size_align(std::mem::size_of::<T>(), align) {
+        Ok(layout) => layout,
+        Err(_) => panic!("invalid alignment for a column"),
+    }
+}
+
+/// A single allocation containing one array for each column of a struct of
+/// arrays vector. The arrays are stored one after the other, each one starting
+/// at an offset aligned for the corresponding layout, and all of them have the
+/// same capacity.
+///
+/// The capacity is always a multiple of the number of elements fitting in the
+/// alignment of each column, so that the end of each array is also aligned.
+///
+/// This type only manages memory, and never reads, writes or drops the values
+/// in the columns: the generated code is responsible for keeping track of the
+/// initialized elements. The layout of each column is passed to all functions
+/// as `columns`, and must always be the same for a given allocation. The offset
+/// of each column is computed when the memory is allocated, and cached until the
+/// next allocation.
+pub struct SingleAlloc<const N: usize> {
+    ptr: NonNull<u8>,
+    capacity: usize,
+    offsets: [usize; N],
+}
+
+// SingleAlloc only contains raw bytes, the generated vectors are only Send and
+// Sync if all the column types are.
+unsafe impl<const N: usize> Send for SingleAlloc<N> {}
+unsafe impl<const N: usize> Sync for SingleAlloc<N> {}
+
+impl<const N: usize> SingleAlloc<N> {
+    /// Create a new empty allocation, without allocating any memory
+    pub fn new(columns: &[Layout; N]) -> SingleAlloc<N> {
+        let capacity = if is_zero_sized(columns) { usize::MAX } else { 0 };
+        SingleAlloc {
+            ptr: dangling(columns),
+            capacity,
+            offsets: layout(columns, capacity).1,
+        }
+    }
+
+    /// Re-create an allocation from a pointer previously obtained from the
+    /// first column of an allocation with the given `capacity`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from `SingleAlloc::columns` with the same `columns`
+    /// and `capacity`, and the corresponding allocation must not be used or
+    /// deallocated anymore.
+    pub unsafe fn from_raw_parts(columns: &[Layout; N], ptr: *mut u8, capacity: usize) -> SingleAlloc<N> {
+        if is_zero_sized(columns) {
+            return SingleAlloc::new(columns);
+        }
+
+        SingleAlloc {
+            ptr: NonNull::new(ptr).expect("got a null pointer in SingleAlloc::from_raw_parts"),
+            capacity,
+            offsets: layout(columns, capacity).1,
+        }
+    }
+
+    /// Get the number of elements that can be stored in each column without
+    /// reallocating
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get pointers to the start of each column
+    pub fn columns(&self) -> [*mut u8; N] {
+        // SAFETY: all the offsets are inside the allocation, or zero for
+        // empty allocations
+        self.offsets.map(|offset| unsafe { self.ptr.as_ptr().add(offset) })
+    }
+
+    /// Move to a new allocation with at least the given `capacity`, copying
+    /// the first `len` elements of each column.
+    ///
+    /// This does nothing if all the columns are zero-sized types, since they
+    /// do not need any memory.
+    ///
+    /// # Panics
+    ///
+    /// If `len` is larger than the old or the new capacity, or if the new
+    /// allocation would be larger than `isize::MAX` bytes.
+    pub fn reallocate(&mut self, columns: &[Layout; N], len: usize, capacity: usize) {
+        assert!(len <= self.capacity && len <= capacity, "can not reallocate with len larger than the capacity");
+        if is_zero_sized(columns) {
+            return;
+        }
+
+        let capacity = padded_capacity(columns, capacity);
+        let (new_layout, new_offsets) = layout(columns, capacity);
+        let new_ptr = if new_layout.size() == 0 {
+            dangling(columns)
+        } else {
+            // SAFETY: the layout is not zero-sized
+            let ptr = unsafe { alloc::alloc(new_layout) };
+            match NonNull::new(ptr) {
+                Some(ptr) => ptr,
+                None => alloc::handle_alloc_error(new_layout),
+            }
+        };
+
+        let old_columns = self.columns();
+        for i in 0..N {
+            // SAFETY: both allocations contain at least `len` elements in
+            // each column, and they do not overlap
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    old_columns[i],
+                    new_ptr.as_ptr().add(new_offsets[i]),
+                    columns[i].size() * len,
+                );
+            }
+        }
+
+        self.deallocate(columns);
+        self.ptr = new_ptr;
+        self.capacity = capacity;
+        self.offsets = new_offsets;
+    }
+
+    /// Release the memory used by this allocation, leaving it empty. The
+    /// values in the columns must have been dropped or moved before.
+    pub fn deallocate(&mut self, columns: &[Layout; N]) {
+        let (layout, _) = layout(columns, self.capacity);
+        if layout.size() != 0 {
+            // SAFETY: the memory was allocated with the same layout
+            unsafe { alloc::dealloc(self.ptr.as_ptr(), layout) };
+        }
+        *self = SingleAlloc::new(columns);
+    }
+}
+
+/// Are all the columns zero-sized types? Such columns never need any memory,
+/// and have an infinite capacity.
+fn is_zero_sized(columns: &[Layout]) -> bool {
+    columns.iter().all(|column| column.size() == 0)
+}
+
+/// Round `capacity` up to a multiple of the number of elements fitting in the
+/// alignment of each column. This number is `align / gcd(size, align)`, which
+/// is always a power of two.
+fn padded_capacity(columns: &[Layout], capacity: usize) -> usize {
+    let step = columns.iter()
+        .filter(|column| column.size() != 0)
+        .map(|column| {
+            let gcd = 1_usize << column.size().trailing_zeros().min(column.align().trailing_zeros());
+            column.align() / gcd
+        })
+        .max()
+        .unwrap_or(1);
+
+    match capacity.checked_add(step - 1) {
+        Some(capacity) => capacity / step * step,
+        None => panic!("capacity overflow"),
+    }
+}
+
+/// Get a non-null pointer aligned for all the columns, to be used when no
+/// memory is allocated
+fn dangling(columns: &[Layout]) -> NonNull<u8> {
+    let align = columns.iter().map(Layout::align).max().unwrap_or(1);
+    NonNull::new(ptr::null_mut::<u8>().wrapping_add(align)).expect("alignment can not be zero")
+}
+
+/// Get the layout of an allocation containing `capacity` elements for each
+/// one of the `columns`, as well as the offset of each column in the
+/// allocation
+fn layout<const N: usize>(columns: &[Layout; N], capacity: usize) -> (Layout, [usize; N]) {
+    let mut layout = Layout::new::<()>();
+    let mut offsets = [0; N];
+    for (column, offset) in columns.iter().zip(&mut offsets) {
+        let array = column.size().checked_mul(capacity)
+            .and_then(|size| Layout::from_size_align(size, column.align()).ok())
+            .and_then(|array| layout.extend(array).ok());
+
+        match array {
+            Some((extended, column_offset)) => {
+                layout = extended;
+                *offset = column_offset;
+            }
+            None => panic!("capacity overflow"),
+        }
+    }
+    (layout, offsets)
+}
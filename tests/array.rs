@@ -0,0 +1,190 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use soa_derive::{soa_zip, StructOfArray};
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(array)]
+pub struct Particle {
+    pub name: String,
+    pub mass: f64,
+}
+
+impl Particle {
+    pub fn new(name: &str, mass: f64) -> Self {
+        Particle {
+            name: name.into(),
+            mass,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq, Eq"]
+#[soa(array)]
+pub struct Point<T: Copy>(T, T);
+
+/// Count the number of times a value is dropped
+#[derive(Debug, Clone)]
+pub struct DropCounter(Rc<Cell<usize>>);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[derive(Debug, StructOfArray)]
+#[soa(array)]
+pub struct Tracked {
+    pub id: usize,
+    pub counter: DropCounter,
+}
+
+fn particles() -> ParticleArray<4> {
+    let mut particles = ParticleArray::new();
+    particles.push(Particle::new("Na", 22.99));
+    particles.push(Particle::new("Cl", 35.45));
+    particles.push(Particle::new("H", 1.008));
+    particles
+}
+
+#[test]
+fn push_and_pop() {
+    let mut particles = ParticleArray::<4>::new();
+    assert_eq!(particles.capacity(), 4);
+    assert!(particles.is_empty());
+
+    particles.push(Particle::new("Na", 22.99));
+    particles.push(Particle::new("Cl", 35.45));
+    assert_eq!(particles.len(), 2);
+    assert_eq!(particles.as_slice().mass, [22.99, 35.45]);
+
+    particles.push(Particle::new("H", 1.008));
+    particles.push(Particle::new("O", 15.999));
+    assert!(particles.is_full());
+    assert_eq!(particles.try_push(Particle::new("C", 12.011)), Err(Particle::new("C", 12.011)));
+
+    assert_eq!(particles.pop(), Some(Particle::new("O", 15.999)));
+    assert!(particles.try_push(Particle::new("C", 12.011)).is_ok());
+    assert_eq!(particles.as_slice().name, ["Na", "Cl", "H", "C"]);
+
+    particles.truncate(1);
+    assert_eq!(particles.as_slice().name, ["Na"]);
+    particles.clear();
+    assert_eq!(particles.pop(), None);
+
+    let empty = ParticleArray::<0>::default();
+    assert!(empty.is_full());
+    assert!(empty.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "can not push to a full array with capacity 1")]
+fn push_full() {
+    let mut particles = ParticleArray::<1>::new();
+    particles.push(Particle::new("Na", 22.99));
+    particles.push(Particle::new("Cl", 35.45));
+}
+
+#[test]
+fn slices() {
+    let mut particles = particles();
+    for mass in particles.as_mut_slice().mass.iter_mut() {
+        *mass *= 2.0;
+    }
+    assert_eq!(particles.as_slice().mass, [45.98, 70.9, 2.016]);
+
+    let mut slice = particles.as_mut_slice();
+    for (name, mass) in soa_zip!(&mut slice, [mut name, mass]) {
+        if *mass > 10.0 {
+            name.push('+');
+        }
+    }
+    assert_eq!(particles.as_slice().name, ["Na+", "Cl+", "H"]);
+
+    let vec = particles.as_slice().to_vec();
+    assert_eq!(vec.name, ["Na+", "Cl+", "H"]);
+}
+
+#[test]
+fn index() {
+    let mut particles = particles();
+    assert_eq!(particles.index(1).name, "Cl");
+    assert_eq!(particles.get(2).unwrap().name, "H");
+    assert!(particles.get(3).is_none());
+    assert_eq!(particles.get(1..).unwrap().name, ["Cl", "H"]);
+    assert_eq!(particles.index(..=1).mass, [22.99, 35.45]);
+    assert!(particles.get(2..5).is_none());
+
+    *particles.index_mut(0).mass = 23.0;
+    particles.get_mut(1..).unwrap().mass[1] = 1.0;
+    assert_eq!(particles.as_slice().mass, [23.0, 35.45, 1.0]);
+    assert_eq!(unsafe { particles.get_unchecked(0).name }, "Na");
+}
+
+#[test]
+fn iteration() {
+    let mut particles = particles();
+    for particle in particles.iter_mut() {
+        *particle.mass += 1.0;
+    }
+    for particle in &mut particles {
+        particle.name.push('!');
+    }
+    let names = particles.iter().map(|particle| particle.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["Na!", "Cl!", "H!"]);
+    let masses = (&particles).into_iter().map(|particle| *particle.mass).collect::<Vec<_>>();
+    assert_eq!(masses, [23.99, 36.45, 2.008]);
+}
+
+#[test]
+fn traits() {
+    let particles = particles();
+    let clone = particles.clone();
+    assert_eq!(particles, clone);
+    assert_eq!(
+        format!("{:?}", clone),
+        r#"ParticleArray { name: ["Na", "Cl", "H"], mass: [22.99, 35.45, 1.008] }"#
+    );
+
+    let mut points = PointArray::<u8, 8>::new();
+    points.push(Point(1, 2));
+    points.push(Point(3, 4));
+    assert_eq!(points.as_slice().0, [1, 3]);
+    assert_eq!(points.index(1), PointRef(&3, &4));
+    assert_ne!(points, PointArray::new());
+    assert_eq!(format!("{:?}", points), "PointArray([1, 3], [2, 4])");
+}
+
+#[test]
+fn drops() {
+    let drops = Rc::new(Cell::new(0));
+    let tracked = |id| Tracked { id, counter: DropCounter(Rc::clone(&drops)) };
+
+    let mut array = TrackedArray::<8>::new();
+    for id in 0..6 {
+        array.push(tracked(id));
+    }
+    assert_eq!(drops.get(), 0);
+
+    array.truncate(4);
+    assert_eq!(drops.get(), 2);
+
+    drop(array.pop());
+    assert_eq!(drops.get(), 3);
+
+    let rejected = {
+        let mut full = TrackedArray::<1>::new();
+        full.push(tracked(0));
+        full.try_push(tracked(1)).unwrap_err()
+    };
+    assert_eq!(drops.get(), 4);
+    assert_eq!(rejected.id, 1);
+    drop(rejected);
+
+    drop(array);
+    assert_eq!(drops.get(), 8);
+    assert_eq!(Rc::strong_count(&drops), 1);
+}
@@ -0,0 +1,26 @@
+use soa_derive::StructOfArray;
+
+#[derive(StructOfArray)]
+#[soa(array)]
+pub struct Empty;
+
+#[derive(StructOfArray)]
+pub struct Vector {
+    x: f32,
+}
+
+#[derive(StructOfArray)]
+#[soa(array)]
+pub struct Nested {
+    #[nested_soa]
+    position: Vector,
+}
+
+#[derive(StructOfArray)]
+#[soa(small_vec)]
+pub struct NestedSmallVec {
+    #[nested_soa]
+    position: Vector,
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+use soa_derive::StructOfArray;
+
+#[derive(StructOfArray)]
+#[soa_attr(Array, derive(Debug))]
+pub struct UnknownType {
+    mass: f64,
+}
+
+#[derive(StructOfArray)]
+#[soa_attr(Vec)]
+pub struct MissingAttribute {
+    mass: f64,
+}
+
+#[derive(StructOfArray)]
+#[soa_attr = "Vec, derive(Debug)"]
+pub struct NameValue {
+    mass: f64,
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+use soa_derive::StructOfArray;
+
+#[derive(StructOfArray)]
+#[soa_derive(Debug, PartialEq)]
+pub struct List {
+    mass: f64,
+}
+
+#[derive(StructOfArray)]
+#[soa_derive = 42]
+pub struct Integer {
+    mass: f64,
+}
+
+#[derive(StructOfArray)]
+#[soa_derive = "Debug, Partial Eq"]
+pub struct InvalidTrait {
+    mass: f64,
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+use soa_derive::StructOfArray;
+
+#[derive(StructOfArray)]
+pub struct UnknownOption {
+    #[soa(hot)]
+    mass: f64,
+}
+
+#[derive(StructOfArray)]
+pub struct NameValue {
+    #[soa = "skip"]
+    mass: f64,
+}
+
+#[derive(StructOfArray)]
+pub struct SkipNested {
+    #[soa(skip)]
+    #[nested_soa]
+    position: UnknownOption,
+}
+
+fn main() {}
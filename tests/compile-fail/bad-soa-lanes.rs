@@ -0,0 +1,16 @@
+use soa_derive::StructOfArray;
+
+#[derive(StructOfArray)]
+#[soa(lanes = "vector")]
+pub struct BadLanesForm {
+    x: f32,
+}
+
+#[derive(StructOfArray)]
+#[soa(lanes = "array")]
+pub struct NoNumbers {
+    name: String,
+    flag: bool,
+}
+
+fn main() {}
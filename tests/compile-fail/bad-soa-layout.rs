@@ -0,0 +1,57 @@
+use soa_derive::StructOfArray;
+
+#[derive(StructOfArray)]
+#[soa(layout = "interleaved")]
+pub struct BadLayout {
+    x: f32,
+}
+
+#[derive(StructOfArray)]
+#[soa(layout = "single_alloc")]
+pub struct Empty;
+
+#[derive(StructOfArray)]
+pub struct Vector {
+    x: f32,
+}
+
+#[derive(StructOfArray)]
+#[soa(layout = "single_alloc")]
+pub struct Nested {
+    #[nested_soa]
+    position: Vector,
+}
+
+#[derive(StructOfArray)]
+#[soa_attr(Vec, derive(Hash))]
+#[soa(layout = "single_alloc")]
+pub struct Hashed {
+    x: u32,
+}
+
+#[derive(StructOfArray)]
+#[soa(align = 48)]
+pub struct BadAlign {
+    x: f32,
+}
+
+#[derive(StructOfArray)]
+pub struct BadFieldAlign {
+    #[soa(align = "64")]
+    x: f32,
+}
+
+#[derive(StructOfArray)]
+#[soa(align = 64, layout = "separate")]
+pub struct SeparateAlign {
+    x: f32,
+}
+
+#[derive(StructOfArray)]
+#[soa(layout = "single_alloc")]
+pub struct Storage {
+    capacity: usize,
+    as_slice: f32,
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+use soa_derive::StructOfArray;
+
+#[derive(StructOfArray)]
+#[soa(array = "Particles")]
+pub struct UnknownOption {
+    mass: f64,
+}
+
+#[derive(StructOfArray)]
+#[soa(vec = "Particle Vec")]
+pub struct InvalidName {
+    mass: f64,
+}
+
+#[derive(StructOfArray)]
+#[soa(vec = Particles)]
+pub struct NotAString {
+    mass: f64,
+}
+
+#[derive(StructOfArray)]
+#[soa(vec = 3)]
+pub struct Integer {
+    mass: f64,
+}
+
+fn main() {}
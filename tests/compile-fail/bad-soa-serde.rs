@@ -0,0 +1,15 @@
+use soa_derive::StructOfArray;
+
+#[derive(StructOfArray)]
+#[soa(serde = "csv")]
+pub struct BadSerdeForm {
+    x: f32,
+}
+
+#[derive(StructOfArray)]
+#[soa(serde = 3)]
+pub struct BadSerdeValue {
+    x: f32,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use soa_derive::StructOfArray;
+
+#[derive(StructOfArray)]
+pub enum Particle {
+    Atom(f64),
+    Molecule(Vec<f64>),
+}
+
+fn main() {}
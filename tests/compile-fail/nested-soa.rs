@@ -0,0 +1,15 @@
+use soa_derive::StructOfArray;
+
+#[derive(StructOfArray)]
+pub struct Array {
+    #[nested_soa]
+    pub values: [f64; 3],
+}
+
+#[derive(StructOfArray)]
+pub struct Arguments {
+    #[nested_soa(Vec)]
+    pub value: Array,
+}
+
+fn main() {}
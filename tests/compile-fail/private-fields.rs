@@ -0,0 +1,33 @@
+use soa_derive::StructOfArray;
+
+mod particles {
+    use soa_derive::StructOfArray;
+
+    #[derive(StructOfArray)]
+    #[soa(private_fields)]
+    pub struct Particle {
+        pub mass: f64,
+    }
+}
+
+#[derive(StructOfArray)]
+#[soa(private_fields)]
+pub struct TupleStruct(f64, f64);
+
+#[derive(StructOfArray)]
+#[soa(private_fields)]
+pub struct Buffer {
+    pub len: usize,
+}
+
+#[derive(StructOfArray)]
+#[soa(private_fields)]
+pub struct Collection {
+    pub name: String,
+    pub iter: u32,
+}
+
+fn main() {
+    let mut particles = particles::ParticleVec::new();
+    particles.mass.push(3.0);
+}
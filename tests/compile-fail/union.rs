@@ -0,0 +1,9 @@
+use soa_derive::StructOfArray;
+
+#[derive(StructOfArray)]
+pub union Particle {
+    mass: f64,
+    charge: f32,
+}
+
+fn main() {}
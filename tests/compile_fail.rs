@@ -0,0 +1,5 @@
+#[test]
+fn compile_fail() {
+    let tests = trybuild::TestCases::new();
+    tests.compile_fail("tests/compile-fail/*.rs");
+}
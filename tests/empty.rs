@@ -0,0 +1,86 @@
+use soa_derive::StructOfArray;
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Marker {}
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Unit;
+
+#[test]
+fn vec() {
+    let mut markers = MarkerVec::new();
+    assert!(markers.is_empty());
+
+    markers.push(Marker {});
+    markers.push(Marker {});
+    markers.insert(1, Marker {});
+    assert_eq!(markers.len(), 3);
+
+    assert_eq!(markers.pop(), Some(Marker {}));
+    assert_eq!(markers.remove(0), Marker {});
+    assert_eq!(markers.swap_remove(0), Marker {});
+    assert_eq!(markers.pop(), None);
+    assert!(markers.is_empty());
+
+    let mut units = UnitVec::with_capacity(10);
+    units.resize(5, Unit);
+    assert_eq!(units.len(), 5);
+    assert!(units.capacity() >= 10);
+
+    units.truncate(2);
+    assert_eq!(units.len(), 2);
+    units.clear();
+    assert!(units.is_empty());
+}
+
+#[test]
+fn refs_and_slices() {
+    let mut units: UnitVec = vec![Unit, Unit, Unit].into_iter().collect();
+    assert_eq!(units.len(), 3);
+
+    let _: UnitRef = units.index(1);
+    let _: UnitRefMut = units.index_mut(1);
+    assert!(units.get(2).is_some());
+    assert!(units.get(3).is_none());
+
+    let slice = units.as_slice();
+    assert_eq!(slice.len(), 3);
+    assert_eq!(slice.index(1..).len(), 2);
+    assert_eq!(slice.split_at(1).1.len(), 2);
+
+    let mut slice = units.as_mut_slice();
+    assert_eq!(slice.index_mut(..2).len(), 2);
+
+    let unit = Unit;
+    assert_eq!(unit.as_ref(), units.index(0));
+    let mut unit = Unit;
+    let _: UnitRefMut = unit.as_mut();
+}
+
+#[test]
+fn iter() {
+    let mut markers = MarkerVec::new();
+    for _ in 0..4 {
+        markers.push(Marker {});
+    }
+
+    assert_eq!(markers.iter().count(), 4);
+    assert_eq!(markers.iter_mut().count(), 4);
+    assert_eq!(markers.as_slice().iter().rev().count(), 4);
+    let marker = Marker {};
+    assert!(markers.iter().all(|item| item == marker.as_ref()));
+    assert_eq!(markers.into_iter().rev().collect::<Vec<_>>(), vec![Marker {}; 4]);
+}
+
+#[test]
+fn ptr() {
+    let mut units: UnitVec = vec![Unit, Unit].into_iter().collect();
+    let ptr = units.as_mut_ptr();
+    unsafe {
+        ptr.write(Unit);
+        assert_eq!(ptr.read(), Unit);
+        assert!(ptr.add(1).as_ref().is_some());
+    }
+}
@@ -0,0 +1,84 @@
+use soa_derive::StructOfArray;
+
+pub trait Float: Copy + PartialEq + std::fmt::Debug + std::ops::Add<Output = Self> {}
+impl Float for f32 {}
+impl Float for f64 {}
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Particle<T: Float> {
+    pub position: [T; 3],
+    pub mass: T,
+}
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Tagged<'s, T, const N: usize> where T: Float {
+    pub name: &'s str,
+    pub values: [T; N],
+}
+
+fn total_mass<T: Float>(particles: &ParticleVec<T>, zero: T) -> T {
+    particles.iter().fold(zero, |acc, particle| acc + *particle.mass)
+}
+
+#[test]
+fn ty() {
+    let _: <Particle<f32> as StructOfArray>::Type = ParticleVec::<f32>::new();
+    let _: <Tagged<'static, f64, 2> as StructOfArray>::Type = TaggedVec::<f64, 2>::new();
+}
+
+#[test]
+fn vec() {
+    let mut particles = ParticleVec::new();
+    particles.push(Particle { position: [0.0f32; 3], mass: 1.0 });
+    particles.push(Particle { position: [1.0f32; 3], mass: 2.0 });
+    assert_eq!(particles.len(), 2);
+    assert_eq!(total_mass(&particles, 0.0), 3.0);
+
+    let mut particles_64 = ParticleVec::new();
+    particles_64.push(Particle { position: [0.0f64; 3], mass: 4.0 });
+    assert_eq!(total_mass(&particles_64, 0.0), 4.0);
+
+    let particle = particles.pop().unwrap();
+    assert_eq!(particle, Particle { position: [1.0; 3], mass: 2.0 });
+}
+
+#[test]
+fn slice_and_index() {
+    let mut particles: ParticleVec<f64> = (0..5).map(|i| Particle {
+        position: [i as f64; 3],
+        mass: i as f64,
+    }).collect();
+
+    let slice: ParticleSlice<f64> = particles.slice(1..4);
+    assert_eq!(slice.len(), 3);
+    assert_eq!(*slice.index(0).mass, 1.0);
+    assert_eq!(slice.get(1..3).unwrap().mass, &[2.0, 3.0]);
+    assert_eq!(slice.to_vec().len(), 3);
+
+    {
+        let mut slice: ParticleSliceMut<f64> = particles.index_mut(..);
+        *slice.index_mut(4).mass = 42.0;
+        for particle in slice.iter_mut() {
+            particle.position[0] = -1.0;
+        }
+    }
+    assert_eq!(particles.mass[4], 42.0);
+    assert!(particles.position.iter().all(|position| position[0] == -1.0));
+
+    let ptr: ParticlePtr<f64> = particles.as_ptr();
+    let particle: ParticleRef<f64> = unsafe { ptr.add(2).as_ref().unwrap() };
+    assert_eq!(*particle.mass, 2.0);
+}
+
+#[test]
+fn lifetimes_and_const_generics() {
+    let mut tagged = TaggedVec::new();
+    tagged.push(Tagged { name: "first", values: [1.0f32, 2.0] });
+    tagged.push(Tagged { name: "second", values: [3.0f32, 4.0] });
+
+    let names = tagged.iter().map(|t| *t.name).collect::<Vec<_>>();
+    assert_eq!(names, ["first", "second"]);
+    assert_eq!(tagged.index(1).values, &[3.0, 4.0]);
+}
@@ -67,3 +67,26 @@ fn from_iter() {
 
     assert_eq!(particles, particles_from_iter)
 }
+
+#[test]
+fn into_iter() {
+    let mut particles = ParticleVec::new();
+    particles.push(Particle::new(String::from("Na"), 0.0));
+    particles.push(Particle::new(String::from("Cl"), 1.0));
+    particles.push(Particle::new(String::from("Zn"), 2.0));
+
+    let mut iter = particles.clone().into_iter();
+    assert_eq!(iter.len(), 3);
+    assert_eq!(iter.next(), Some(Particle::new(String::from("Na"), 0.0)));
+    assert_eq!(iter.next_back(), Some(Particle::new(String::from("Zn"), 2.0)));
+    assert_eq!(iter.len(), 1);
+    assert_eq!(iter.next(), Some(Particle::new(String::from("Cl"), 1.0)));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next_back(), None);
+
+    let mut names = Vec::new();
+    for particle in particles {
+        names.push(particle.name);
+    }
+    assert_eq!(names, ["Na", "Cl", "Zn"]);
+}
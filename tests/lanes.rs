@@ -0,0 +1,107 @@
+use soa_derive::StructOfArray;
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(lanes = "array")]
+pub struct Particle {
+    pub name: String,
+    pub position: f64,
+    pub velocity: f32,
+    pub charge: i8,
+}
+
+impl Particle {
+    pub fn new(name: &str, position: f64, velocity: f32) -> Self {
+        Particle {
+            name: name.into(),
+            position,
+            velocity,
+            charge: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa(lanes = "array", layout = "single_alloc", align = 32)]
+pub struct Point<T: Copy>(T, u32, f32);
+
+fn particles(n: usize) -> ParticleVec {
+    let mut particles = ParticleVec::new();
+    for i in 0..n {
+        particles.push(Particle::new("Ar", i as f64, 0.5 * i as f32));
+    }
+    particles
+}
+
+#[test]
+fn lanes() {
+    let particles = particles(10);
+    let slice = particles.as_slice();
+
+    let mut lanes = slice.lanes::<4>();
+    assert_eq!(lanes.len(), 2);
+    assert_eq!(lanes.remainder().position, [8.0, 9.0]);
+
+    let first = lanes.next().unwrap();
+    assert_eq!(first.position, [0.0, 1.0, 2.0, 3.0]);
+    assert_eq!(first.velocity, [0.0, 0.5, 1.0, 1.5]);
+    assert_eq!(first.charge, [0; 4]);
+
+    let last = lanes.next_back().unwrap();
+    assert_eq!(last.position, [4.0, 5.0, 6.0, 7.0]);
+    assert!(lanes.next().is_none());
+
+    assert_eq!(slice.lanes::<16>().count(), 0);
+    assert_eq!(slice.lanes::<16>().remainder().len(), 10);
+    assert_eq!(slice.lanes::<5>().remainder().len(), 0);
+
+    let total = slice.lanes::<2>()
+        .map(|lanes| lanes.position[0] + lanes.position[1])
+        .sum::<f64>();
+    assert_eq!(total, 45.0);
+}
+
+#[test]
+fn load_and_store() {
+    let mut particles = particles(7);
+
+    let mut slice = particles.as_mut_slice();
+    let mut chunks = slice.chunks_exact_mut(4);
+    for mut chunk in &mut chunks {
+        let mut lanes = ParticleLanes::<4>::from_slice(chunk.as_ref());
+        for (position, velocity) in lanes.position.iter_mut().zip(&lanes.velocity) {
+            *position += f64::from(*velocity);
+        }
+        lanes.charge = [1; 4];
+        lanes.copy_to_slice(&mut chunk);
+    }
+    for particle in chunks.into_remainder().iter_mut() {
+        *particle.position += f64::from(*particle.velocity);
+    }
+
+    assert_eq!(particles.position, [0.0, 1.5, 3.0, 4.5, 6.0, 7.5, 9.0]);
+    assert_eq!(particles.charge, [1, 1, 1, 1, 0, 0, 0]);
+    assert_eq!(particles.name[3], "Ar");
+}
+
+#[test]
+#[should_panic(expected = "the slice must contain at least N elements")]
+fn load_too_short() {
+    let particles = particles(3);
+    let _ = ParticleLanes::<4>::from_slice(particles.as_slice());
+}
+
+#[test]
+fn tuple() {
+    let mut points = PointVec::new();
+    for i in 0..6 {
+        points.push(Point(i as u8, i, i as f32));
+    }
+
+    let lanes = points.as_slice().lanes::<4>().next().unwrap();
+    // generic fields are not included in the lanes
+    assert_eq!(lanes.0, [0, 1, 2, 3]);
+    assert_eq!(lanes.1, [0.0, 1.0, 2.0, 3.0]);
+    assert_eq!(points.as_slice().lanes::<4>().remainder().1, [4, 5]);
+    assert!(points.as_ptr().is_aligned());
+}
@@ -0,0 +1,82 @@
+use soa_derive::StructOfArray;
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(vec = "Particles", slice = "ParticlesView", slice_mut = "ParticlesViewMut")]
+#[soa(ref = "ParticleView", ref_mut = "ParticleViewMut", ptr = "ParticlePointer")]
+pub struct Particle {
+    pub name: String,
+    pub mass: f64,
+}
+
+// the default name for the vector is free to use for something else
+#[allow(dead_code)]
+type ParticleVec = Vec<Particle>;
+
+#[test]
+fn custom_names() {
+    let mut particles = Particles::new();
+    particles.push(Particle { name: "Na".into(), mass: 22.99 });
+    particles.push(Particle { name: "Cl".into(), mass: 35.45 });
+
+    let slice: ParticlesView = particles.as_slice();
+    let particle: ParticleView = slice.index(1);
+    assert_eq!(particle.name, "Cl");
+
+    let mut slice: ParticlesViewMut = particles.as_mut_slice();
+    let particle: ParticleViewMut = slice.index_mut(0);
+    *particle.mass = 23.0;
+    assert_eq!(particles.mass, [23.0, 35.45]);
+
+    let ptr: ParticlePointer = particles.as_ptr();
+    // types without a custom name keep the default one
+    let ptr_mut: ParticlePtrMut = ptr.as_mut_ptr();
+    assert_eq!(unsafe { ptr_mut.add(1).as_ref() }.unwrap().mass, &35.45);
+
+    let vec: Particles = vec![Particle { name: "H".into(), mass: 1.0 }].into_iter().collect();
+    assert_eq!(vec.len(), 1);
+}
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(vec = "Positions", slice = "PositionsView", slice_mut = "PositionsViewMut")]
+#[soa(ref = "PositionView", ref_mut = "PositionViewMut", ptr = "PositionPointer", ptr_mut = "PositionPointerMut")]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Atom {
+    pub name: String,
+    #[nested_soa]
+    pub position: Position,
+}
+
+#[test]
+fn nested_custom_names() {
+    let mut atoms = AtomVec::new();
+    atoms.push(Atom { name: "Na".into(), position: Position { x: 1.0, y: 2.0 } });
+    atoms.push(Atom { name: "Cl".into(), position: Position { x: 3.0, y: 4.0 } });
+
+    let positions: &Positions = &atoms.position;
+    assert_eq!(positions.x, [1.0, 3.0]);
+
+    let slice: PositionsView = atoms.as_slice().position;
+    assert_eq!(slice.y, [2.0, 4.0]);
+
+    let atom = atoms.index(1);
+    let position: PositionView = atom.position;
+    assert_eq!(*position.x, 3.0);
+
+    *atoms.index_mut(0).position.y = -2.0;
+    assert_eq!(atoms.position.y, [-2.0, 4.0]);
+
+    let ptr: PositionPointer = atoms.as_ptr().position;
+    assert_eq!(unsafe { *ptr.x.add(1) }, 3.0);
+
+    let names = atoms.iter().map(|atom| atom.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["Na", "Cl"]);
+    assert_eq!(atoms.pop(), Some(Atom { name: "Cl".into(), position: Position { x: 3.0, y: 4.0 } }));
+}
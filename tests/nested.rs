@@ -0,0 +1,284 @@
+use soa_derive::StructOfArray;
+
+mod geometry {
+    use soa_derive::StructOfArray;
+
+    #[derive(Debug, Clone, PartialEq, StructOfArray)]
+    #[soa_derive = "Debug, Clone, PartialEq"]
+    pub struct Vector3 {
+        pub x: f64,
+        pub y: f64,
+        pub z: f64,
+    }
+
+    #[derive(Debug, Clone, PartialEq, StructOfArray)]
+    #[soa_derive = "Debug, Clone, PartialEq"]
+    pub struct Point<T: Copy>(pub T, pub T);
+
+    #[derive(Debug, Clone, PartialEq, StructOfArray)]
+    pub struct Pair<T> {
+        pub first: T,
+        pub second: T,
+    }
+}
+
+use self::geometry::*;
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Particle {
+    pub name: String,
+    #[nested_soa]
+    pub position: Vector3,
+    #[nested_soa]
+    pub velocity: geometry::Vector3,
+    pub mass: f64,
+}
+
+impl Particle {
+    fn new(name: &str, x: f64) -> Particle {
+        Particle {
+            name: name.into(),
+            position: Vector3 { x, y: 2.0 * x, z: 3.0 * x },
+            velocity: Vector3 { x: -x, y: 0.0, z: 0.0 },
+            mass: x,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Segment<T: Copy> {
+    #[nested_soa]
+    pub start: Point<T>,
+    #[nested_soa]
+    pub end: Point<T>,
+}
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+pub struct Labelled<T> {
+    pub label: String,
+    #[nested_soa]
+    pub pair: Pair<T>,
+}
+
+#[test]
+fn vec() {
+    let mut particles = ParticleVec::new();
+    particles.push(Particle::new("Na", 1.0));
+    particles.push(Particle::new("Cl", 2.0));
+    particles.insert(0, Particle::new("H", 0.5));
+
+    assert_eq!(particles.len(), 3);
+    assert_eq!(particles.position.x, [0.5, 1.0, 2.0]);
+    assert_eq!(particles.position.z, [1.5, 3.0, 6.0]);
+    assert_eq!(particles.velocity.x, [-0.5, -1.0, -2.0]);
+
+    assert_eq!(particles.remove(0), Particle::new("H", 0.5));
+    assert_eq!(particles.pop(), Some(Particle::new("Cl", 2.0)));
+    assert_eq!(particles.position.len(), 1);
+
+    let mut other = ParticleVec::with_capacity(2);
+    other.push(Particle::new("O", 3.0));
+    particles.append(&mut other);
+    assert_eq!(particles.position.y, [2.0, 6.0]);
+    assert!(other.position.is_empty());
+
+    particles.retain(|particle| *particle.position.x > 2.0);
+    assert_eq!(particles.name, ["O"]);
+    assert_eq!(particles.position.x, [3.0]);
+}
+
+#[test]
+fn extend() {
+    let mut particles = ParticleVec::new();
+    particles.extend(vec![Particle::new("Na", 1.0), Particle::new("Cl", 2.0)]);
+    assert_eq!(particles.position.y, [2.0, 4.0]);
+
+    let mut other = ParticleVec::new();
+    other.extend(particles.iter().rev());
+    assert_eq!(other.name, ["Cl", "Na"]);
+    assert_eq!(other.position.x, [2.0, 1.0]);
+    assert_eq!(other.velocity.x, [-2.0, -1.0]);
+
+    other.extend_from_slice(particles.slice(1..2));
+    assert_eq!(other.name, ["Cl", "Na", "Cl"]);
+    assert_eq!(other.position.z, [6.0, 3.0, 6.0]);
+}
+
+#[test]
+fn extend_without_clone_derive() {
+    let labelled = |label: &str, first: &str| Labelled {
+        label: label.into(),
+        pair: Pair { first: String::from(first), second: first.to_uppercase() },
+    };
+
+    let mut vec = LabelledVec::new();
+    vec.extend((0..4).filter(|i| i % 2 == 1).map(|i| labelled("odd", &i.to_string())));
+    vec.extend(vec![labelled("a", "x"), labelled("b", "y")]);
+    assert_eq!(vec.label, ["odd", "odd", "a", "b"]);
+    assert_eq!(vec.pair.first, ["1", "3", "x", "y"]);
+
+    let mut other = LabelledVec::new();
+    other.extend_from_slice(vec.slice(2..4));
+    other.extend(vec.iter().take(1));
+    assert_eq!(other.label, ["a", "b", "odd"]);
+    assert_eq!(other.pair.second, ["X", "Y", "1"]);
+}
+
+#[test]
+fn index_and_slices() {
+    let mut particles = ParticleVec::new();
+    particles.push(Particle::new("Na", 1.0));
+    particles.push(Particle::new("Cl", 2.0));
+    particles.push(Particle::new("O", 3.0));
+
+    let particle = particles.index(1);
+    assert_eq!(particle.name, "Cl");
+    let position: Vector3Ref = particle.position;
+    assert_eq!(*position.y, 4.0);
+
+    {
+        let particle = particles.index_mut(2);
+        *particle.position.x = 42.0;
+    }
+    assert_eq!(particles.position.x, [1.0, 2.0, 42.0]);
+
+    let slice = particles.slice(1..3);
+    assert_eq!(slice.len(), 2);
+    assert_eq!(slice.position.x, [2.0, 42.0]);
+    assert_eq!(*slice.index(0).velocity.x, -2.0);
+    assert_eq!(slice.get(1..).unwrap().position.len(), 1);
+    assert_eq!(slice.last().unwrap().name, "O");
+
+    let (first, rest) = slice.split_first().unwrap();
+    assert_eq!(*first.position.x, 2.0);
+    assert_eq!(rest.len(), 1);
+
+    let mut slice = particles.as_mut_slice();
+    slice.swap(0, 2);
+    *slice.index_mut(1).mass = 8.0;
+    let mut tail = slice.index_mut(1..);
+    *tail.first_mut().unwrap().position.z = -1.0;
+    assert_eq!(slice.as_ref().position.z, [9.0, -1.0, 3.0]);
+
+    assert_eq!(particles.name, ["O", "Cl", "Na"]);
+    assert_eq!(particles.mass, [3.0, 8.0, 1.0]);
+    assert_eq!(particles.as_slice().to_vec(), particles);
+}
+
+#[test]
+fn iter() {
+    let particles: ParticleVec = vec![Particle::new("Na", 1.0), Particle::new("Cl", 2.0)].into_iter().collect();
+
+    let names = particles.iter().map(|particle| particle.name.clone()).collect::<Vec<_>>();
+    assert_eq!(names, ["Na", "Cl"]);
+
+    let x = particles.as_slice().iter().rev().map(|particle| *particle.position.x).collect::<Vec<_>>();
+    assert_eq!(x, [2.0, 1.0]);
+    assert_eq!(particles.iter().len(), 2);
+
+    let mut particles = particles;
+    for particle in &mut particles {
+        *particle.velocity.y += 1.0;
+    }
+    for particle in particles.as_mut_slice() {
+        *particle.mass *= 2.0;
+    }
+    assert_eq!(particles.velocity.y, [1.0, 1.0]);
+    assert_eq!(particles.mass, [2.0, 4.0]);
+
+    let mut count = 0;
+    for particle in particles.as_slice() {
+        assert_eq!(*particle.position.y, 2.0 * *particle.position.x);
+        count += 1;
+    }
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn drain() {
+    let mut particles: ParticleVec = vec![
+        Particle::new("Na", 1.0), Particle::new("Cl", 2.0), Particle::new("O", 3.0)
+    ].into_iter().collect();
+
+    let drained = particles.drain(..2).collect::<Vec<_>>();
+    assert_eq!(drained, [Particle::new("Na", 1.0), Particle::new("Cl", 2.0)]);
+    assert_eq!(particles.name, ["O"]);
+    assert_eq!(particles.position.x, [3.0]);
+    assert_eq!(particles.velocity.x, [-3.0]);
+}
+
+#[test]
+fn sort() {
+    let mut particles: ParticleVec = vec![
+        Particle::new("Na", 2.0), Particle::new("Cl", 3.0), Particle::new("O", 1.0)
+    ].into_iter().collect();
+
+    particles.sort_by(|a, b| a.position.x.partial_cmp(b.position.x).unwrap());
+    assert_eq!(particles.name, ["O", "Na", "Cl"]);
+    assert_eq!(particles.position.y, [2.0, 4.0, 6.0]);
+    assert_eq!(particles.velocity.x, [-1.0, -2.0, -3.0]);
+}
+
+#[test]
+fn chunks() {
+    let mut particles: ParticleVec = vec![
+        Particle::new("Na", 1.0), Particle::new("Cl", 2.0), Particle::new("O", 3.0)
+    ].into_iter().collect();
+
+    let chunks = particles.as_slice().chunks(2).map(|chunk| chunk.position.x.to_vec()).collect::<Vec<_>>();
+    assert_eq!(chunks, [vec![1.0, 2.0], vec![3.0]]);
+
+    for chunk in particles.as_mut_slice().chunks_mut(2) {
+        chunk.position.y[0] = 0.0;
+    }
+    assert_eq!(particles.position.y, [0.0, 4.0, 0.0]);
+}
+
+#[test]
+fn into_iter() {
+    let particles: ParticleVec = vec![Particle::new("Na", 1.0), Particle::new("Cl", 2.0)].into_iter().collect();
+
+    let mut iter = particles.into_iter();
+    assert_eq!(iter.len(), 2);
+    assert_eq!(iter.next_back(), Some(Particle::new("Cl", 2.0)));
+    assert_eq!(iter.next(), Some(Particle::new("Na", 1.0)));
+    assert_eq!(iter.next(), None);
+}
+
+#[test]
+fn refs_and_ptr() {
+    let mut particle = Particle::new("Na", 1.0);
+    assert_eq!(*particle.as_ref().position.z, 3.0);
+    *particle.as_mut().velocity.z = 5.0;
+    assert_eq!(particle.velocity.z, 5.0);
+
+    let mut particles = ParticleVec::new();
+    particles.push(Particle::new("H", 0.5));
+    particles.push(particle.clone());
+
+    let ptr = particles.as_mut_ptr();
+    unsafe {
+        let second = ptr.add(1);
+        assert_eq!(second.as_ref().unwrap().name, "Na");
+        assert_eq!(second.read(), particle);
+        second.write(Particle::new("Cl", 2.0));
+    }
+    assert_eq!(particles.position.x, [0.5, 2.0]);
+    assert_eq!(particles.name, ["H", "Cl"]);
+}
+
+#[test]
+fn generic() {
+    let mut segments = SegmentVec::new();
+    segments.push(Segment { start: Point(0, 1), end: Point(2, 3) });
+    segments.push(Segment { start: Point(4, 5), end: Point(6, 7) });
+
+    let starts: &PointVec<i32> = &segments.start;
+    assert_eq!(starts.0, [0, 4]);
+    assert_eq!(segments.end.1, [3, 7]);
+
+    let sum = segments.iter().map(|segment| *segment.start.0 + *segment.end.1).sum::<i32>();
+    assert_eq!(sum, 14);
+}
@@ -0,0 +1,64 @@
+mod geometry {
+    use soa_derive::StructOfArray;
+
+    #[derive(Debug, Clone, PartialEq, StructOfArray)]
+    #[soa_derive = "Debug, Clone, PartialEq"]
+    #[soa(private_fields)]
+    pub struct Vector2 {
+        pub x: f64,
+        pub y: f64,
+    }
+
+    #[derive(Debug, Clone, PartialEq, StructOfArray)]
+    #[soa_derive = "Debug, Clone, PartialEq"]
+    #[soa(private_fields)]
+    pub struct Body {
+        #[nested_soa]
+        pub position: Vector2,
+        pub mass: f64,
+    }
+}
+
+use self::geometry::*;
+
+#[test]
+fn accessors() {
+    let mut vectors = Vector2Vec::new();
+    vectors.push(Vector2 { x: 1.0, y: 2.0 });
+    vectors.push(Vector2 { x: 3.0, y: 4.0 });
+
+    assert_eq!(vectors.x(), [1.0, 3.0]);
+    assert_eq!(vectors.y(), [2.0, 4.0]);
+
+    vectors.x_mut()[1] = -3.0;
+    assert_eq!(vectors.x(), [1.0, -3.0]);
+
+    let (x, y) = vectors.into_columns();
+    assert_eq!(x, [1.0, -3.0]);
+    assert_eq!(y, [2.0, 4.0]);
+
+    let vectors = Vector2Vec::from_columns(x, y).unwrap();
+    assert_eq!(vectors.len(), 2);
+}
+
+#[test]
+fn nested() {
+    let mut bodies = BodyVec::new();
+    bodies.push(Body { position: Vector2 { x: 1.0, y: 2.0 }, mass: 3.0 });
+    bodies.push(Body { position: Vector2 { x: 4.0, y: 5.0 }, mass: 6.0 });
+
+    assert_eq!(bodies.mass(), [3.0, 6.0]);
+    assert_eq!(bodies.position().x, [1.0, 4.0]);
+    bodies.position_mut().y[0] = 8.0;
+    assert_eq!(bodies.position().y, [8.0, 5.0]);
+
+    let positions = Vector2Vec::from_columns(vec![0.0], vec![0.0]).unwrap();
+    let error = BodyVec::from_columns(positions, vec![]).unwrap_err();
+    assert_eq!(error.field, "mass");
+    assert_eq!(error.len, 0);
+    assert_eq!(error.expected, 1);
+
+    bodies.retain_by_mass(|&mass| mass > 4.0);
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(bodies.position().x, [4.0]);
+}
@@ -0,0 +1,143 @@
+#![cfg(feature = "rayon")]
+
+use rayon::prelude::*;
+use soa_derive::{StructOfArray, soa_par_zip};
+
+mod particles;
+use self::particles::{Particle, ParticleVec};
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Vector3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Body {
+    #[nested_soa]
+    pub position: Vector3,
+    pub mass: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(layout = "single_alloc")]
+pub struct Atom {
+    pub name: String,
+    pub charge: f64,
+}
+
+fn particles(size: usize) -> ParticleVec {
+    (0..size).map(|i| Particle::new(format!("{}", i), i as f64)).collect()
+}
+
+#[test]
+fn par_iter() {
+    let particles = particles(1000);
+
+    let total: f64 = particles.par_iter().map(|particle| *particle.mass).sum();
+    assert_eq!(total, 499500.0);
+
+    let names = particles.as_slice().par_iter()
+        .map(|particle| particle.name.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(names.len(), 1000);
+    assert_eq!(names[42], "42");
+
+    let (first, _) = particles.as_slice().split_at(10);
+    let masses = first.into_par_iter().rev().map(|particle| *particle.mass).collect::<Vec<_>>();
+    assert_eq!(masses, [9.0, 8.0, 7.0, 6.0, 5.0, 4.0, 3.0, 2.0, 1.0, 0.0]);
+
+    assert_eq!((&particles).into_par_iter().len(), 1000);
+}
+
+#[test]
+fn par_iter_mut() {
+    let mut particles = particles(1000);
+
+    particles.par_iter_mut().for_each(|particle| *particle.mass *= 2.0);
+    particles.as_mut_slice().par_iter_mut().enumerate().for_each(|(i, particle)| {
+        assert_eq!(*particle.mass, 2.0 * i as f64);
+        particle.name.push('!');
+    });
+    (&mut particles).into_par_iter().with_min_len(7).for_each(|particle| *particle.mass += 1.0);
+
+    for (i, particle) in particles.iter().enumerate() {
+        assert_eq!(*particle.mass, 2.0 * i as f64 + 1.0);
+        assert_eq!(*particle.name, format!("{}!", i));
+    }
+}
+
+#[test]
+fn into_par_iter() {
+    let particles = particles(100);
+    let collected = particles.clone().into_par_iter().collect::<Vec<_>>();
+    assert_eq!(collected.len(), 100);
+    for (particle, expected) in collected.iter().zip(particles.iter()) {
+        assert_eq!(particle.as_ref(), expected);
+    }
+}
+
+#[test]
+fn par_chunks_mut() {
+    let mut particles = particles(103);
+
+    let chunks = particles.par_chunks_mut(10);
+    assert_eq!(chunks.len(), 11);
+    chunks.enumerate().for_each(|(i, chunk)| {
+        for mass in chunk.mass {
+            *mass = i as f64;
+        }
+    });
+    assert_eq!(particles.mass[9], 0.0);
+    assert_eq!(particles.mass[10], 1.0);
+    assert_eq!(particles.mass[102], 10.0);
+
+    let lengths = particles.as_mut_slice().par_chunks_mut(25).map(|chunk| chunk.len()).collect::<Vec<_>>();
+    assert_eq!(lengths, [25, 25, 25, 25, 3]);
+}
+
+#[test]
+fn nested() {
+    let mut bodies = (0..100).map(|i| Body {
+        position: Vector3 { x: i as f64, y: 0.0, z: 0.0 },
+        mass: 1.0,
+    }).collect::<BodyVec>();
+
+    bodies.par_iter_mut().for_each(|body| *body.position.y = 2.0 * *body.position.x);
+    let sum: f64 = bodies.par_iter().map(|body| *body.position.y * *body.mass).sum();
+    assert_eq!(sum, 9900.0);
+
+    let bodies = bodies.into_par_iter().collect::<Vec<_>>();
+    assert_eq!(bodies[3].position, Vector3 { x: 3.0, y: 6.0, z: 0.0 });
+}
+
+#[test]
+fn par_zip() {
+    let mut particles = particles(100);
+    let factors = vec![2.0; 100];
+
+    soa_par_zip!(&mut particles, [name, mut mass], &factors).for_each(|(name, mass, factor)| {
+        assert_eq!(*name, format!("{}", *mass));
+        *mass *= factor;
+    });
+    assert_eq!(particles.mass[50], 100.0);
+
+    let total: f64 = soa_par_zip!(&particles, [mass]).map(|mass| *mass).sum();
+    assert_eq!(total, 9900.0);
+}
+
+#[test]
+fn single_alloc() {
+    let mut atoms = (0..100).map(|i| Atom { name: format!("{}", i), charge: 1.0 }).collect::<AtomVec>();
+
+    atoms.par_iter_mut().for_each(|atom| *atom.charge = -*atom.charge);
+    let total: f64 = atoms.par_iter().map(|atom| *atom.charge).sum();
+    assert_eq!(total, -100.0);
+
+    let atoms = atoms.into_par_iter().collect::<Vec<_>>();
+    assert_eq!(atoms[42], Atom { name: "42".into(), charge: -1.0 });
+}
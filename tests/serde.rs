@@ -0,0 +1,224 @@
+#![cfg(feature = "serde")]
+
+use serde::{Deserialize, Serialize};
+use soa_derive::StructOfArray;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(serde = "columns")]
+pub struct Particle {
+    pub name: String,
+    pub mass: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(serde = "rows")]
+pub struct Atom {
+    pub name: String,
+    pub charge: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(serde = "columns")]
+pub struct Point(f64, f64);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(serde = "columns")]
+pub struct Vector2<T: Copy> {
+    pub x: T,
+    pub y: T,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(serde = "columns")]
+pub struct Body<T: Copy> {
+    #[nested_soa]
+    pub position: Vector2<T>,
+    pub mass: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(serde = "rows")]
+pub struct Segment<T: Copy> {
+    #[nested_soa]
+    pub start: Vector2<T>,
+    #[nested_soa]
+    pub end: Vector2<T>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(serde = "columns", layout = "single_alloc")]
+pub struct Ion {
+    pub name: String,
+    pub charge: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(serde = "columns")]
+pub struct Residue {
+    #[serde(rename = "id")]
+    pub number: u32,
+    #[serde(skip)]
+    pub cached: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(serde = "rows")]
+pub struct Bond {
+    #[serde(rename = "i")]
+    pub first: u32,
+    #[serde(rename = "j")]
+    pub second: u32,
+    #[serde(skip)]
+    pub cached: Option<f64>,
+}
+
+fn particles() -> ParticleVec {
+    let mut particles = ParticleVec::new();
+    particles.push(Particle { name: "Na".into(), mass: 22.99 });
+    particles.push(Particle { name: "Cl".into(), mass: 35.45 });
+    particles.push(Particle { name: "H".into(), mass: 1.008 });
+    particles
+}
+
+#[test]
+fn columns() {
+    let particles = particles();
+    let json = serde_json::to_string(&particles).unwrap();
+    assert_eq!(json, r#"{"name":["Na","Cl","H"],"mass":[22.99,35.45,1.008]}"#);
+
+    let deserialized: ParticleVec = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, particles);
+
+    let json = serde_json::to_string(&particles.slice(1..3)).unwrap();
+    assert_eq!(json, r#"{"name":["Cl","H"],"mass":[35.45,1.008]}"#);
+
+    let json = serde_json::to_string(&particles.get(0).unwrap()).unwrap();
+    assert_eq!(json, r#"{"name":"Na","mass":22.99}"#);
+}
+
+#[test]
+fn columns_length_mismatch() {
+    let json = r#"{"name":["Na","Cl","H"],"mass":[22.99,35.45]}"#;
+    let error = serde_json::from_str::<ParticleVec>(json).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "all the fields must have the same length, but `mass` has 2 elements instead of 3"
+    );
+}
+
+#[test]
+fn rows() {
+    let mut atoms = AtomVec::new();
+    atoms.push(Atom { name: "Na".into(), charge: 1.0 });
+    atoms.push(Atom { name: "Cl".into(), charge: -1.0 });
+
+    let json = serde_json::to_string(&atoms).unwrap();
+    assert_eq!(json, r#"[{"name":"Na","charge":1.0},{"name":"Cl","charge":-1.0}]"#);
+    let vec = atoms.iter().map(|atom| atom.to_owned()).collect::<Vec<_>>();
+    assert_eq!(json, serde_json::to_string(&vec).unwrap());
+
+    let deserialized: AtomVec = serde_json::from_str(&json).unwrap();
+    assert_eq!(deserialized, atoms);
+
+    let json = serde_json::to_string(&atoms.slice(1..2)).unwrap();
+    assert_eq!(json, r#"[{"name":"Cl","charge":-1.0}]"#);
+}
+
+#[test]
+fn tuple() {
+    let mut points = PointVec::new();
+    points.push(Point(1.0, 2.0));
+    points.push(Point(3.0, 4.0));
+
+    let json = serde_json::to_string(&points).unwrap();
+    assert_eq!(json, "[[1.0,3.0],[2.0,4.0]]");
+    assert_eq!(serde_json::from_str::<PointVec>(&json).unwrap(), points);
+
+    let json = serde_json::to_string(&points.get(1).unwrap()).unwrap();
+    assert_eq!(json, serde_json::to_string(&Point(3.0, 4.0)).unwrap());
+
+    let error = serde_json::from_str::<PointVec>("[[1.0],[2.0,4.0]]").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "all the fields must have the same length, but `1` has 2 elements instead of 1"
+    );
+}
+
+#[test]
+fn nested() {
+    let mut bodies = BodyVec::<f32>::new();
+    bodies.push(Body { position: Vector2 { x: 1.0, y: 2.0 }, mass: 3.0 });
+    bodies.push(Body { position: Vector2 { x: 4.0, y: 5.0 }, mass: 6.0 });
+
+    let json = serde_json::to_string(&bodies).unwrap();
+    assert_eq!(json, r#"{"position":{"x":[1.0,4.0],"y":[2.0,5.0]},"mass":[3.0,6.0]}"#);
+    assert_eq!(serde_json::from_str::<BodyVec<f32>>(&json).unwrap(), bodies);
+
+    let json = r#"{"position":{"x":[1.0,4.0],"y":[2.0,5.0]},"mass":[3.0]}"#;
+    let error = serde_json::from_str::<BodyVec<f32>>(json).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "all the fields must have the same length, but `mass` has 1 elements instead of 2"
+    );
+
+    let mut segments = SegmentVec::<i32>::new();
+    segments.push(Segment { start: Vector2 { x: 0, y: 1 }, end: Vector2 { x: 2, y: 3 } });
+    let json = serde_json::to_string(&segments).unwrap();
+    assert_eq!(json, r#"[{"start":{"x":0,"y":1},"end":{"x":2,"y":3}}]"#);
+    assert_eq!(serde_json::from_str::<SegmentVec<i32>>(&json).unwrap(), segments);
+}
+
+#[test]
+fn single_alloc() {
+    let mut ions = IonVec::new();
+    ions.push(Ion { name: "Na".into(), charge: 1 });
+    ions.push(Ion { name: "Cl".into(), charge: -1 });
+
+    let json = serde_json::to_string(&ions).unwrap();
+    assert_eq!(json, r#"{"name":["Na","Cl"],"charge":[1,-1]}"#);
+    assert_eq!(serde_json::from_str::<IonVec>(&json).unwrap(), ions);
+}
+
+#[test]
+fn field_attributes() {
+    let mut residues = ResidueVec::new();
+    residues.push(Residue { number: 3, cached: Some(1.0) });
+    residues.push(Residue { number: 5, cached: None });
+
+    let json = serde_json::to_string(&residues).unwrap();
+    assert_eq!(json, r#"{"id":[3,5]}"#);
+    let json = serde_json::to_string(&residues.get(0).unwrap()).unwrap();
+    assert_eq!(json, r#"{"id":3}"#);
+
+    // skipped columns are deserialized as empty columns
+    let empty = serde_json::from_str::<ResidueVec>(r#"{"id":[]}"#).unwrap();
+    assert!(empty.is_empty());
+    let error = serde_json::from_str::<ResidueVec>(r#"{"id":[3,5]}"#).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "all the fields must have the same length, but `cached` has 0 elements instead of 2"
+    );
+
+    let mut bonds = BondVec::new();
+    bonds.push(Bond { first: 0, second: 1, cached: Some(2.0) });
+    bonds.push(Bond { first: 1, second: 2, cached: None });
+
+    let json = serde_json::to_string(&bonds).unwrap();
+    assert_eq!(json, r#"[{"i":0,"j":1},{"i":1,"j":2}]"#);
+    let vec = bonds.iter().map(|bond| bond.to_owned()).collect::<Vec<_>>();
+    assert_eq!(json, serde_json::to_string(&vec).unwrap());
+
+    let deserialized = serde_json::from_str::<BondVec>(&json).unwrap();
+    assert_eq!(deserialized.first, [0, 1]);
+    assert_eq!(deserialized.second, [1, 2]);
+    assert_eq!(deserialized.cached, [None, None]);
+}
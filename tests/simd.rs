@@ -0,0 +1,41 @@
+// `portable_simd` is only available on nightly compilers, even when the
+// `nightly` feature is enabled with `--all-features`
+#![cfg(all(feature = "nightly", nightly_compiler))]
+#![cfg_attr(all(feature = "nightly", nightly_compiler), feature(portable_simd))]
+
+use std::simd::Simd;
+
+use soa_derive::StructOfArray;
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa(lanes = "simd", layout = "single_alloc", align = 32)]
+pub struct Particle {
+    pub name: String,
+    pub position: f64,
+    pub velocity: f64,
+}
+
+#[test]
+fn simd_lanes() {
+    let mut particles = ParticleVec::new();
+    for i in 0..6 {
+        particles.push(Particle { name: "Ar".into(), position: i as f64, velocity: 0.5 });
+    }
+
+    let lanes = particles.as_slice().lanes::<4>();
+    assert_eq!(lanes.remainder().len(), 2);
+    let sum = lanes.map(|lanes| lanes.position).sum::<Simd<f64, 4>>();
+    assert_eq!(sum.to_array(), [0.0, 1.0, 2.0, 3.0]);
+
+    let mut slice = particles.as_mut_slice();
+    let mut chunks = slice.chunks_exact_mut(4);
+    for mut chunk in &mut chunks {
+        let mut lanes = ParticleLanes::<4>::from_slice(chunk.as_ref());
+        lanes.position += lanes.velocity;
+        lanes.copy_to_slice(&mut chunk);
+    }
+    for particle in chunks.into_remainder().iter_mut() {
+        *particle.position += *particle.velocity;
+    }
+    assert_eq!(particles.position(), [0.5, 1.5, 2.5, 3.5, 4.5, 5.5]);
+}
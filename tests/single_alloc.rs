@@ -0,0 +1,371 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use soa_derive::{soa_zip, StructOfArray};
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(layout = "single_alloc")]
+pub struct Particle {
+    pub name: String,
+    pub mass: f64,
+    pub charge: i8,
+}
+
+impl Particle {
+    pub fn new(name: &str, mass: f64) -> Self {
+        Particle {
+            name: name.into(),
+            mass,
+            charge: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq, Eq"]
+#[soa_attr(Vec, derive(Default))]
+#[soa(layout = "single_alloc")]
+pub struct Point<T: Copy>(T, T);
+
+/// Count the number of times a value is dropped
+#[derive(Debug, Clone)]
+pub struct DropCounter(Rc<Cell<usize>>);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[derive(Debug, Clone, StructOfArray)]
+#[soa(layout = "single_alloc")]
+pub struct Tracked {
+    pub id: usize,
+    pub counter: DropCounter,
+    pub empty: (),
+}
+
+#[derive(Debug, Clone, StructOfArray)]
+#[soa(align = 64)]
+pub struct Aligned {
+    pub x: f64,
+    #[soa(align = 32)]
+    pub flag: u8,
+    pub y: f32,
+}
+
+fn particles() -> ParticleVec {
+    let mut particles = ParticleVec::new();
+    particles.push(Particle::new("Na", 22.99));
+    particles.push(Particle::new("Cl", 35.45));
+    particles.push(Particle::new("H", 1.008));
+    particles
+}
+
+fn names(particles: &ParticleVec) -> Vec<&str> {
+    particles.name().iter().map(String::as_str).collect()
+}
+
+#[test]
+fn capacity() {
+    let mut particles = ParticleVec::new();
+    assert_eq!(particles.capacity(), 0);
+    assert!(particles.is_empty());
+
+    particles.reserve(10);
+    assert!(particles.capacity() >= 10);
+
+    particles.reserve_exact(20);
+    assert_eq!(particles.capacity(), 20);
+
+    particles.push(Particle::new("Na", 22.99));
+    particles.shrink_to_fit();
+    assert_eq!(particles.capacity(), 1);
+
+    let particles = ParticleVec::with_capacity(7);
+    assert_eq!(particles.capacity(), 7);
+    assert_eq!(particles.len(), 0);
+
+    // zero-sized types do not need any memory
+    let points = PointVec::<()>::new();
+    assert_eq!(points.capacity(), usize::MAX);
+}
+
+#[test]
+fn alignment() {
+    let mut particles = ParticleVec::new();
+    for i in 0..17 {
+        particles.push(Particle::new("Fe", i as f64));
+        let ptr = particles.as_ptr();
+        assert_eq!(ptr.name as usize % std::mem::align_of::<String>(), 0);
+        assert_eq!(ptr.mass as usize % std::mem::align_of::<f64>(), 0);
+        assert_eq!(ptr.charge as usize % std::mem::align_of::<i8>(), 0);
+    }
+    assert_eq!(particles.mass()[16], 16.0);
+    assert_eq!(particles.name()[16], "Fe");
+}
+
+#[test]
+fn requested_alignment() {
+    assert_eq!(AlignedPtr::ALIGN_X, 64);
+    assert_eq!(AlignedPtr::ALIGN_FLAG, 32);
+    assert_eq!(AlignedPtrMut::ALIGN_Y, 64);
+    assert_eq!(ParticlePtr::ALIGN_NAME, std::mem::align_of::<String>());
+    assert_eq!(PointPtr::<u16>::ALIGN_1, 2);
+
+    let mut vec = AlignedVec::with_capacity(1);
+    // the `flag` column needs 32 elements to end on a 32 bytes boundary
+    assert_eq!(vec.capacity(), 32);
+
+    for i in 0..100 {
+        vec.push(Aligned { x: i as f64, flag: i as u8, y: 0.0 });
+        assert_eq!(vec.capacity() % 32, 0);
+
+        let ptr = vec.as_ptr();
+        assert!(ptr.is_aligned());
+        assert_eq!(ptr.x as usize % 64, 0);
+        assert_eq!(ptr.flag as usize % 32, 0);
+        assert_eq!(ptr.y as usize % 64, 0);
+        assert!(vec.as_mut_ptr().is_aligned());
+    }
+    assert_eq!(vec.flag()[99], 99);
+
+    vec.truncate(33);
+    vec.shrink_to_fit();
+    assert_eq!(vec.capacity(), 64);
+    assert!(vec.as_ptr().is_aligned());
+
+    vec.reserve_exact(40);
+    assert_eq!(vec.capacity(), 96);
+
+    let aligned = AlignedVec::new();
+    assert!(aligned.as_ptr().is_aligned());
+    assert!(!aligned.as_ptr().wrapping_add(1).is_aligned());
+}
+
+#[test]
+fn push_and_remove() {
+    let mut particles = particles();
+    assert_eq!(particles.len(), 3);
+    assert_eq!(particles.mass(), [22.99, 35.45, 1.008]);
+
+    particles.insert(1, Particle::new("Zn", 65.38));
+    assert_eq!(names(&particles), ["Na", "Zn", "Cl", "H"]);
+    assert_eq!(particles.mass(), [22.99, 65.38, 35.45, 1.008]);
+
+    assert_eq!(particles.remove(0), Particle::new("Na", 22.99));
+    assert_eq!(names(&particles), ["Zn", "Cl", "H"]);
+
+    assert_eq!(particles.swap_remove(0), Particle::new("Zn", 65.38));
+    assert_eq!(names(&particles), ["H", "Cl"]);
+
+    assert_eq!(particles.pop(), Some(Particle::new("Cl", 35.45)));
+    assert_eq!(particles.pop(), Some(Particle::new("H", 1.008)));
+    assert_eq!(particles.pop(), None);
+
+    let mut particles = self::particles();
+    let mut other = particles.split_off(1);
+    assert_eq!(names(&particles), ["Na"]);
+    assert_eq!(names(&other), ["Cl", "H"]);
+
+    particles.append(&mut other);
+    assert!(other.is_empty());
+    assert_eq!(names(&particles), ["Na", "Cl", "H"]);
+
+    particles.truncate(1);
+    assert_eq!(names(&particles), ["Na"]);
+    particles.clear();
+    assert!(particles.is_empty());
+}
+
+#[test]
+fn clone_and_compare() {
+    let mut particles = particles();
+    let clone = particles.clone();
+    assert_eq!(particles, clone);
+
+    particles.mass_mut()[0] = 0.0;
+    assert_ne!(particles, clone);
+
+    particles.extend_from_slice(clone.as_slice());
+    assert_eq!(particles.len(), 6);
+    particles.extend(clone.iter());
+    assert_eq!(particles.len(), 9);
+    particles.resize(2, Particle::new("He", 4.0));
+    assert_eq!(names(&particles), ["Na", "Cl"]);
+    particles.resize(4, Particle::new("He", 4.0));
+    assert_eq!(names(&particles), ["Na", "Cl", "He", "He"]);
+
+    assert_eq!(
+        format!("{:?}", clone.slice(0..1)),
+        r#"ParticleSlice { name: ["Na"], mass: [22.99], charge: [0] }"#
+    );
+    assert_eq!(
+        format!("{:?}", clone),
+        r#"ParticleVec { name: ["Na", "Cl", "H"], mass: [22.99, 35.45, 1.008], charge: [0, 0, 0] }"#
+    );
+
+    let mut points = PointVec::default();
+    points.push(Point(1, 2));
+    assert_eq!(points, points.clone());
+    assert_eq!(format!("{:?}", points), "PointVec([1], [2])");
+}
+
+#[test]
+fn iteration() {
+    let mut particles = particles();
+    for particle in particles.iter_mut() {
+        *particle.mass *= 2.0;
+    }
+    for particle in &mut particles {
+        *particle.charge += 1;
+    }
+    let masses = particles.iter().map(|particle| *particle.mass).collect::<Vec<_>>();
+    assert_eq!(masses, [45.98, 70.9, 2.016]);
+    assert_eq!(particles.charge(), [1, 1, 1]);
+
+    let mut slice = particles.as_mut_slice();
+    for (mass, charge) in soa_zip!(&mut slice, [mut mass, charge]) {
+        *mass += f64::from(*charge);
+    }
+    assert_eq!(particles.mass(), [46.98, 71.9, 3.016]);
+
+    let mut iter = particles.clone().into_iter();
+    assert_eq!(iter.len(), 3);
+    assert_eq!(iter.next_back().unwrap().name, "H");
+    assert_eq!(iter.next().unwrap().name, "Na");
+    assert_eq!(iter.len(), 1);
+    // the remaining element is dropped with the iterator
+    drop(iter);
+
+    let mut particles = particles.into_iter().rev().collect::<ParticleVec>();
+    assert_eq!(names(&particles), ["H", "Cl", "Na"]);
+
+    particles.push(Particle::new("O", 15.999));
+    let drained = particles.drain(1..3).map(|particle| particle.name).collect::<Vec<_>>();
+    assert_eq!(drained, ["Cl", "Na"]);
+    assert_eq!(names(&particles), ["H", "O"]);
+
+    // partially consumed drains still remove the whole range
+    let mut particles = self::particles();
+    assert_eq!(particles.drain(..2).next_back().unwrap().name, "Cl");
+    assert_eq!(names(&particles), ["H"]);
+}
+
+#[test]
+fn index_and_sort() {
+    let mut particles = particles();
+    assert_eq!(particles.index(1).name, "Cl");
+    assert_eq!(particles.get(1..).unwrap().name, ["Cl", "H"]);
+    assert!(particles.get(3).is_none());
+    assert!(particles.get(2..4).is_none());
+
+    *particles.index_mut(2).mass = 1.0;
+    assert_eq!(particles.get_mut(..=2).unwrap().mass, [22.99, 35.45, 1.0]);
+
+    particles.sort_by_field_mass();
+    assert_eq!(names(&particles), ["H", "Na", "Cl"]);
+    particles.sort_by(|a, b| a.name.cmp(b.name));
+    assert_eq!(names(&particles), ["Cl", "H", "Na"]);
+
+    particles.retain_by_mass(|&mass| mass > 10.0);
+    assert_eq!(names(&particles), ["Cl", "Na"]);
+    particles.retain(|particle| particle.name != "Na");
+    assert_eq!(names(&particles), ["Cl"]);
+}
+
+#[test]
+fn columns() {
+    let particles = ParticleVec::from_columns(
+        vec!["Na".into(), "Cl".into()],
+        vec![22.99, 35.45],
+        vec![1, -1],
+    ).unwrap();
+    assert_eq!(names(&particles), ["Na", "Cl"]);
+    assert_eq!(particles.charge(), [1, -1]);
+
+    let (names, masses, charges) = particles.into_columns();
+    assert_eq!(names, ["Na", "Cl"]);
+    assert_eq!(masses, [22.99, 35.45]);
+    assert_eq!(charges, [1, -1]);
+
+    let error = ParticleVec::from_columns(names, masses, vec![1]).unwrap_err();
+    assert_eq!(error.field, "charge");
+    assert_eq!(error.len, 1);
+    assert_eq!(error.expected, 2);
+}
+
+#[test]
+fn raw_parts() {
+    let mut particles = std::mem::ManuallyDrop::new(particles());
+    let (ptr, len, capacity) = (particles.as_mut_ptr(), particles.len(), particles.capacity());
+    let particles = unsafe { ParticleVec::from_raw_parts(ptr, len, capacity) };
+    assert_eq!(names(&particles), ["Na", "Cl", "H"]);
+}
+
+#[test]
+fn extend() {
+    let drops = Rc::new(Cell::new(0));
+    let tracked = |id| Tracked { id, counter: DropCounter(Rc::clone(&drops)), empty: () };
+
+    let mut vec = TrackedVec::with_capacity(2);
+    // the size hint of the iterator does not cover all the values
+    vec.extend((0..10).filter(|id| id % 2 == 0).map(tracked));
+    assert_eq!(vec.id(), [0, 2, 4, 6, 8]);
+    assert!(vec.capacity() >= 5);
+
+    // `TrackedVec` does not derive `Clone`, but all the fields implement it
+    let mut other = TrackedVec::new();
+    other.extend_from_slice(vec.slice(1..3));
+    other.extend(vec.iter().rev().take(2));
+    assert_eq!(other.id(), [2, 4, 8, 6]);
+    assert_eq!(drops.get(), 0);
+
+    drop(vec);
+    drop(other);
+    assert_eq!(drops.get(), 9);
+    assert_eq!(Rc::strong_count(&drops), 1);
+}
+
+#[test]
+fn drops() {
+    let drops = Rc::new(Cell::new(0));
+    let tracked = |id| Tracked { id, counter: DropCounter(Rc::clone(&drops)), empty: () };
+
+    let mut vec = TrackedVec::new();
+    for id in 0..8 {
+        vec.push(tracked(id));
+    }
+    assert_eq!(drops.get(), 0);
+
+    vec.truncate(6);
+    assert_eq!(drops.get(), 2);
+
+    vec.retain_mask(&[true, false, true, false, true, true]);
+    assert_eq!(drops.get(), 4);
+    assert_eq!(vec.id(), [0, 2, 4, 5]);
+
+    drop(vec.pop());
+    assert_eq!(drops.get(), 5);
+
+    vec.drain(..1);
+    assert_eq!(drops.get(), 6);
+    assert_eq!(vec.id(), [2, 4]);
+
+    let mut iter = vec.into_iter();
+    drop(iter.next());
+    assert_eq!(drops.get(), 7);
+    drop(iter);
+    assert_eq!(drops.get(), 8);
+
+    let mut vec = TrackedVec::with_capacity(2);
+    vec.push(tracked(0));
+    vec.push(tracked(1));
+    let (ids, counters, _) = vec.into_columns();
+    assert_eq!(ids, [0, 1]);
+    assert_eq!(counters.len(), 2);
+    assert_eq!(drops.get(), 8);
+    drop(counters);
+    assert_eq!(Rc::strong_count(&drops), 1);
+}
@@ -0,0 +1,88 @@
+use soa_derive::StructOfArray;
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Body {
+    pub mass: f64,
+    #[soa(skip)]
+    pub debug_name: String,
+    pub velocity: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Sample(f32, #[soa(skip)] Option<String>, u8);
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Cold {
+    #[soa(skip)]
+    pub data: Vec<u8>,
+}
+
+fn body(mass: f64) -> Body {
+    Body {
+        mass,
+        debug_name: format!("body of mass {}", mass),
+        velocity: -mass,
+    }
+}
+
+#[test]
+fn skipped_fields_use_default() {
+    let mut bodies = BodyVec::new();
+    bodies.push(body(1.0));
+    bodies.push(body(2.0));
+    bodies.insert(0, body(3.0));
+
+    assert_eq!(bodies.mass, [3.0, 1.0, 2.0]);
+    assert_eq!(bodies.velocity, [-3.0, -1.0, -2.0]);
+
+    let expected = Body { mass: 2.0, debug_name: String::new(), velocity: -2.0 };
+    assert_eq!(bodies.pop(), Some(expected));
+    assert_eq!(bodies.remove(0).debug_name, "");
+    assert_eq!(bodies.swap_remove(0).mass, 1.0);
+
+    let bodies: BodyVec = vec![body(4.0), body(5.0)].into_iter().collect();
+    let masses = bodies.iter().map(|body| *body.mass).collect::<Vec<_>>();
+    assert_eq!(masses, [4.0, 5.0]);
+    assert_eq!(unsafe { bodies.as_ptr().add(1).read() }.debug_name, "");
+
+    let body = body(6.0);
+    assert_eq!(*body.as_ref().velocity, -6.0);
+}
+
+#[test]
+fn tuple_struct() {
+    let mut samples = SampleVec::new();
+    samples.push(Sample(0.5, Some("first".into()), 3));
+    samples.push(Sample(1.5, None, 4));
+
+    // the generated tuple structs only contain the stored fields
+    assert_eq!(samples.0, [0.5, 1.5]);
+    assert_eq!(samples.1, [3, 4]);
+
+    let sample = samples.index(0);
+    assert_eq!(*sample.1, 3);
+    assert_eq!(samples.pop(), Some(Sample(1.5, None, 4)));
+    assert_eq!(samples.pop(), Some(Sample(0.5, None, 3)));
+}
+
+#[test]
+fn all_fields_skipped() {
+    let mut cold = ColdVec::new();
+    cold.push(Cold { data: vec![1, 2, 3] });
+    cold.push(Cold { data: vec![] });
+    assert_eq!(cold.len(), 2);
+    assert_eq!(cold.pop(), Some(Cold { data: vec![] }));
+}
+
+#[test]
+fn into_iter() {
+    let bodies: BodyVec = vec![body(1.0), body(2.0)].into_iter().collect();
+    let masses = bodies.into_iter().map(|body| {
+        assert_eq!(body.debug_name, "");
+        body.mass
+    }).collect::<Vec<_>>();
+    assert_eq!(masses, [1.0, 2.0]);
+}
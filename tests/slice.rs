@@ -95,3 +95,75 @@ fn get() {
         assert_eq!(particles.as_slice().get_unchecked(0).name, "Cl");
     }
 }
+
+#[test]
+fn chunks() {
+    let mut particles = ParticleVec::new();
+    for (i, name) in ["Cl", "Na", "Zn", "Fe", "C"].iter().enumerate() {
+        particles.push(Particle::new(String::from(*name), i as f64));
+    }
+    let slice = particles.as_slice();
+
+    let chunks = slice.chunks(2);
+    assert_eq!(chunks.len(), 3);
+    let chunks = chunks.map(|chunk| chunk.mass.to_vec()).collect::<Vec<_>>();
+    assert_eq!(chunks, [vec![0.0, 1.0], vec![2.0, 3.0], vec![4.0]]);
+
+    let mut chunks = slice.chunks_exact(2);
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(chunks.next().unwrap().name, ["Cl", "Na"]);
+    assert_eq!(chunks.next().unwrap().name, ["Zn", "Fe"]);
+    assert!(chunks.next().is_none());
+    assert_eq!(chunks.remainder().name, ["C"]);
+
+    let chunks = slice.rchunks(2).map(|chunk| chunk.mass.to_vec()).collect::<Vec<_>>();
+    assert_eq!(chunks, [vec![3.0, 4.0], vec![1.0, 2.0], vec![0.0]]);
+
+    let windows = slice.windows(4);
+    assert_eq!(windows.len(), 2);
+    let windows = windows.map(|window| window.mass.to_vec()).collect::<Vec<_>>();
+    assert_eq!(windows, [vec![0.0, 1.0, 2.0, 3.0], vec![1.0, 2.0, 3.0, 4.0]]);
+    assert_eq!(slice.windows(6).count(), 0);
+}
+
+#[test]
+fn chunks_rev() {
+    let mut particles = ParticleVec::new();
+    for (i, name) in ["Cl", "Na", "Zn", "Fe", "C"].iter().enumerate() {
+        particles.push(Particle::new(String::from(*name), i as f64));
+    }
+    let slice = particles.as_slice();
+
+    let chunks = slice.chunks(2).rev().map(|chunk| chunk.mass.to_vec()).collect::<Vec<_>>();
+    assert_eq!(chunks, [vec![4.0], vec![2.0, 3.0], vec![0.0, 1.0]]);
+
+    let mut chunks = slice.chunks(2);
+    assert_eq!(chunks.next_back().unwrap().name, ["C"]);
+    assert_eq!(chunks.next().unwrap().name, ["Cl", "Na"]);
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks.next_back().unwrap().name, ["Zn", "Fe"]);
+    assert!(chunks.next().is_none());
+
+    let mut chunks = slice.chunks_exact(2);
+    assert_eq!(chunks.next_back().unwrap().name, ["Zn", "Fe"]);
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks.next_back().unwrap().name, ["Cl", "Na"]);
+    assert!(chunks.next_back().is_none());
+
+    let chunks = slice.rchunks(2).rev().map(|chunk| chunk.mass.to_vec()).collect::<Vec<_>>();
+    assert_eq!(chunks, [vec![0.0], vec![1.0, 2.0], vec![3.0, 4.0]]);
+
+    let mut windows = slice.windows(3);
+    assert_eq!(windows.next_back().unwrap().mass, [2.0, 3.0, 4.0]);
+    assert_eq!(windows.len(), 2);
+    assert_eq!(windows.next().unwrap().mass, [0.0, 1.0, 2.0]);
+    assert_eq!(windows.next_back().unwrap().mass, [1.0, 2.0, 3.0]);
+    assert!(windows.next_back().is_none());
+}
+
+#[test]
+#[should_panic(expected = "chunk size must be non-zero")]
+fn chunks_zero_size() {
+    let particles = ParticleVec::new();
+    let _ = particles.as_slice().chunks(0);
+}
@@ -1,6 +1,15 @@
 mod particles;
 use self::particles::{Particle, ParticleVec};
 
+use soa_derive::StructOfArray;
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+pub struct Item<T> {
+    key: u32,
+    value: T,
+    label: std::string::String,
+}
+
 #[test]
 fn len() {
     let mut particles = ParticleVec::new();
@@ -126,3 +135,146 @@ fn split_non_mut() {
     assert_eq!(end.len(), 3);
     assert_eq!(end.name[0], "Na");
 }
+
+fn particles(masses: &[f64]) -> ParticleVec {
+    masses.iter().enumerate()
+        .map(|(i, &mass)| Particle::new(format!("{}", i), mass))
+        .collect()
+}
+
+#[test]
+fn sort() {
+    let mut particles = particles(&[3.0, 1.0, 2.0, 1.0, 0.5]);
+
+    particles.as_mut_slice().sort_by(|a, b| a.mass.partial_cmp(b.mass).unwrap());
+    assert_eq!(particles.mass, [0.5, 1.0, 1.0, 2.0, 3.0]);
+    // the sort is stable
+    assert_eq!(particles.name, ["4", "1", "3", "2", "0"]);
+
+    let mut slice = particles.as_mut_slice();
+    slice.index_mut(1..).sort_by_key(|particle| std::cmp::Reverse(particle.name.clone()));
+    assert_eq!(particles.name, ["4", "3", "2", "1", "0"]);
+    assert_eq!(particles.mass, [0.5, 1.0, 2.0, 1.0, 3.0]);
+
+    particles.sort_unstable_by(|a, b| b.mass.partial_cmp(a.mass).unwrap());
+    assert_eq!(particles.mass, [3.0, 2.0, 1.0, 1.0, 0.5]);
+    assert_eq!(particles.name[0], "0");
+    assert_eq!(particles.name[1], "2");
+}
+
+#[test]
+fn sort_by_field() {
+    let mut particles = particles(&[3.0, 1.0, 2.0, 1.0, 0.5]);
+
+    particles.sort_by_field_mass();
+    assert_eq!(particles.mass, [0.5, 1.0, 1.0, 2.0, 3.0]);
+    assert_eq!(particles.name, ["4", "1", "3", "2", "0"]);
+
+    particles.as_mut_slice().index_mut(..3).sort_by_field_name();
+    assert_eq!(particles.name, ["1", "3", "4", "2", "0"]);
+    assert_eq!(particles.mass, [1.0, 1.0, 0.5, 2.0, 3.0]);
+}
+
+#[test]
+fn sort_by_field_any_ord() {
+    let mut items = ItemVec::new();
+    for (key, value) in [(2, 'b'), (0, 'c'), (1, 'a')] {
+        items.push(Item { key, value, label: key.to_string() });
+    }
+
+    items.sort_by_field_key();
+    assert_eq!(items.value, ['c', 'a', 'b']);
+    items.sort_by_field_value();
+    assert_eq!(items.key, [1, 2, 0]);
+    items.as_mut_slice().sort_by_field_label();
+    assert_eq!(items.label, ["0", "1", "2"]);
+
+    // the generic `sort_by_key` is still available
+    items.sort_by_key(|item| std::cmp::Reverse(*item.key));
+    assert_eq!(items.key, [2, 1, 0]);
+}
+
+#[test]
+fn sort_by_field_nan() {
+    let mut particles = particles(&[3.0, f64::NAN, 2.0, -f64::NAN]);
+    particles.sort_by_field_mass();
+    assert_eq!(particles.name, ["3", "2", "0", "1"]);
+    assert_eq!(particles.mass[1..3], [2.0, 3.0]);
+}
+
+#[test]
+fn apply_permutation() {
+    let mut particles = particles(&[0.0, 1.0, 2.0, 3.0, 4.0]);
+    particles.apply_permutation(&[2, 0, 1, 4, 3]);
+    assert_eq!(particles.mass, [2.0, 0.0, 1.0, 4.0, 3.0]);
+    assert_eq!(particles.name, ["2", "0", "1", "4", "3"]);
+
+    particles.as_mut_slice().apply_permutation(&[0, 1, 2, 3, 4]);
+    assert_eq!(particles.mass, [2.0, 0.0, 1.0, 4.0, 3.0]);
+}
+
+#[test]
+#[should_panic(expected = "invalid permutation")]
+fn apply_invalid_permutation() {
+    let mut particles = particles(&[0.0, 1.0, 2.0]);
+    particles.apply_permutation(&[0, 2, 2]);
+}
+
+#[test]
+fn chunks_mut() {
+    let mut particles = particles(&[0.0, 1.0, 2.0, 3.0, 4.0]);
+
+    let mut slice = particles.as_mut_slice();
+    let chunks = slice.chunks_mut(2);
+    assert_eq!(chunks.len(), 3);
+    for (i, chunk) in chunks.enumerate() {
+        for mass in chunk.mass.iter_mut() {
+            *mass += 10.0 * i as f64;
+        }
+        chunk.name[0].push('!');
+    }
+    assert_eq!(particles.mass, [0.0, 1.0, 12.0, 13.0, 24.0]);
+    assert_eq!(particles.name, ["0!", "1", "2!", "3", "4!"]);
+
+    // chunks can be processed in separate threads
+    let mut slice = particles.as_mut_slice();
+    std::thread::scope(|scope| {
+        for chunk in slice.chunks_mut(2) {
+            scope.spawn(move || {
+                for mass in chunk.mass {
+                    *mass *= 2.0;
+                }
+            });
+        }
+    });
+    assert_eq!(particles.mass, [0.0, 2.0, 24.0, 26.0, 48.0]);
+}
+
+#[test]
+fn chunks_exact_mut() {
+    let mut particles = particles(&[0.0, 1.0, 2.0, 3.0, 4.0]);
+
+    let mut slice = particles.as_mut_slice();
+    let mut chunks = slice.chunks_exact_mut(2);
+    assert_eq!(chunks.len(), 2);
+    for (i, chunk) in (&mut chunks).enumerate() {
+        assert_eq!(chunk.len(), 2);
+        for mass in chunk.mass.iter_mut() {
+            *mass += 10.0 * i as f64;
+        }
+    }
+    let remainder = chunks.into_remainder();
+    assert_eq!(remainder.len(), 1);
+    remainder.name[0].push('!');
+
+    assert_eq!(particles.mass, [0.0, 1.0, 12.0, 13.0, 4.0]);
+    assert_eq!(particles.name, ["0", "1", "2", "3", "4!"]);
+
+    let mut slice = particles.as_mut_slice();
+    let mut chunks = slice.chunks_exact_mut(2);
+    chunks.next_back().unwrap().name[0].push('?');
+    assert_eq!(chunks.len(), 1);
+    chunks.next().unwrap().name[1].push('?');
+    assert!(chunks.next_back().is_none());
+    assert_eq!(particles.name, ["0", "1?", "2?", "3", "4!"]);
+}
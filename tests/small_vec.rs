@@ -0,0 +1,232 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use soa_derive::StructOfArray;
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+#[soa(small_vec)]
+pub struct Particle {
+    pub name: String,
+    pub mass: f64,
+}
+
+impl Particle {
+    pub fn new(name: &str, mass: f64) -> Self {
+        Particle {
+            name: name.into(),
+            mass,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq, Eq"]
+#[soa(small_vec)]
+pub struct Point<T: Copy>(T, T);
+
+/// Count the number of times a value is dropped
+#[derive(Debug, Clone)]
+pub struct DropCounter(Rc<Cell<usize>>);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[derive(Debug, StructOfArray)]
+#[soa(small_vec)]
+pub struct Tracked {
+    pub id: usize,
+    pub counter: DropCounter,
+}
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa(small_vec, layout = "single_alloc")]
+pub struct Packed {
+    pub id: u32,
+    pub name: String,
+}
+
+fn particles() -> ParticleSmallVec<2> {
+    let mut particles = ParticleSmallVec::new();
+    particles.push(Particle::new("Na", 22.99));
+    particles.push(Particle::new("Cl", 35.45));
+    particles.push(Particle::new("H", 1.008));
+    particles
+}
+
+#[test]
+fn push_and_pop() {
+    let mut particles = ParticleSmallVec::<2>::new();
+    assert_eq!(particles.capacity(), 2);
+    assert!(particles.is_empty());
+    assert!(!particles.spilled());
+
+    particles.push(Particle::new("Na", 22.99));
+    particles.push(Particle::new("Cl", 35.45));
+    assert_eq!(particles.len(), 2);
+    assert!(!particles.spilled());
+    assert_eq!(particles.as_slice().mass, [22.99, 35.45]);
+
+    particles.push(Particle::new("H", 1.008));
+    assert!(particles.spilled());
+    assert!(particles.capacity() >= 3);
+    assert_eq!(particles.as_slice().name, ["Na", "Cl", "H"]);
+
+    assert_eq!(particles.pop(), Some(Particle::new("H", 1.008)));
+    assert!(particles.spilled());
+    particles.shrink_to_fit();
+    assert!(!particles.spilled());
+    assert_eq!(particles.as_slice().name, ["Na", "Cl"]);
+
+    particles.truncate(1);
+    assert_eq!(particles.as_slice().name, ["Na"]);
+    particles.clear();
+    assert_eq!(particles.pop(), None);
+
+    let particles = ParticleSmallVec::<2>::with_capacity(2);
+    assert!(!particles.spilled());
+    let particles = ParticleSmallVec::<2>::with_capacity(3);
+    assert!(particles.spilled());
+    assert!(particles.capacity() >= 3);
+}
+
+#[test]
+fn insert_and_remove() {
+    let mut particles = ParticleSmallVec::<3>::new();
+    particles.push(Particle::new("Na", 22.99));
+    particles.insert(0, Particle::new("Cl", 35.45));
+    particles.insert(1, Particle::new("H", 1.008));
+    assert!(!particles.spilled());
+    assert_eq!(particles.as_slice().name, ["Cl", "H", "Na"]);
+
+    particles.insert(3, Particle::new("O", 15.999));
+    assert!(particles.spilled());
+    assert_eq!(particles.as_slice().name, ["Cl", "H", "Na", "O"]);
+
+    assert_eq!(particles.remove(1), Particle::new("H", 1.008));
+    assert_eq!(particles.swap_remove(0), Particle::new("Cl", 35.45));
+    assert_eq!(particles.as_slice().name, ["O", "Na"]);
+
+    particles.shrink_to_fit();
+    assert!(!particles.spilled());
+    assert_eq!(particles.remove(0), Particle::new("O", 15.999));
+    particles.push(Particle::new("C", 12.011));
+    assert_eq!(particles.swap_remove(0), Particle::new("Na", 22.99));
+    assert_eq!(particles.as_slice().name, ["C"]);
+}
+
+#[test]
+fn index() {
+    let mut particles = particles();
+    assert_eq!(particles.index(1).name, "Cl");
+    assert_eq!(particles.get(2).unwrap().name, "H");
+    assert!(particles.get(3).is_none());
+    assert_eq!(particles.get(1..).unwrap().name, ["Cl", "H"]);
+    assert_eq!(particles.index(..=1).mass, [22.99, 35.45]);
+
+    *particles.index_mut(0).mass = 23.0;
+    particles.get_mut(1..).unwrap().mass[1] = 1.0;
+    assert_eq!(particles.as_slice().mass, [23.0, 35.45, 1.0]);
+    assert_eq!(unsafe { particles.get_unchecked(0).name }, "Na");
+}
+
+#[test]
+fn iteration() {
+    let mut particles = particles();
+    for particle in particles.iter_mut() {
+        *particle.mass += 1.0;
+    }
+    for particle in &mut particles {
+        particle.name.push('!');
+    }
+    let names = particles.iter().map(|particle| particle.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["Na!", "Cl!", "H!"]);
+    let masses = (&particles).into_iter().map(|particle| *particle.mass).collect::<Vec<_>>();
+    assert_eq!(masses, [23.99, 36.45, 2.008]);
+
+    let collected = vec![Point(1, 2), Point(3, 4)].into_iter().collect::<PointSmallVec<u8, 2>>();
+    assert!(!collected.spilled());
+    let mut extended = collected.clone();
+    extended.extend(vec![Point(5, 6)]);
+    assert!(extended.spilled());
+    assert_eq!(extended.as_slice().0, [1, 3, 5]);
+
+    let vec = extended.into_vec();
+    assert_eq!(vec.1, [2, 4, 6]);
+
+    let mut extended = PointSmallVec::<u8, 4>::new();
+    extended.extend((0..10).filter(|i| i % 3 == 0).map(|i| Point(i, i + 1)));
+    assert!(!extended.spilled());
+    extended.extend((0..10).filter(|i| i % 3 == 1).map(|i| Point(i, i + 1)));
+    assert!(extended.spilled());
+    assert_eq!(extended.as_slice().0, [0, 3, 6, 9, 1, 4, 7]);
+    assert_eq!(collected.into_vec().0, [1, 3]);
+}
+
+#[test]
+fn traits() {
+    let particles = particles();
+    let clone = particles.clone();
+    assert_eq!(particles, clone);
+    assert_eq!(
+        format!("{:?}", clone),
+        r#"ParticleSmallVec { name: ["Na", "Cl", "H"], mass: [22.99, 35.45, 1.008] }"#
+    );
+
+    let mut points = PointSmallVec::<u8, 1>::default();
+    points.push(Point(1, 2));
+    let inline = points.clone();
+    points.push(Point(3, 4));
+    assert_eq!(points.index(1), PointRef(&3, &4));
+    assert_ne!(points, inline);
+    points.pop();
+    // equality does not depend on the storage
+    assert_eq!(points, inline);
+    assert_eq!(format!("{:?}", points), "PointSmallVec([1], [2])");
+}
+
+#[test]
+fn drops() {
+    let drops = Rc::new(Cell::new(0));
+    let tracked = |id| Tracked { id, counter: DropCounter(Rc::clone(&drops)) };
+
+    let mut small_vec = TrackedSmallVec::<4>::new();
+    for id in 0..6 {
+        small_vec.push(tracked(id));
+    }
+    // moving the values to the heap does not drop them
+    assert!(small_vec.spilled());
+    assert_eq!(drops.get(), 0);
+
+    small_vec.truncate(3);
+    assert_eq!(drops.get(), 3);
+
+    small_vec.shrink_to_fit();
+    assert!(!small_vec.spilled());
+    assert_eq!(drops.get(), 3);
+    assert_eq!(small_vec.as_slice().id, [0, 1, 2]);
+
+    drop(small_vec.pop());
+    assert_eq!(drops.get(), 4);
+
+    drop(small_vec);
+    assert_eq!(drops.get(), 6);
+    assert_eq!(Rc::strong_count(&drops), 1);
+}
+
+#[test]
+fn single_alloc() {
+    let mut packed = PackedSmallVec::<2>::new();
+    packed.extend((0..5).map(|id| Packed { id, name: id.to_string() }));
+    assert!(packed.spilled());
+    assert_eq!(packed.as_slice().id, [0, 1, 2, 3, 4]);
+
+    packed.truncate(2);
+    packed.shrink_to_fit();
+    assert!(!packed.spilled());
+    assert_eq!(packed.as_slice().id, [0, 1]);
+    assert_eq!(packed.as_slice().name, ["0", "1"]);
+}
@@ -0,0 +1,54 @@
+// clippy does not know that each soa_attr targets a different type
+#![allow(clippy::duplicated_attributes)]
+
+use soa_derive::StructOfArray;
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_attr(Vec, derive(Debug, Default, Clone, PartialEq))]
+#[soa_attr(Ref, derive(Debug, PartialEq, PartialOrd))]
+#[soa_attr(RefMut, derive(Debug))]
+#[soa_attr(Slice, derive(Debug))]
+#[soa_attr(Slice, repr(C))]
+#[soa_attr(SliceMut, derive(Debug))]
+#[soa_attr(Ptr, derive(Debug, PartialEq))]
+#[soa_attr(PtrMut, derive(Debug))]
+pub struct Item {
+    pub id: u32,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug"]
+#[soa_attr(Vec, derive(Default))]
+pub struct Mixed {
+    pub value: i64,
+}
+
+#[test]
+fn per_type_derives() {
+    let mut items = ItemVec::default();
+    items.push(Item { id: 1, weight: 2.0 });
+    items.push(Item { id: 0, weight: 3.0 });
+
+    // Vec derives Clone, which also enables resize and to_vec
+    let copy = items.clone();
+    assert_eq!(copy, items);
+    items.resize(3, Item { id: 4, weight: 0.5 });
+    assert_eq!(items.as_slice().to_vec().id, [1, 0, 4]);
+
+    assert!(items.index(0) > items.index(1));
+    assert_eq!(format!("{:?}", items.index(1)), "ItemRef { id: 0, weight: 3.0 }");
+    assert_eq!(format!("{:?}", items.index_mut(1)), "ItemRefMut { id: 0, weight: 3.0 }");
+    assert_eq!(format!("{:?}", items.slice(0..1)), "ItemSlice { id: [1], weight: [2.0] }");
+    assert_eq!(format!("{:?}", items.as_mut_slice().index_mut(2..)), "ItemSliceMut { id: [4], weight: [0.5] }");
+    assert_eq!(items.as_ptr(), items.as_slice().as_ptr());
+    assert!(format!("{:?}", items.as_mut_ptr()).starts_with("ItemPtrMut"));
+}
+
+#[test]
+fn with_soa_derive() {
+    let mut values = MixedVec::default();
+    values.push(Mixed { value: 4 });
+    assert_eq!(format!("{:?}", values), "MixedVec { value: [4] }");
+    assert_eq!(format!("{:?}", values.index(0)), "MixedRef { value: 4 }");
+}
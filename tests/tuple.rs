@@ -0,0 +1,91 @@
+use soa_derive::{StructOfArray, soa_zip};
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Point(f64, f64, f64);
+
+#[derive(Debug, Clone, PartialEq, StructOfArray)]
+#[soa_derive = "Debug, Clone, PartialEq"]
+pub struct Meters<T: Copy>(pub T) where T: PartialOrd;
+
+#[test]
+fn vec() {
+    let mut points = PointVec::new();
+    points.push(Point(0.0, 1.0, 2.0));
+    points.push(Point(3.0, 4.0, 5.0));
+    points.insert(0, Point(-1.0, -1.0, -1.0));
+
+    assert_eq!(points.len(), 3);
+    assert_eq!(points.0, [-1.0, 0.0, 3.0]);
+    assert_eq!(points.1, [-1.0, 1.0, 4.0]);
+    assert_eq!(points.2, [-1.0, 2.0, 5.0]);
+
+    assert_eq!(points.remove(0), Point(-1.0, -1.0, -1.0));
+    assert_eq!(points.pop(), Some(Point(3.0, 4.0, 5.0)));
+    assert_eq!(points.swap_remove(0), Point(0.0, 1.0, 2.0));
+    assert!(points.is_empty());
+
+    let mut meters = MetersVec::new();
+    meters.push(Meters(3u32));
+    meters.push(Meters(8u32));
+    assert_eq!(meters.0, [3, 8]);
+
+    let mut points: PointVec = vec![Point(2.0, 0.0, 1.0), Point(1.0, 2.0, 0.0)].into_iter().collect();
+    points.sort_by_field_0();
+    assert_eq!(points.1, [2.0, 0.0]);
+    points.sort_by_field_1();
+    assert_eq!(points.0, [2.0, 1.0]);
+}
+
+#[test]
+fn refs_and_slices() {
+    let mut points: PointVec = vec![Point(0.0, 1.0, 2.0), Point(3.0, 4.0, 5.0)].into_iter().collect();
+
+    let point: PointRef = points.index(1);
+    assert_eq!(*point.0, 3.0);
+    assert_eq!(*point.2, 5.0);
+
+    let slice = points.as_slice();
+    let (first, rest) = slice.split_first().unwrap();
+    assert_eq!(*first.1, 1.0);
+    assert_eq!(rest.len(), 1);
+    assert_eq!(slice.to_vec(), points);
+
+    {
+        let mut slice = points.as_mut_slice();
+        slice.swap(0, 1);
+        let point: PointRefMut = slice.index_mut(1);
+        *point.0 = 42.0;
+    }
+    assert_eq!(points.0, [3.0, 42.0]);
+
+    let point = Point(1.0, 2.0, 3.0);
+    assert_eq!(*point.as_ref().1, 2.0);
+
+    let ptr = points.as_ptr();
+    assert_eq!(unsafe { ptr.add(1).read() }, Point(42.0, 1.0, 2.0));
+}
+
+#[test]
+fn iter() {
+    let mut points = PointVec::new();
+    points.push(Point(0.0, 1.0, 2.0));
+    points.push(Point(3.0, 4.0, 5.0));
+
+    let sums = points.iter().map(|p| p.0 + p.1 + p.2).collect::<Vec<_>>();
+    assert_eq!(sums, [3.0, 12.0]);
+
+    for point in points.iter_mut() {
+        *point.1 = 0.0;
+    }
+    assert_eq!(points.1, [0.0, 0.0]);
+
+    for (x, z) in soa_zip!(&mut points, [0, mut 2]) {
+        *z += x;
+    }
+    assert_eq!(points.2, [2.0, 8.0]);
+
+    for (z, y) in soa_zip!(&points, [2, 1]) {
+        assert!(*z > *y);
+    }
+}
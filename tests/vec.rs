@@ -159,3 +159,190 @@ fn retain() {
     assert_eq!(particles.index(0).name, "Cl");
     assert_eq!(particles.index(1).name, "C");
 }
+
+#[test]
+fn extend() {
+    let mut particles = ParticleVec::new();
+    particles.push(Particle::new(String::from("Cl"), 0.0));
+    particles.extend(vec![
+        Particle::new(String::from("Na"), 1.0),
+        Particle::new(String::from("Zn"), 2.0),
+    ]);
+    assert_eq!(particles.len(), 3);
+    assert_eq!(particles.name, ["Cl", "Na", "Zn"]);
+    assert_eq!(particles.mass, [0.0, 1.0, 2.0]);
+
+    let mut other = ParticleVec::new();
+    other.extend(particles.iter().filter(|particle| *particle.mass > 0.5));
+    assert_eq!(other.name, ["Na", "Zn"]);
+    assert_eq!(other.mass, [1.0, 2.0]);
+
+    other.extend_from_slice(particles.slice(0..2));
+    assert_eq!(other.name, ["Na", "Zn", "Cl", "Na"]);
+    assert_eq!(other.mass, [1.0, 2.0, 0.0, 1.0]);
+}
+
+#[test]
+fn drain() {
+    let mut particles = ParticleVec::new();
+    particles.push(Particle::new(String::from("Cl"), 0.0));
+    particles.push(Particle::new(String::from("Na"), 1.0));
+    particles.push(Particle::new(String::from("Zn"), 2.0));
+    particles.push(Particle::new(String::from("Mg"), 3.0));
+
+    let drained = particles.drain(1..3).collect::<Vec<_>>();
+    assert_eq!(drained, [
+        Particle::new(String::from("Na"), 1.0),
+        Particle::new(String::from("Zn"), 2.0),
+    ]);
+    assert_eq!(particles.name, ["Cl", "Mg"]);
+    assert_eq!(particles.mass, [0.0, 3.0]);
+
+    // dropping the iterator early still removes the whole range
+    let mut drain = particles.drain(..);
+    assert_eq!(drain.len(), 2);
+    assert_eq!(drain.next_back(), Some(Particle::new(String::from("Mg"), 3.0)));
+    drop(drain);
+    assert!(particles.is_empty());
+    assert!(particles.name.is_empty() && particles.mass.is_empty());
+
+    particles.push(Particle::new(String::from("Cl"), 0.0));
+    particles.push(Particle::new(String::from("Na"), 1.0));
+    std::mem::forget(particles.drain(..=0));
+    assert_eq!(particles.name.len(), particles.mass.len());
+}
+
+#[test]
+fn dedup() {
+    let mut particles = ParticleVec::new();
+    particles.push(Particle::new(String::from("Cl"), 0.0));
+    particles.push(Particle::new(String::from("Cl"), 1.0));
+    particles.push(Particle::new(String::from("Na"), 2.0));
+    particles.push(Particle::new(String::from("Cl"), 3.0));
+    particles.push(Particle::new(String::from("Zn"), 4.0));
+    particles.push(Particle::new(String::from("Zn"), 5.0));
+
+    particles.dedup_by(|a, b| a.name == b.name);
+    assert_eq!(particles.name, ["Cl", "Na", "Cl", "Zn"]);
+    assert_eq!(particles.mass, [0.0, 2.0, 3.0, 4.0]);
+
+    particles.dedup_by_key(|particle| *particle.mass < 2.5);
+    assert_eq!(particles.name, ["Cl", "Cl"]);
+    assert_eq!(particles.mass, [0.0, 3.0]);
+
+    // the arguments are mutable references to the elements
+    particles.dedup_by(|a, b| {
+        *b.mass += *a.mass;
+        true
+    });
+    assert_eq!(particles.name, ["Cl"]);
+    assert_eq!(particles.mass, [3.0]);
+}
+
+#[test]
+fn retain_mut() {
+    let mut particles = ParticleVec::new();
+    particles.push(Particle::new(String::from("Cl"), 0.0));
+    particles.push(Particle::new(String::from("Na"), 1.0));
+    particles.push(Particle::new(String::from("Zn"), 2.0));
+
+    particles.retain_mut(|particle| {
+        *particle.mass += 1.0;
+        particle.name != "Na"
+    });
+    assert_eq!(particles.name, ["Cl", "Zn"]);
+    assert_eq!(particles.mass, [1.0, 3.0]);
+
+    particles.retain_mask(&[false, true]);
+    assert_eq!(particles.name, ["Zn"]);
+    assert_eq!(particles.mass, [3.0]);
+}
+
+#[test]
+fn retain_by_field() {
+    let mut particles = ParticleVec::new();
+    particles.push(Particle::new(String::from("Cl"), 0.0));
+    particles.push(Particle::new(String::from("Na"), 1.0));
+    particles.push(Particle::new(String::from("Zn"), 2.0));
+    particles.push(Particle::new(String::from("C"), 3.0));
+
+    particles.retain_by_mass(|&mass| mass != 1.0);
+    assert_eq!(particles.name, ["Cl", "Zn", "C"]);
+    assert_eq!(particles.mass, [0.0, 2.0, 3.0]);
+
+    particles.retain_by_name(|name| name.starts_with('C'));
+    assert_eq!(particles.name, ["Cl", "C"]);
+    assert_eq!(particles.mass, [0.0, 3.0]);
+}
+
+#[derive(StructOfArray)]
+pub struct PanicOnDrop {
+    value: u32,
+    guard: Option<PanicGuard>,
+}
+
+pub struct PanicGuard;
+
+impl Drop for PanicGuard {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            panic!("dropping a PanicGuard");
+        }
+    }
+}
+
+#[test]
+fn retain_panic_safety() {
+    let mut vec = PanicOnDropVec::new();
+    vec.push(PanicOnDrop { value: 0, guard: None });
+    vec.push(PanicOnDrop { value: 1, guard: Some(PanicGuard) });
+    vec.push(PanicOnDrop { value: 2, guard: None });
+
+    // the predicate panics: nothing is removed
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        vec.retain(|item| if *item.value == 2 { panic!() } else { true });
+    }));
+    assert!(result.is_err());
+    assert_eq!(vec.value, [0, 1, 2]);
+
+    // dropping an element panics: only the elements retained before it are
+    // kept, with all the fields of the same element
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        vec.retain(|item| *item.value != 1);
+    }));
+    assert!(result.is_err());
+    assert_eq!(vec.len(), 1);
+    assert_eq!(vec.value, [0]);
+    assert_eq!(vec.guard.len(), 1);
+    assert!(vec.guard[0].is_none());
+}
+
+#[test]
+fn from_columns() {
+    let names = vec![String::from("Na"), String::from("Cl")];
+    let particles = ParticleVec::from_columns(names.clone(), vec![22.99, 35.45]).unwrap();
+    assert_eq!(particles.len(), 2);
+    assert_eq!(particles.name, names);
+    assert_eq!(particles.mass, [22.99, 35.45]);
+
+    let (name, mass) = particles.into_columns();
+    assert_eq!(name, names);
+    assert_eq!(mass, [22.99, 35.45]);
+
+    let error = ParticleVec::from_columns(names, vec![1.0]).unwrap_err();
+    assert_eq!(error, soa_derive::LengthMismatch { field: "mass", len: 1, expected: 2 });
+    assert_eq!(
+        error.to_string(),
+        "all the fields must have the same length, but `mass` has 1 elements instead of 2"
+    );
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "the `mass` field has a different length than the others")]
+fn len_mismatch() {
+    let mut particles = ParticleVec::new();
+    particles.push(Particle::new(String::from("Na"), 56.0));
+    particles.mass.push(42.0);
+    let _ = particles.len();
+}